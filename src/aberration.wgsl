@@ -0,0 +1,13 @@
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let amount = settings.values.x;
+
+    // Grows from the center to `amount` pixels in the corners
+    let position = frame_position(in.position);
+    let offset = (frame_uv(in.position) * 2.0 - 1.0) * amount / sqrt(2.0);
+
+    let color = textureLoad(source, vec2u(in.position.xy), 0);
+    let red = sample_frame(position + offset).r;
+    let blue = sample_frame(position - offset).b;
+    return vec4f(red, color.g, blue, color.a);
+}
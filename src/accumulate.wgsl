@@ -0,0 +1,16 @@
+struct Settings {
+    weight: f32,
+    // Non zero to clamp the sub-frames to 1, so highlights do not smear
+    clamp: u32,
+}
+
+@group(0) @binding(2) var<uniform> settings: Settings;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    var color = textureLoad(source, vec2u(in.position.xy), 0);
+    if settings.clamp != 0u {
+        color = min(color, vec4f(1.0));
+    }
+    return color * settings.weight;
+}
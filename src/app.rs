@@ -1,4 +1,13 @@
-use crate::pipeline::Pipeline;
+use crate::audio::AudioFrame;
+use crate::effects::Frame;
+use crate::error::{DeviceErrors, Result, ShaderArtError};
+use crate::gpu::GpuOptions;
+use crate::layer::Finish;
+use crate::pipeline::{Params, Pipeline, Scene, PASS_FORMAT};
+use crate::pixels;
+use crate::playlist::{Playlist, PlaylistState, Transition};
+use crate::post::{self, Downsample, PostPass};
+use crate::resolution;
 use wgpu::MemoryHints;
 use winit::window::Window;
 
@@ -15,48 +24,153 @@ pub struct Surface<'a> {
     pub surface_size: winit::dpi::PhysicalSize<u32>,
 }
 
+/// How the frames are rendered without a window.
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    /// Frames are rendered at `ssaa` times the resolution then downsampled.
+    pub ssaa: u32,
+    /// Number of sub-frames averaged into each frame, 1 disables motion blur.
+    pub motion_blur: u32,
+    /// Fraction of the frame interval covered by the sub-frames, in degrees.
+    pub shutter_angle: f32,
+    /// Frame rate of the animation, it spaces the frames in shader time.
+    pub fps: f32,
+    /// Format of the frames read back, `Rgba16Float` and `Rgba32Float` keep
+    /// the values above 1.
+    pub format: wgpu::TextureFormat,
+    pub gpu: GpuOptions,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            ssaa: 1,
+            motion_blur: 1,
+            shutter_angle: 180.0,
+            fps: 20.0,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            gpu: GpuOptions::default(),
+        }
+    }
+}
+
+struct Supersampling {
+    factor: u32,
+    target: wgpu::TextureView,
+    downsample: Downsample,
+}
+
+struct MotionBlur {
+    samples: u32,
+    shutter_angle: f32,
+    // Sub-frame and running average, both in linear light
+    frame: wgpu::TextureView,
+    accumulation: wgpu::TextureView,
+    accumulate: PostPass,
+    resolve: PostPass,
+}
+
+/// Animation state kept when the app is recreated after a device loss.
+#[derive(Clone, Debug)]
+pub struct State {
+    time: f32,
+    speed: f32,
+    params: Params,
+    playlist: Option<PlaylistState>,
+}
+
 pub struct App<'a> {
     device: wgpu::Device,
     queue: wgpu::Queue,
     pub surface: Option<Surface<'a>>,
     pub pipeline: Pipeline,
+    /// Shown instead of the pipeline, which still keeps the time.
+    pub playlist: Option<Playlist>,
     pub output_buffer: Option<wgpu::Buffer>,
     pub texture_size: Option<TextureSize>,
+    output_format: wgpu::TextureFormat,
+    supersampling: Option<Supersampling>,
+    motion_blur: Option<MotionBlur>,
+    finish: Option<Finish>,
+    // Frame rate of the animation, the motion blur spreads over a frame
+    fps: f32,
+    // Size of the whole image and position of the rendered region, for the effects
+    frame: Frame,
+    errors: DeviceErrors,
 }
 
 impl<'a> App<'a> {
-    pub async fn new_without_window(speed: u8, resolution: [u16; 2]) -> App<'a> {
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            dx12_shader_compiler: Default::default(),
-            flags: Default::default(),
-            gles_minor_version: Default::default(),
-        });
+    pub async fn new_without_window(
+        speed: f32,
+        resolution: [u32; 2],
+        scene: &Scene,
+        options: &RenderOptions,
+    ) -> Result<App<'a>> {
+        let instance = options.gpu.instance();
+        let adapter = options.gpu.adapter(&instance, None).await?;
+
+        let limits = adapter.limits();
+        let max_dimension = limits.max_texture_dimension_2d;
+        let factor = options.ssaa.max(1);
+        let render_size = resolution.map(|side| side.saturating_mul(factor));
+        resolution::check_limit(render_size, max_dimension)?;
+
+        if scene.compute.is_some()
+            && !adapter
+                .get_downlevel_capabilities()
+                .flags
+                .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+        {
+            return Err(ShaderArtError::Unsupported(
+                "The GPU does not support compute shaders".into(),
+            ));
+        }
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: None,
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
+        let format_features = adapter.get_texture_format_features(options.format);
+        if !pixels::FORMATS.contains(&options.format)
+            || !format_features
+                .allowed_usages
+                .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+        {
+            return Err(ShaderArtError::UnsupportedFormat(options.format));
+        }
+
+        let output_format = options.format;
+        let pixel_size = output_format
+            .block_copy_size(None)
+            .ok_or(ShaderArtError::UnsupportedFormat(output_format))?;
+
+        let padded_bytes_per_row = Self::calculate_padding(resolution[0], pixel_size);
+        let output_buffer_size = padded_bytes_per_row * resolution[1] as wgpu::BufferAddress;
+        if output_buffer_size > limits.max_buffer_size {
+            return Err(ShaderArtError::Unsupported(format!(
+                "The {}x{} frames of {} bytes exceed the maximum buffer size {} of the GPU",
+                resolution[0], resolution[1], output_buffer_size, limits.max_buffer_size
+            )));
+        }
 
         let (device, queue) = adapter
-            .request_device(&Default::default(), None)
-            .await
-            .unwrap();
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    required_features: wgpu::Features::empty(),
+                    required_limits: wgpu::Limits {
+                        max_texture_dimension_2d: max_dimension,
+                        max_buffer_size: limits.max_buffer_size,
+                        ..Default::default()
+                    },
+                    label: None,
+                    memory_hints: MemoryHints::default(),
+                },
+                None,
+            )
+            .await?;
+        let errors = DeviceErrors::watch(&device);
 
         let texture_size = TextureSize {
-            width: resolution[0].into(),
-            height: resolution[1].into(),
+            width: resolution[0],
+            height: resolution[1],
         };
 
-        let padded_bytes_per_row = Self::calculate_padding(texture_size.width);
-
-        let output_buffer_size =
-            (padded_bytes_per_row * texture_size.height) as wgpu::BufferAddress;
-
         let output_buffer_desc = wgpu::BufferDescriptor {
             size: output_buffer_size,
             usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
@@ -66,43 +180,91 @@ impl<'a> App<'a> {
 
         let output_buffer = device.create_buffer(&output_buffer_desc);
 
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let finish = Finish::new(&device, &queue, resolution, output_format, scene);
+
+        // Format of the frames once blurred
+        let blurred_format = if finish.is_some() {
+            PASS_FORMAT
+        } else {
+            output_format
+        };
+
+        // Samples can be clamped when the output clips them anyway
+        let clamp = !is_float(output_format) && finish.is_none();
+
+        let motion_blur = (options.motion_blur > 1).then(|| MotionBlur {
+            samples: options.motion_blur,
+            shutter_angle: options.shutter_angle,
+            frame: post::create_target(&device, resolution, PASS_FORMAT),
+            accumulation: post::create_target(&device, resolution, PASS_FORMAT),
+            accumulate: post::accumulate(
+                &device,
+                PASS_FORMAT,
+                1.0 / options.motion_blur as f32,
+                clamp,
+            ),
+            resolve: post::copy(&device, blurred_format),
+        });
+
+        // Format of the frames before they are blurred
+        let frame_format = if motion_blur.is_some() {
+            PASS_FORMAT
+        } else {
+            blurred_format
+        };
+
+        let supersampling = (factor > 1).then(|| Supersampling {
+            factor,
+            target: post::create_target(&device, render_size, PASS_FORMAT),
+            downsample: Downsample::new(&device, resolution, frame_format, factor, clamp),
+        });
+
         let pipeline = Pipeline::new(
             &device,
-            wgpu::TextureFormat::Rgba8UnormSrgb,
-            [texture_size.height as f32, texture_size.width as f32],
+            &queue,
+            if supersampling.is_some() {
+                PASS_FORMAT
+            } else {
+                frame_format
+            },
+            [render_size[1] as f32, render_size[0] as f32],
             speed,
+            scene,
         );
+        let pipeline = creation_result(&device, pipeline).await?;
 
-        Self {
+        Ok(Self {
             device,
             queue,
             surface: None,
             pipeline,
+            playlist: None,
             output_buffer: Some(output_buffer),
             texture_size: Some(texture_size),
-        }
+            output_format,
+            supersampling,
+            motion_blur,
+            finish,
+            fps: options.fps,
+            frame: Frame::new(resolution, [0, 0], 0.0),
+            errors,
+        })
     }
 
-    pub async fn new_with_window(window: &'a Window, speed: u8) -> App<'a> {
+    pub async fn new_with_window(
+        window: &'a Window,
+        speed: f32,
+        scene: &Scene,
+        vsync: bool,
+        gpu: &GpuOptions,
+    ) -> Result<App<'a>> {
         let size = window.inner_size();
 
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            dx12_shader_compiler: Default::default(),
-            flags: Default::default(),
-            gles_minor_version: Default::default(),
-        });
-
-        let surface = instance.create_surface(window).unwrap();
-
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
+        let instance = gpu.instance();
+        let surface = instance.create_surface(window)?;
+        let adapter = gpu.adapter(&instance, Some(&surface)).await?;
 
         let (device, queue) = adapter
             .request_device(
@@ -114,8 +276,8 @@ impl<'a> App<'a> {
                 },
                 None,
             )
-            .await
-            .unwrap();
+            .await?;
+        let errors = DeviceErrors::watch(&device);
 
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps
@@ -123,14 +285,21 @@ impl<'a> App<'a> {
             .iter()
             .copied()
             .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
+            .or(surface_caps.formats.first().copied())
+            .ok_or_else(|| {
+                ShaderArtError::Unsupported("The adapter can not present to the window".into())
+            })?;
 
         let surface_config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
             width: size.width,
             height: size.height,
-            present_mode: surface_caps.present_modes[0],
+            present_mode: if vsync {
+                wgpu::PresentMode::AutoVsync
+            } else {
+                wgpu::PresentMode::AutoNoVsync
+            },
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
@@ -138,12 +307,29 @@ impl<'a> App<'a> {
 
         surface.configure(&device, &surface_config);
 
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let finish = Finish::new(
+            &device,
+            &queue,
+            [size.width, size.height],
+            surface_format,
+            scene,
+        );
+
         let pipeline = Pipeline::new(
             &device,
-            surface_config.format,
+            &queue,
+            if finish.is_some() {
+                PASS_FORMAT
+            } else {
+                surface_format
+            },
             [size.height as f32, size.width as f32],
             speed,
+            scene,
         );
+        let pipeline = creation_result(&device, pipeline).await?;
 
         let app_surface = Surface {
             window,
@@ -152,60 +338,238 @@ impl<'a> App<'a> {
             surface_size: size,
         };
 
-        Self {
+        Ok(Self {
             device,
             queue,
             surface: Some(app_surface),
             pipeline,
+            playlist: None,
             output_buffer: None,
             texture_size: None,
-        }
+            output_format: surface_format,
+            supersampling: None,
+            motion_blur: None,
+            finish,
+            fps: RenderOptions::default().fps,
+            frame: Frame::new([size.width, size.height], [0, 0], 0.0),
+            errors,
+        })
     }
 
     // https://en.wikipedia.org/wiki/Data_structure_alignment#Computing_padding
-    pub fn calculate_padding(size: u32) -> u32 {
-        let pixel_size = std::mem::size_of::<[u8; 4]>() as u32;
-        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
-        let unpadded_bytes_per_row = pixel_size * size;
+    pub fn calculate_padding(size: u32, pixel_size: u32) -> wgpu::BufferAddress {
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as wgpu::BufferAddress;
+        let unpadded_bytes_per_row =
+            pixel_size as wgpu::BufferAddress * size as wgpu::BufferAddress;
         let padding = (align - unpadded_bytes_per_row % align) % align;
         unpadded_bytes_per_row + padding
     }
 
-    pub fn window(&self) -> &Window {
-        self.surface.as_ref().unwrap().window
+    /// Format of the frames returned by `run`.
+    pub fn output_format(&self) -> wgpu::TextureFormat {
+        self.output_format
+    }
+
+    /// The window rendered into, `None` without one.
+    pub fn window(&self) -> Option<&Window> {
+        self.surface.as_ref().map(|surface| surface.window)
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
-            let app_surface = self.surface.as_mut().unwrap();
+            let Some(app_surface) = self.surface.as_mut() else {
+                return;
+            };
             app_surface.surface_size = new_size;
             app_surface.surface_config.width = new_size.width;
             app_surface.surface_config.height = new_size.height;
             app_surface
                 .surface
                 .configure(&self.device, &app_surface.surface_config);
-            self.pipeline
-                .common
-                .update_dimensions([new_size.height as f32, new_size.width as f32]);
-            self.queue.write_buffer(
-                &self.pipeline.uniform_buffer,
-                0,
-                bytemuck::cast_slice(&[self.pipeline.common]),
+            self.pipeline.resize(
+                &self.device,
+                &self.queue,
+                [new_size.height as f32, new_size.width as f32],
             );
+            let size = [new_size.width, new_size.height];
+            if let Some(finish) = &mut self.finish {
+                finish.resize(&self.device, size);
+            }
+            if let Some(playlist) = &mut self.playlist {
+                playlist.resize(&self.device, &self.queue, size);
+            }
+            self.frame = Frame::new(size, [0, 0], self.frame.time);
+            self.write_uniforms();
         }
     }
 
+    fn write_uniforms(&mut self) {
+        self.pipeline.write_uniforms(&self.queue);
+        if let Some(finish) = &self.finish {
+            self.frame.time = self.pipeline.common.time;
+            finish.write_uniforms(&self.queue, self.frame);
+        }
+        if let Some(playlist) = &mut self.playlist {
+            playlist.update(&self.queue, self.pipeline.common.time);
+        }
+    }
+
+    /// Shows `scenes` in turn in the window, they are all built now.
+    pub async fn set_playlist(&mut self, scenes: &[Scene], transition: Transition) -> Result<()> {
+        let app_surface = self.surface.as_ref().ok_or_else(|| {
+            ShaderArtError::Project("Only an app with a window shows a playlist".into())
+        })?;
+        let size = [
+            app_surface.surface_size.width,
+            app_surface.surface_size.height,
+        ];
+
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let playlist = Playlist::new(
+            &self.device,
+            &self.queue,
+            self.output_format,
+            size,
+            scenes,
+            transition,
+        );
+        let mut playlist = creation_result(&self.device, playlist).await?;
+        playlist.update(&self.queue, self.pipeline.common.time);
+        self.playlist = Some(playlist);
+
+        Ok(())
+    }
+
+    /// Rebuilds the passes of the window for `scene`, keeping the time, the
+    /// speed and the parameters. Nothing changes when they fail to build.
+    pub async fn set_scene(&mut self, scene: &Scene) -> Result<()> {
+        let app_surface = self.surface.as_ref().ok_or_else(|| {
+            ShaderArtError::Project("Only an app with a window changes its scene".into())
+        })?;
+        let size = [
+            app_surface.surface_size.width,
+            app_surface.surface_size.height,
+        ];
+
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let finish = Finish::new(&self.device, &self.queue, size, self.output_format, scene);
+        let pipeline = Pipeline::new(
+            &self.device,
+            &self.queue,
+            if finish.is_some() {
+                PASS_FORMAT
+            } else {
+                self.output_format
+            },
+            [size[1] as f32, size[0] as f32],
+            1.0,
+            scene,
+        );
+        let mut pipeline = creation_result(&self.device, pipeline).await?;
+
+        pipeline.common = self.pipeline.common;
+        pipeline.params = self.pipeline.params;
+        self.pipeline = pipeline;
+        self.finish = finish;
+        self.write_uniforms();
+
+        Ok(())
+    }
+
+    pub fn state(&self) -> State {
+        State {
+            time: self.pipeline.common.time,
+            speed: self.pipeline.common.speed(),
+            params: self.pipeline.params,
+            playlist: self.playlist.as_ref().map(Playlist::state),
+        }
+    }
+
+    pub fn restore(&mut self, state: State) {
+        self.pipeline.params = state.params;
+        self.pipeline.common.set_speed(state.speed);
+        if let (Some(playlist), Some(playlist_state)) = (&mut self.playlist, state.playlist) {
+            playlist.restore(&self.queue, playlist_state);
+        }
+        self.set_time(state.time);
+    }
+
     pub fn update(&mut self) {
         self.pipeline.common.update_time();
-        self.queue.write_buffer(
-            &self.pipeline.uniform_buffer,
-            0,
-            bytemuck::cast_slice(&[self.pipeline.common]),
+        self.write_uniforms();
+    }
+
+    pub fn set_time(&mut self, time: f32) {
+        self.pipeline.common.time = time;
+        self.write_uniforms();
+    }
+
+    /// Sets a parameter of the scene and of the playlist entries, returns
+    /// `false` if none has it.
+    pub fn set_param(&mut self, name: &str, value: f32) -> bool {
+        let mut found = self.pipeline.set_param(name, value);
+        if let Some(playlist) = &mut self.playlist {
+            found |= playlist.set_param(&self.queue, name, value);
+        }
+        self.write_uniforms();
+        found
+    }
+
+    /// Shader time between two frames at the frame rate of the options.
+    pub fn frame_interval(&self) -> f32 {
+        self.pipeline.common.frame_interval(self.fps)
+    }
+
+    /// Frame rate of the animation, it changes the frame interval.
+    pub fn set_fps(&mut self, fps: f32) {
+        self.fps = fps;
+    }
+
+    /// Speed factor of the animation, from the next frame on.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.pipeline.common.set_speed(speed);
+        self.write_uniforms();
+    }
+
+    /// Shows `frame` of the audio track from the next frame on.
+    pub fn set_audio(&mut self, frame: &AudioFrame) {
+        self.pipeline.set_audio(&self.queue, frame);
+        if let Some(playlist) = &mut self.playlist {
+            playlist.set_audio(&self.queue, frame);
+        }
+        self.write_uniforms();
+    }
+
+    /// Renders the tile at `origin` of an image of `resolution` on the next `run`.
+    pub fn set_tile(&mut self, resolution: [u32; 2], origin: [u32; 2]) -> Result<()> {
+        let texture_size = self.texture_size.as_ref().ok_or_else(headless)?;
+        // Each tile would advance the storage buffers
+        if self.pipeline.has_compute() && [texture_size.width, texture_size.height] != resolution {
+            return Err(ShaderArtError::Unsupported(
+                "Compute shaders render the whole image at once, it can not be tiled".into(),
+            ));
+        }
+        let factor = self.supersampling.as_ref().map_or(1, |s| s.factor);
+        self.pipeline.common.update_dimensions([
+            (resolution[1] * factor) as f32,
+            (resolution[0] * factor) as f32,
+        ]);
+        self.pipeline.common.update_viewport(
+            origin.map(|v| v * factor),
+            [texture_size.width * factor, texture_size.height * factor],
         );
+        self.frame = Frame::new(resolution, origin, self.frame.time);
+        self.write_uniforms();
+
+        Ok(())
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let app_surface = self.surface.as_ref().unwrap();
+    pub fn render(&mut self) -> Result<()> {
+        let app_surface = self.surface.as_ref().ok_or_else(|| {
+            ShaderArtError::Project("Only an app with a window can present frames".into())
+        })?;
+        // A lost device fails to give a texture, it is only known from the callback
+        self.errors.check()?;
         let output = app_surface.surface.get_current_texture()?;
         let view = output
             .texture
@@ -215,49 +579,52 @@ impl<'a> App<'a> {
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-
-            render_pass.set_pipeline(&self.pipeline.render_pipeline);
-            render_pass.set_vertex_buffer(0, self.pipeline.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(
-                self.pipeline.index_buffer.slice(..),
-                wgpu::IndexFormat::Uint16,
-            );
-            render_pass.set_bind_group(0, &self.pipeline.shader_binding_group, &[]);
-            render_pass.draw_indexed(0..self.pipeline.num_indices, 0, 0..1);
+        if self.playlist.is_none() {
+            self.pipeline.encode_compute(&mut encoder);
+        }
+        match (&self.playlist, &self.finish) {
+            (Some(playlist), _) => playlist.encode(&self.device, &mut encoder, &view),
+            (None, Some(finish)) => {
+                self.pipeline.encode(&mut encoder, finish.target());
+                finish.encode(&self.device, &mut encoder, &view);
+            }
+            (None, None) => self.pipeline.encode(&mut encoder, &view),
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
         output.present();
 
-        Ok(())
+        self.errors.check()
     }
 
-    pub async fn run(&mut self, frames: &mut Vec<Vec<u8>>) {
-        let texture_size = self.texture_size.as_ref().unwrap();
+    // Renders the art into `target`, downsampling it when supersampling.
+    fn encode_frame(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        match &self.supersampling {
+            Some(supersampling) => {
+                self.pipeline.encode(encoder, &supersampling.target);
+                supersampling.downsample.encode(
+                    &self.device,
+                    encoder,
+                    &supersampling.target,
+                    target,
+                );
+            }
+            None => self.pipeline.encode(encoder, target),
+        }
+    }
 
-        let pixel_size = std::mem::size_of::<[u8; 4]>() as u32;
-        let unpadded_bytes_per_row = pixel_size * texture_size.width;
-        let padded_bytes_per_row = Self::calculate_padding(texture_size.width);
+    /// Renders a frame and appends its pixels, tightly packed, to `frames`.
+    pub async fn run(&mut self, frames: &mut Vec<Vec<u8>>) -> Result<()> {
+        let texture_size = self.texture_size.as_ref().ok_or_else(headless)?;
+        let output_buffer = self.output_buffer.as_ref().ok_or_else(headless)?;
+
+        let pixel_size = self
+            .output_format
+            .block_copy_size(None)
+            .ok_or(ShaderArtError::UnsupportedFormat(self.output_format))?;
+        let unpadded_bytes_per_row = pixel_size as usize * texture_size.width as usize;
+        // Rows are at most `max_texture_dimension_2d` pixels of 16 bytes
+        let padded_bytes_per_row = Self::calculate_padding(texture_size.width, pixel_size) as u32;
 
         let texture_desc = wgpu::TextureDescriptor {
             size: wgpu::Extent3d {
@@ -268,7 +635,7 @@ impl<'a> App<'a> {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            format: self.output_format,
             usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::RENDER_ATTACHMENT,
             label: None,
             view_formats: &[],
@@ -277,40 +644,74 @@ impl<'a> App<'a> {
         let texture = self.device.create_texture(&texture_desc);
         let texture_view = texture.create_view(&Default::default());
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        // Where the frame goes before the effects and the grading
+        let frame_view = self
+            .finish
+            .as_ref()
+            .map_or(&texture_view, |finish| finish.target());
+
+        // Once per frame at its time, the supersamples and sub-frames read it
+        if self.pipeline.has_compute() {
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            self.pipeline.encode_compute(&mut encoder);
+            self.queue.submit(std::iter::once(encoder.finish()));
+        }
 
-        {
-            let render_pass_desc = wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &texture_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            };
-            let mut render_pass = encoder.begin_render_pass(&render_pass_desc);
+        let mut encoder = match &self.motion_blur {
+            Some(motion_blur) => {
+                // Sub-frames are evenly spaced over the shutter interval,
+                // centered on the frame time. 360° covers a whole frame.
+                let time = self.pipeline.common.time;
+                let shutter = self.frame_interval() * motion_blur.shutter_angle / 360.0;
+
+                for i in 0..motion_blur.samples {
+                    let offset = (i as f32 + 0.5) / motion_blur.samples as f32 - 0.5;
+                    self.pipeline.common.time = time + shutter * offset;
+                    self.pipeline.write_uniforms(&self.queue);
+
+                    let mut encoder = self
+                        .device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                    if i == 0 {
+                        post::clear(&mut encoder, &motion_blur.accumulation);
+                    }
+                    self.encode_frame(&mut encoder, &motion_blur.frame);
+                    motion_blur.accumulate.encode(
+                        &self.device,
+                        &mut encoder,
+                        &motion_blur.frame,
+                        &motion_blur.accumulation,
+                    );
+                    self.queue.submit(std::iter::once(encoder.finish()));
+                }
+
+                self.pipeline.common.time = time;
+                self.pipeline.write_uniforms(&self.queue);
+
+                let mut encoder = self
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                motion_blur.resolve.encode(
+                    &self.device,
+                    &mut encoder,
+                    &motion_blur.accumulation,
+                    frame_view,
+                );
+                encoder
+            }
+            None => {
+                let mut encoder = self
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                self.encode_frame(&mut encoder, frame_view);
+                encoder
+            }
+        };
 
-            render_pass.set_pipeline(&self.pipeline.render_pipeline);
-            render_pass.set_vertex_buffer(0, self.pipeline.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(
-                self.pipeline.index_buffer.slice(..),
-                wgpu::IndexFormat::Uint16,
-            );
-            render_pass.set_bind_group(0, &self.pipeline.shader_binding_group, &[]);
-            render_pass.draw_indexed(0..self.pipeline.num_indices, 0, 0..1);
+        if let Some(finish) = &self.finish {
+            finish.encode(&self.device, &mut encoder, &texture_view);
         }
 
         encoder.copy_texture_to_buffer(
@@ -321,11 +722,11 @@ impl<'a> App<'a> {
                 origin: wgpu::Origin3d::ZERO,
             },
             wgpu::ImageCopyBuffer {
-                buffer: self.output_buffer.as_ref().unwrap(),
+                buffer: output_buffer,
                 layout: wgpu::ImageDataLayout {
                     offset: 0,
                     bytes_per_row: Some(padded_bytes_per_row),
-                    rows_per_image: Some(texture_size.width),
+                    rows_per_image: Some(texture_size.height),
                 },
             },
             wgpu::Extent3d {
@@ -337,29 +738,55 @@ impl<'a> App<'a> {
 
         self.queue.submit(std::iter::once(encoder.finish()));
 
-        let output_buffer = self.output_buffer.as_mut().unwrap();
-
         let buffer_slice = output_buffer.slice(..);
 
         let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
         buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
-            tx.send(result).unwrap();
+            // The receiver only goes away when `run` is cancelled
+            let _ = tx.send(result);
         });
         self.device.poll(wgpu::Maintain::Wait);
 
-        match rx.receive().await {
+        let data = match rx.receive().await {
             Some(Ok(())) => {
                 let padded_data = buffer_slice.get_mapped_range();
                 let data = padded_data
                     .chunks(padded_bytes_per_row as _)
-                    .flat_map(|chunk| &chunk[..unpadded_bytes_per_row as _])
+                    .flat_map(|chunk| &chunk[..unpadded_bytes_per_row])
                     .copied()
                     .collect::<Vec<_>>();
                 drop(padded_data);
                 output_buffer.unmap();
-                frames.push(data);
+                data
             }
-            _ => eprintln!("Something went wrong"),
-        }
+            Some(Err(e)) => return Err(e.into()),
+            None => return Err(wgpu::BufferAsyncError.into()),
+        };
+
+        self.errors.check()?;
+        frames.push(data);
+
+        Ok(())
+    }
+}
+
+// Pops the error scope pushed before creating the pipelines.
+pub(crate) async fn creation_result<T>(device: &wgpu::Device, created: Result<T>) -> Result<T> {
+    let error = device.pop_error_scope().await;
+    let created = created?;
+    match error {
+        Some(error) => Err(ShaderArtError::Shader(error.to_string())),
+        None => Ok(created),
     }
 }
+
+pub(crate) fn headless() -> ShaderArtError {
+    ShaderArtError::Project("Only an app without a window renders frames to read back".into())
+}
+
+fn is_float(format: wgpu::TextureFormat) -> bool {
+    matches!(
+        format,
+        wgpu::TextureFormat::Rgba16Float | wgpu::TextureFormat::Rgba32Float
+    )
+}
@@ -0,0 +1,195 @@
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+
+/// Width of the audio texture: bins of the spectrum, then samples of the waveform.
+pub const AUDIO_SIZE: usize = 512;
+
+// Samples analyzed for each frame, centered on its time
+const WINDOW: usize = 2 * AUDIO_SIZE;
+
+// Range of the spectrum mapped to [0, 1], like the Web Audio analyser
+const MIN_DECIBELS: f32 = -100.0;
+const MAX_DECIBELS: f32 = -30.0;
+
+// Upper frequencies of the bass and the mid bands in Hz, the treble is above
+const BASS: f32 = 250.0;
+const MID: f32 = 4000.0;
+
+/// A decoded track, mixed down to mono.
+#[derive(Clone, Debug)]
+pub struct Track {
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+/// Energies of the bands of the spectrum and loudness, all in [0, 1].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Bands {
+    pub bass: f32,
+    pub mid: f32,
+    pub treble: f32,
+    /// RMS of the samples.
+    pub volume: f32,
+}
+
+/// What the shaders see of the track at a frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioFrame {
+    /// Decibels of the spectrum mapped to [0, 1], then the waveform around
+    /// 0.5, as the rows of the `audio` texture.
+    pub texture: [[u8; AUDIO_SIZE]; 2],
+    pub bands: Bands,
+}
+
+impl Default for AudioFrame {
+    // Silence
+    fn default() -> Self {
+        Self {
+            texture: [[0; AUDIO_SIZE], [128; AUDIO_SIZE]],
+            bands: Bands::default(),
+        }
+    }
+}
+
+impl Track {
+    /// Decodes a PCM or float WAV file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut reader = hound::WavReader::open(path)
+            .with_context(|| format!("Can not read the audio `{}`", path.display()))?;
+        let spec = reader.spec();
+        if spec.channels == 0 || spec.sample_rate == 0 {
+            bail!("The audio `{}` has no channel", path.display());
+        }
+
+        let interleaved = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<_>, _>>(),
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1i64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .samples::<i32>()
+                    .map(|sample| sample.map(|sample| sample as f32 * scale))
+                    .collect()
+            }
+        }
+        .with_context(|| format!("Can not decode the audio `{}`", path.display()))?;
+
+        let channels = spec.channels as usize;
+        let samples = interleaved
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect();
+
+        Ok(Self {
+            sample_rate: spec.sample_rate,
+            samples,
+        })
+    }
+
+    /// Index of the sample shown with the frame `index` of a video at `fps`,
+    /// exact for any frame rate.
+    pub fn sample_at_frame(&self, index: usize, fps: u16) -> u64 {
+        index as u64 * self.sample_rate as u64 / fps.max(1) as u64
+    }
+
+    /// Index of the sample at `seconds` from the start.
+    pub fn sample_at(&self, seconds: f64) -> u64 {
+        (seconds.max(0.0) * self.sample_rate as f64).round() as u64
+    }
+
+    /// Analyzes the samples centered on `sample`, silence outside of the track.
+    pub fn analyze(&self, sample: u64) -> AudioFrame {
+        let start = sample as i64 - (WINDOW / 2) as i64;
+        let window = (0..WINDOW as i64)
+            .map(|i| {
+                usize::try_from(start + i)
+                    .ok()
+                    .and_then(|i| self.samples.get(i))
+                    .copied()
+                    .unwrap_or(0.0)
+            })
+            .collect::<Vec<_>>();
+
+        let mut texture = [[0; AUDIO_SIZE]; 2];
+
+        // Hann window, the amplitudes are normalized by its sum
+        let weights = (0..WINDOW)
+            .map(|i| 0.5 - 0.5 * (std::f32::consts::TAU * i as f32 / WINDOW as f32).cos())
+            .collect::<Vec<_>>();
+        let mut spectrum = window
+            .iter()
+            .zip(&weights)
+            .map(|(sample, weight)| [sample * weight, 0.0])
+            .collect::<Vec<_>>();
+        fft(&mut spectrum);
+
+        let norm = 2.0 / weights.iter().sum::<f32>();
+        let levels = spectrum[..AUDIO_SIZE]
+            .iter()
+            .map(|[re, im]| {
+                let decibels = 20.0 * (re.hypot(*im) * norm).max(1e-10).log10();
+                ((decibels - MIN_DECIBELS) / (MAX_DECIBELS - MIN_DECIBELS)).clamp(0.0, 1.0)
+            })
+            .collect::<Vec<_>>();
+        for (texel, level) in texture[0].iter_mut().zip(&levels) {
+            *texel = (level * 255.0).round() as u8;
+        }
+
+        // The samples around the frame time
+        for (texel, sample) in texture[1]
+            .iter_mut()
+            .zip(&window[AUDIO_SIZE / 2..AUDIO_SIZE / 2 + AUDIO_SIZE])
+        {
+            *texel = ((sample.clamp(-1.0, 1.0) * 0.5 + 0.5) * 255.0).round() as u8;
+        }
+
+        let bin = |frequency: f32| {
+            ((frequency * WINDOW as f32 / self.sample_rate as f32) as usize).min(AUDIO_SIZE)
+        };
+        let band = |bins: std::ops::Range<usize>| {
+            if bins.is_empty() {
+                0.0
+            } else {
+                levels[bins.clone()].iter().sum::<f32>() / bins.len() as f32
+            }
+        };
+        // The first bin is the constant offset, not a sound
+        let bands = Bands {
+            bass: band(1..bin(BASS).max(2)),
+            mid: band(bin(BASS).max(2)..bin(MID)),
+            treble: band(bin(MID)..AUDIO_SIZE),
+            volume: (window.iter().map(|v| v * v).sum::<f32>() / WINDOW as f32).sqrt(),
+        };
+
+        AudioFrame { texture, bands }
+    }
+}
+
+// In place radix-2 FFT of complex values, the length is a power of 2
+fn fft(values: &mut [[f32; 2]]) {
+    let n = values.len();
+
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+
+    let mut size = 2;
+    while size <= n {
+        let angle = -std::f32::consts::TAU / size as f32;
+        for start in (0..n).step_by(size) {
+            for k in 0..size / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let [re, im] = values[start + k + size / 2];
+                let odd = [re * cos - im * sin, re * sin + im * cos];
+                let even = values[start + k];
+                values[start + k] = [even[0] + odd[0], even[1] + odd[1]];
+                values[start + k + size / 2] = [even[0] - odd[0], even[1] - odd[1]];
+            }
+        }
+        size *= 2;
+    }
+}
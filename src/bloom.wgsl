@@ -0,0 +1,9 @@
+@group(0) @binding(3) var bloom: texture_2d<f32>;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let intensity = settings.values.z;
+    let color = textureLoad(source, vec2u(in.position.xy), 0);
+    let glow = textureLoad(bloom, vec2u(in.position.xy), 0).rgb;
+    return vec4f(color.rgb + intensity * glow, color.a);
+}
@@ -0,0 +1,22 @@
+// Gaussian blur along `direction`, keeping only what exceeds the threshold.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let direction = settings.values.xy;
+    let radius = settings.values.z;
+    let threshold = settings.values.w;
+
+    let size = vec2i(textureDimensions(source));
+    let sigma = max(radius / 3.0, 0.001);
+    let samples = i32(ceil(radius));
+
+    var sum = vec3f(0.0);
+    var total = 0.0;
+    for (var i = -samples; i <= samples; i++) {
+        let position = clamp(vec2i(in.position.xy) + vec2i(direction) * i, vec2i(0), size - 1);
+        let color = textureLoad(source, position, 0).rgb;
+        let weight = exp(-f32(i * i) / (2.0 * sigma * sigma));
+        sum += weight * max(color - threshold, vec3f(0.0));
+        total += weight;
+    }
+    return vec4f(sum / total, 1.0);
+}
@@ -1,17 +1,568 @@
-use clap::{arg, Command};
+use crate::effects::Effect;
+use crate::gpu::{Backend, Power};
+use crate::grade::ToneMapping;
+use crate::presets;
+use crate::project::{OutputFormat, Precision, Project};
+use anyhow::{bail, Context, Result};
+use clap::parser::MatchesError;
+use clap::{arg, builder::PossibleValuesParser, Arg, ArgAction, ArgMatches, Command};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Preview,
+    Export,
+    Still,
+    Validate,
+    Serve,
+    Info,
+    List,
+    Adapters,
+}
 
 pub fn cli() -> Command {
     Command::new("shader-art")
         .about("Shader Art")
-        .arg(arg!(--save <filename>).help("Save the animation as GIF."))
+        .args(scene_args())
+        .args(finish_args())
+        .args(gpu_args())
         .arg(
-            arg!(--speed <speed>)
-                .help("The animation speed.")
-                .value_parser(clap::value_parser!(u8)),
+            arg!(--"dump-config")
+                .global(true)
+                .help("Print the effective configuration as TOML and exit."),
         )
+        // Flags of the flat invocation, kept as an alias of `preview` and `export`.
+        // The subcommands define their own, see `FLAT_ARGS`.
         .arg(
-            arg!(--resolution <resolution>)
-                .requires("save")
-                .help("The animation resolution."),
+            arg!(--save <filename>)
+                .help("Save the animation, the format is deduced from the extension.")
+                .action(ArgAction::Append)
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(resolution_arg())
+        .arg(aspect_arg())
+        .arg(duration_arg())
+        .arg(fps_arg())
+        .subcommand(
+            Command::new("preview")
+                .about("Render the animation in a window.")
+                .arg(arg!(--size <size>).help("The window size as `widthxheight`."))
+                .arg(
+                    arg!(--vsync <vsync>)
+                        .help("Synchronize the frames with the display.")
+                        .value_parser(clap::value_parser!(bool)),
+                )
+                .arg(arg!(--fullscreen).help("Open the window in fullscreen."))
+                .arg(
+                    arg!(--playlist <entry>)
+                        .help("Show a preset or a shader file in turn, repeat it to add entries.")
+                        .action(ArgAction::Append),
+                )
+                .arg(
+                    arg!(--interval <seconds>)
+                        .help("Switch to the next playlist entry after `seconds`, N and P switch anyway.")
+                        .value_parser(clap::value_parser!(f64)),
+                )
+                .arg(
+                    arg!(--crossfade <seconds>)
+                        .help("The length of the crossfade between playlist entries.")
+                        .value_parser(clap::value_parser!(f64)),
+                )
+                .arg(
+                    arg!(--osc <address>)
+                        .help("Listen to OSC messages on a UDP address like `0.0.0.0:9000`."),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Save the animation.")
+                .arg(
+                    arg!(<output> ...)
+                        .help("The output files, the format is deduced from the extension.")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(format_arg())
+                .arg(resolution_arg())
+                .arg(aspect_arg())
+                .arg(ssaa_arg())
+                .arg(precision_arg())
+                .arg(
+                    arg!(--"motion-blur" <samples>)
+                        .help("Average `samples` sub-frames into each frame.")
+                        .value_parser(clap::value_parser!(u32).range(1..=64)),
+                )
+                .arg(
+                    arg!(--"shutter-angle" <degrees>)
+                        .help("The part of the frame interval covered by the motion blur.")
+                        .value_parser(clap::value_parser!(f32)),
+                )
+                .arg(duration_arg())
+                .arg(fps_arg())
+                .arg(
+                    arg!(--frames <frames>)
+                        .help("The number of frames, takes precedence over the duration.")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    arg!(--quality <quality>)
+                        .help("The encoding quality, from 1 to 30.")
+                        .value_parser(clap::value_parser!(u8).range(1..=30)),
+                )
+                .arg(
+                    arg!(--"sample-rate" <hz>)
+                        .help("The sample rate of the WAV outputs.")
+                        .value_parser(clap::value_parser!(u32).range(1..=384000)),
+                ),
+        )
+        .subcommand(
+            Command::new("still")
+                .about("Save a single frame as an image.")
+                .arg(
+                    arg!(<output>)
+                        .help("The image file, the format is deduced from the extension.")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(format_arg())
+                .arg(resolution_arg())
+                .arg(aspect_arg())
+                .arg(ssaa_arg())
+                .arg(precision_arg())
+                .arg(
+                    arg!(--time <time>)
+                        .help("The shader time of the frame.")
+                        .value_parser(clap::value_parser!(f32)),
+                )
+                .arg(
+                    arg!(--"tile-size" <pixels>)
+                        .help("The largest side of the tiles for PNG and TIFF.")
+                        .value_parser(clap::value_parser!(u32).range(1..)),
+                ),
+        )
+        .subcommand(
+            Command::new("serve")
+                .about("Control a headless renderer and stream its frames over HTTP.")
+                .arg(
+                    arg!(--listen <address>)
+                        .help("The HTTP address, only local clients by default."),
+                )
+                .arg(
+                    arg!(--"output-dir" <dir>)
+                        .help("The directory the jobs write their outputs into.")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(resolution_arg())
+                .arg(aspect_arg())
+                .arg(fps_arg()),
         )
+        .subcommand(Command::new("validate").about("Check that the shaders compile."))
+        .subcommand(Command::new("info").about("Show the effective settings and the scene."))
+        .subcommand(Command::new("list").about("List the presets with their parameters."))
+        .subcommand(
+            Command::new("adapters").about("List the GPU adapters with their limits and features."),
+        )
+}
+
+// Arguments describing what is rendered, shared by every subcommand.
+fn scene_args() -> Vec<Arg> {
+    vec![
+        arg!(--project <path>)
+            .help("Load the settings from a project file.")
+            .value_parser(clap::value_parser!(PathBuf)),
+        arg!(--speed <speed>)
+            .help("The animation speed.")
+            .value_parser(clap::value_parser!(f32)),
+        arg!(--shader <path>)
+            .help("Render a shader file instead of the bundled one, repeat it to add passes.")
+            .action(ArgAction::Append)
+            .value_parser(clap::value_parser!(PathBuf)),
+        arg!(--preset <name>)
+            .help("Render a bundled preset instead of the default art, `list` shows them.")
+            .value_parser(PossibleValuesParser::new(
+                presets::PRESETS.iter().map(|preset| preset.name),
+            )),
+        arg!(--input <path>)
+            .help("Bind an image as a texture input, up to 4.")
+            .action(ArgAction::Append)
+            .value_parser(clap::value_parser!(PathBuf)),
+        arg!(--audio <path>)
+            .help("Analyze a WAV track at each frame, shaders read it from `audio` and `infos`.")
+            .value_parser(clap::value_parser!(PathBuf)),
+        arg!(--param <param>)
+            .help("Set a shader parameter as `name=value`.")
+            .action(ArgAction::Append),
+        arg!(--define <define>)
+            .help("Define a name for the shader preprocessor as `NAME` or `NAME=value`.")
+            .action(ArgAction::Append),
+        arg!(--sound <path>)
+            .help("Render a sound shader defining `mainSound` to the `.wav` outputs.")
+            .value_parser(clap::value_parser!(PathBuf)),
+        arg!(--compute <path>)
+            .help("Render a compute shader, the shader passes read its output as `previous_pass`.")
+            .value_parser(clap::value_parser!(PathBuf)),
+        arg!(--"workgroup-size" <size>)
+            .help("The workgroup size of the compute shader as `xxy`, 8x8 by default."),
+        arg!(--"storage-buffer" <bytes>)
+            .help("Add a storage buffer of `bytes` kept between the frames of the compute shader.")
+            .action(ArgAction::Append)
+            .value_parser(clap::value_parser!(u64)),
+    ]
+    .into_iter()
+    .map(|arg| arg.global(true))
+    .collect()
+}
+
+// Effects and color grading, applied identically to the preview and the exports.
+fn finish_args() -> Vec<Arg> {
+    vec![
+        arg!(--effect <effect>)
+            .help(
+                "Add a post effect as `name` or `name:key=value,...`, repeat it to chain them. \
+                 Effects: bloom, vignette, grain, chromatic-aberration, crt.",
+            )
+            .action(ArgAction::Append),
+        arg!(--"tone-mapping" <operator>)
+            .help("Compress the values above 1 instead of clipping them.")
+            .value_parser(clap::value_parser!(ToneMapping)),
+        arg!(--exposure <stops>)
+            .help("Brighten or darken the frames before the tone mapping.")
+            .allow_negative_numbers(true)
+            .value_parser(clap::value_parser!(f64)),
+        arg!(--gamma <gamma>)
+            .help("Gamma adjustment, above 1 brightens the midtones.")
+            .value_parser(clap::value_parser!(f64)),
+        arg!(--saturation <saturation>)
+            .help("0 is grayscale, 1 keeps the colors.")
+            .value_parser(clap::value_parser!(f64)),
+        arg!(--lut <path>)
+            .help("Apply a `.cube` 3D LUT.")
+            .value_parser(clap::value_parser!(PathBuf)),
+    ]
+    .into_iter()
+    .map(|arg| arg.global(true))
+    .collect()
+}
+
+// Adapter selection, the CI needs to pin the software one.
+fn gpu_args() -> Vec<Arg> {
+    vec![
+        arg!(--backend <backend>)
+            .help("Only use adapters of this backend.")
+            .value_parser(clap::value_parser!(Backend)),
+        arg!(--adapter <adapter>)
+            .help("The adapter index in the `adapters` list, or part of its name."),
+        arg!(--power <power>)
+            .help("Prefer the low power or the high performance adapter.")
+            .value_parser(clap::value_parser!(Power))
+            .conflicts_with("adapter"),
+        arg!(--software).help("Use the software adapter of the backend."),
+        arg!(--cpu)
+            .help("Render the exports on the CPU, without any GPU. Only the bundled shader."),
+    ]
+    .into_iter()
+    .map(|arg| arg.global(true))
+    .collect()
+}
+
+fn format_arg() -> Arg {
+    arg!(--format <format>)
+        .help("Force the format of the outputs.")
+        .value_parser(clap::value_parser!(OutputFormat))
+}
+
+fn resolution_arg() -> Arg {
+    arg!(--resolution <resolution>)
+        .help("The output resolution as `widthxheight`, `widthx?` or a preset like `1080p`.")
+}
+
+fn aspect_arg() -> Arg {
+    arg!(--aspect <ratio>)
+        .help("The aspect ratio used to complete `?` in the resolution, like `16:9`.")
+}
+
+fn duration_arg() -> Arg {
+    arg!(--duration <seconds>)
+        .help("The animation duration.")
+        .value_parser(clap::value_parser!(f64))
+}
+
+fn ssaa_arg() -> Arg {
+    arg!(--ssaa <factor>)
+        .help("Render at `factor` times the resolution and downsample, to smooth thin lines.")
+        .value_parser(clap::value_parser!(u32).range(1..=8))
+}
+
+fn precision_arg() -> Arg {
+    arg!(--precision <precision>)
+        .help("The precision of the EXR outputs.")
+        .value_parser(clap::value_parser!(Precision))
+}
+
+fn fps_arg() -> Arg {
+    arg!(--fps <fps>)
+        .help("The animation frame rate.")
+        .value_parser(clap::value_parser!(u16).range(1..))
+}
+
+// Not every subcommand defines every argument, the other errors are type
+// mismatches between the parsers and the readers.
+fn value<'a, T: Clone + Send + Sync + 'static>(matches: &'a ArgMatches, id: &str) -> Option<&'a T> {
+    defined(matches.try_get_one::<T>(id), id)
+}
+
+fn values<T: Clone + Send + Sync + 'static>(matches: &ArgMatches, id: &str) -> Option<Vec<T>> {
+    defined(matches.try_get_many::<T>(id), id).map(|values| values.cloned().collect())
+}
+
+fn defined<T>(value: Result<Option<T>, MatchesError>, id: &str) -> Option<T> {
+    match value {
+        Ok(value) => value,
+        Err(MatchesError::UnknownArgument { .. }) => None,
+        Err(error) => panic!("Invalid `--{}` reader: {}", id, error),
+    }
+}
+
+// Flags only read without a subcommand
+const FLAT_ARGS: &[&str] = &["save", "resolution", "aspect", "duration", "fps"];
+
+/// The selected subcommand and its matches, `None` for the flat invocation.
+pub fn mode(matches: &ArgMatches) -> Result<(Option<Mode>, &ArgMatches)> {
+    if let Some((name, _)) = matches.subcommand() {
+        if let Some(id) = FLAT_ARGS
+            .iter()
+            .find(|id| matches.value_source(id).is_some())
+        {
+            bail!(
+                "`--{}` is ignored before the `{}` subcommand, pass it after it",
+                id,
+                name
+            );
+        }
+    }
+
+    Ok(match matches.subcommand() {
+        Some(("preview", sub)) => (Some(Mode::Preview), sub),
+        Some(("export", sub)) => (Some(Mode::Export), sub),
+        Some(("still", sub)) => (Some(Mode::Still), sub),
+        Some(("serve", sub)) => (Some(Mode::Serve), sub),
+        Some(("validate", sub)) => (Some(Mode::Validate), sub),
+        Some(("info", sub)) => (Some(Mode::Info), sub),
+        Some(("list", sub)) => (Some(Mode::List), sub),
+        Some(("adapters", sub)) => (Some(Mode::Adapters), sub),
+        _ => (None, matches),
+    })
+}
+
+/// Builds the effective project: the project file if any, overridden by the flags.
+pub fn project(matches: &ArgMatches) -> Result<Project> {
+    let mut project = match value::<PathBuf>(matches, "project") {
+        Some(path) => Project::load(path)?,
+        None => Project::default(),
+    };
+
+    if let Some(outputs) = values::<PathBuf>(matches, "save") {
+        project.outputs = outputs;
+    }
+
+    if let Some(outputs) = values::<PathBuf>(matches, "output") {
+        project.outputs = outputs;
+    }
+
+    if let Some(speed) = value::<f32>(matches, "speed") {
+        project.speed = Some(*speed);
+    }
+
+    if let Some(time) = value::<f32>(matches, "time") {
+        project.time = *time;
+    }
+
+    if let Some(tile_size) = value::<u32>(matches, "tile-size") {
+        project.tile_size = *tile_size;
+    }
+
+    if let Some(resolution) = value::<String>(matches, "resolution") {
+        project.resolution = resolution.clone();
+    }
+
+    if let Some(aspect) = value::<String>(matches, "aspect") {
+        project.aspect = Some(aspect.clone());
+    }
+
+    if let Some(duration) = value::<f64>(matches, "duration") {
+        project.duration = *duration;
+    }
+
+    if let Some(fps) = value::<u16>(matches, "fps") {
+        project.fps = *fps;
+    }
+
+    if let Some(frames) = value::<usize>(matches, "frames") {
+        project.frames = Some(*frames);
+    }
+
+    if let Some(ssaa) = value::<u32>(matches, "ssaa") {
+        project.ssaa = *ssaa;
+    }
+
+    if let Some(samples) = value::<u32>(matches, "motion-blur") {
+        project.motion_blur = *samples;
+    }
+
+    if let Some(shutter_angle) = value::<f32>(matches, "shutter-angle") {
+        project.shutter_angle = *shutter_angle;
+    }
+
+    if let Some(precision) = value::<Precision>(matches, "precision") {
+        project.precision = *precision;
+    }
+
+    if let Some(quality) = value::<u8>(matches, "quality") {
+        project.quality = *quality;
+    }
+
+    if let Some(sample_rate) = value::<u32>(matches, "sample-rate") {
+        project.sample_rate = *sample_rate;
+    }
+
+    if let Some(format) = value::<OutputFormat>(matches, "format") {
+        project.format = Some(*format);
+    }
+
+    if let Some(size) = value::<String>(matches, "size") {
+        project.preview.size = Some(size.clone());
+    }
+
+    if let Some(vsync) = value::<bool>(matches, "vsync") {
+        project.preview.vsync = *vsync;
+    }
+
+    if value::<bool>(matches, "fullscreen").is_some_and(|fullscreen| *fullscreen) {
+        project.preview.fullscreen = true;
+    }
+
+    if let Some(playlist) = values::<String>(matches, "playlist") {
+        project.preview.playlist = playlist;
+    }
+
+    if let Some(interval) = value::<f64>(matches, "interval") {
+        project.preview.interval = Some(*interval);
+    }
+
+    if let Some(crossfade) = value::<f64>(matches, "crossfade") {
+        project.preview.crossfade = *crossfade;
+    }
+
+    if let Some(osc) = value::<String>(matches, "osc") {
+        project.preview.osc = Some(osc.clone());
+    }
+
+    if let Some(listen) = value::<String>(matches, "listen") {
+        project.serve.listen = listen.clone();
+    }
+
+    if let Some(output_dir) = value::<PathBuf>(matches, "output-dir") {
+        project.serve.output_dir = Some(output_dir.clone());
+    }
+
+    if let Some(effects) = values::<String>(matches, "effect") {
+        project.effects = effects
+            .iter()
+            .map(|spec| Effect::parse(spec))
+            .collect::<Result<_>>()?;
+    }
+
+    if let Some(tone_mapping) = value::<ToneMapping>(matches, "tone-mapping") {
+        project.grade.tone_mapping = *tone_mapping;
+    }
+
+    if let Some(exposure) = value::<f64>(matches, "exposure") {
+        project.grade.exposure = *exposure;
+    }
+
+    if let Some(gamma) = value::<f64>(matches, "gamma") {
+        project.grade.gamma = *gamma;
+    }
+
+    if let Some(saturation) = value::<f64>(matches, "saturation") {
+        project.grade.saturation = *saturation;
+    }
+
+    if let Some(lut) = value::<PathBuf>(matches, "lut") {
+        project.grade.lut = Some(lut.clone());
+    }
+
+    if let Some(backend) = value::<Backend>(matches, "backend") {
+        project.gpu.backend = Some(*backend);
+    }
+
+    if let Some(adapter) = value::<String>(matches, "adapter") {
+        project.gpu.adapter = Some(adapter.clone());
+    }
+
+    if let Some(power) = value::<Power>(matches, "power") {
+        project.gpu.power = Some(*power);
+    }
+
+    if value::<bool>(matches, "software").is_some_and(|software| *software) {
+        project.gpu.software = true;
+    }
+
+    if value::<bool>(matches, "cpu").is_some_and(|cpu| *cpu) {
+        project.cpu = true;
+    }
+
+    if let Some(preset) = value::<String>(matches, "preset") {
+        project.preset = Some(preset.clone());
+    }
+
+    if let Some(shaders) = values::<PathBuf>(matches, "shader") {
+        project.shaders = shaders;
+    }
+
+    if let Some(inputs) = values::<PathBuf>(matches, "input") {
+        project.inputs = inputs;
+    }
+
+    if let Some(audio) = value::<PathBuf>(matches, "audio") {
+        project.audio = Some(audio.clone());
+    }
+
+    if let Some(sound) = value::<PathBuf>(matches, "sound") {
+        project.sound = Some(sound.clone());
+    }
+
+    if let Some(compute) = value::<PathBuf>(matches, "compute") {
+        project.compute = Some(compute.clone());
+    }
+
+    if let Some(size) = value::<String>(matches, "workgroup-size") {
+        project.workgroup_size = size
+            .split_once('x')
+            .and_then(|(x, y)| Some([x.parse().ok()?, y.parse().ok()?]))
+            .with_context(|| format!("Invalid workgroup size `{}`, expected `xxy`", size))?;
+    }
+
+    if let Some(sizes) = values::<u64>(matches, "storage-buffer") {
+        project.storage_buffers = sizes;
+    }
+
+    if let Some(defines) = values::<String>(matches, "define") {
+        for define in defines {
+            let (name, value) = define.split_once('=').unwrap_or((&define, ""));
+            project.defines.insert(name.to_string(), value.to_string());
+        }
+    }
+
+    if let Some(params) = values::<String>(matches, "param") {
+        for param in params {
+            let (name, value) = param
+                .split_once('=')
+                .with_context(|| format!("Invalid parameter `{}`, expected `name=value`", param))?;
+            let value: f32 = value
+                .parse()
+                .with_context(|| format!("Invalid value for the parameter `{}`", name))?;
+            project.params.insert(name.to_string(), value);
+        }
+    }
+
+    Ok(project)
 }
@@ -0,0 +1,9 @@
+// Written by `cs_main`, the passes read it as `previous_pass`
+@group(1) @binding(0) var output: texture_storage_2d<rgba16float, write>;
+
+// Position of the pixel `id` in the whole image, in [-1, 1] like `in.pos`.
+fn pixel_pos(id: vec2u) -> vec2f {
+    let size = vec2f(textureDimensions(output));
+    let local = ((vec2f(id) + 0.5) / size * 2.0 - 1.0) * vec2f(1.0, -1.0);
+    return infos.viewport.xy + local * infos.viewport.zw;
+}
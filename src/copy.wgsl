@@ -0,0 +1,4 @@
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return textureLoad(source, vec2u(in.position.xy), 0);
+}
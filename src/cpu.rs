@@ -0,0 +1,222 @@
+use crate::app::RenderOptions;
+use crate::error::{Result, ShaderArtError};
+use crate::pipeline::{Common, Scene};
+use crate::pixels;
+use rayon::prelude::*;
+
+/// Whether the CPU can render `scene`, only the bundled shader is implemented.
+pub fn check(scene: &Scene) -> Result<()> {
+    let unsupported = if !scene.bundled {
+        "The CPU only renders the bundled `fractal-rings` shader, the other presets, `--shader` and `--compute` need a GPU adapter"
+    } else if !scene.effects.is_empty() {
+        "The CPU does not apply effects, remove the `--effect` options"
+    } else if !scene.grading.is_identity() {
+        "The CPU does not grade the frames, remove the tone mapping, exposure, gamma, saturation and LUT options"
+    } else {
+        return Ok(());
+    };
+
+    Err(ShaderArtError::Project(unsupported.into()))
+}
+
+// Cosine palette of `shader.wgsl`, with its approximation of tau
+#[allow(clippy::approx_constant)]
+fn color(t: f32) -> [f32; 3] {
+    let d = [0.263, 0.416, 0.557];
+    d.map(|d| 0.5 + 0.5 * (6.28318 * (t + d)).cos())
+}
+
+/// Uniforms of frames of `resolution`, supersampled like on the GPU.
+pub fn common(speed: f32, resolution: [u32; 2], options: &RenderOptions) -> Common {
+    let factor = options.ssaa.max(1) as f32;
+    Common::new(
+        [resolution[1] as f32 * factor, resolution[0] as f32 * factor],
+        speed,
+    )
+}
+
+/// Linear color of the bundled shader at `pos`, in [-1, 1] over the whole image.
+pub fn shade(common: &Common, pos: [f32; 2]) -> [f32; 3] {
+    let [height, width] = common.dimensions();
+    let time = common.time;
+
+    let uv0 = [pos[0] * width / height, pos[1]];
+    let length0 = uv0[0].hypot(uv0[1]);
+    let mut uv = uv0;
+    let mut final_color = [0.0; 3];
+
+    for i in 0..6 {
+        uv = uv.map(|v| {
+            let v = v * 1.5;
+            v - v.floor() - 0.5
+        });
+
+        let d = uv[0].hypot(uv[1]) * (-length0).exp();
+        let col = color(length0 + i as f32 * 0.4 + time);
+
+        let d = ((d * 8.0 + time).sin() / 8.0).abs();
+        let d = (0.005 / d).powf(3.0);
+
+        for (c, col) in final_color.iter_mut().zip(col) {
+            *c += col * d;
+        }
+    }
+
+    final_color
+}
+
+/// Renders a frame of `resolution` like `App::run` does on the GPU, with the
+/// supersampling and the motion blur of `options`. The rows are shaded in
+/// parallel.
+pub fn render_frame(
+    common: &Common,
+    resolution: [u32; 2],
+    options: &RenderOptions,
+) -> Result<Vec<u8>> {
+    let [width, height] = resolution;
+    let factor = options.ssaa.max(1);
+    let samples = options.motion_blur.max(1);
+    let shutter = common.frame_interval(options.fps) * options.shutter_angle / 360.0;
+    let viewport = common.viewport();
+
+    // The GPU clamps the supersamples and the sub-frames when the output
+    // clips them anyway
+    let clamp = options.format == wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    // `--cpu` skips the texture limits of the GPU, so the sizes are checked
+    // before anything is allocated
+    let too_large = || {
+        ShaderArtError::Project(format!(
+            "A {}x{} frame supersampled {} times is too large for the CPU",
+            width, height, factor
+        ))
+    };
+    let size = [width, height].map(|side| (side as usize).checked_mul(factor as usize));
+    let [Some(columns), Some(rows)] = size else {
+        return Err(too_large());
+    };
+    let size = [columns, rows];
+    for pixels in [
+        columns.checked_mul(rows),
+        (width as usize).checked_mul(height as usize),
+    ] {
+        pixels
+            .and_then(|pixels| pixels.checked_mul(std::mem::size_of::<[f32; 4]>()))
+            .filter(|&bytes| bytes <= isize::MAX as usize)
+            .ok_or_else(too_large)?;
+    }
+    let mut sum = vec![[0.0; 3]; width as usize * height as usize];
+    for i in 0..samples {
+        let mut common = *common;
+        if samples > 1 {
+            let offset = (i as f32 + 0.5) / samples as f32 - 0.5;
+            common.time += shutter * offset;
+        }
+
+        let shaded = (0..size[1])
+            .into_par_iter()
+            .flat_map_iter(|y| {
+                (0..size[0]).map(move |x| {
+                    // Sample centers in clip space
+                    let clip = [
+                        (x as f32 + 0.5) / size[0] as f32 * 2.0 - 1.0,
+                        1.0 - (y as f32 + 0.5) / size[1] as f32 * 2.0,
+                    ];
+                    let pos = [
+                        viewport[0] + clip[0] * viewport[2],
+                        viewport[1] + clip[1] * viewport[3],
+                    ];
+                    let color = shade(&common, pos);
+                    if clamp && factor > 1 {
+                        color.map(|c| c.clamp(0.0, 1.0))
+                    } else {
+                        color
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let frame = if factor > 1 {
+            let horizontal = downsample(&shaded, size, factor, true);
+            downsample(&horizontal, [width as usize, size[1]], factor, false)
+        } else {
+            shaded
+        };
+
+        for (s, color) in sum.iter_mut().zip(frame) {
+            for (s, c) in s.iter_mut().zip(color) {
+                *s += if clamp && samples > 1 { c.min(1.0) } else { c };
+            }
+        }
+    }
+
+    let data = sum
+        .iter()
+        .flat_map(|[r, g, b]| {
+            [r, g, b]
+                .map(|c| c / samples as f32)
+                .into_iter()
+                .chain([1.0])
+        })
+        .collect::<Vec<f32>>();
+
+    pixels::from_rgba32f(&data, options.format)
+}
+
+// Mitchell-Netravali with B = C = 1/3, like `downsample.wgsl`
+fn mitchell(x: f32) -> f32 {
+    let x = x.abs();
+    if x < 1.0 {
+        (7.0 * x * x * x - 12.0 * x * x + 16.0 / 3.0) / 6.0
+    } else if x < 2.0 {
+        (-7.0 / 3.0 * x * x * x + 12.0 * x * x - 20.0 * x + 32.0 / 3.0) / 6.0
+    } else {
+        0.0
+    }
+}
+
+// One axis of the downsampling filter of the GPU, `size` is the one of `data`
+fn downsample(data: &[[f32; 3]], size: [usize; 2], factor: u32, horizontal: bool) -> Vec<[f32; 3]> {
+    let axis = if horizontal { 0 } else { 1 };
+    let mut output = size;
+    output[axis] /= factor as usize;
+    let f = factor as f32;
+
+    // Taps of each output position along the axis, as `(sample, weight)`
+    let taps = (0..output[axis])
+        .map(|along| {
+            let center = (along as f32 + 0.5) * f;
+            let first = (center - 2.0 * f).floor() as i64;
+            (first..=first + 4 * factor as i64)
+                .map(|i| {
+                    let weight = mitchell((i as f32 + 0.5 - center) / f);
+                    (i.clamp(0, size[axis] as i64 - 1) as usize, weight)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    (0..output[1])
+        .into_par_iter()
+        .flat_map_iter(|y| {
+            let taps = &taps;
+            (0..output[0]).map(move |x| {
+                let (along, across) = if horizontal { (x, y) } else { (y, x) };
+                let mut sum = [0.0; 3];
+                let mut total = 0.0;
+                for (i, weight) in &taps[along] {
+                    let index = if horizontal {
+                        across * size[0] + i
+                    } else {
+                        i * size[0] + across
+                    };
+                    for (s, c) in sum.iter_mut().zip(data[index]) {
+                        *s += weight * c;
+                    }
+                    total += weight;
+                }
+                sum.map(|s| (s / total).max(0.0))
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,14 @@
+struct Settings {
+    progress: f32,
+}
+
+@group(0) @binding(2) var<uniform> settings: Settings;
+// The outgoing entry is the source
+@group(0) @binding(3) var incoming: texture_2d<f32>;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let position = vec2u(in.position.xy);
+    let t = smoothstep(0.0, 1.0, settings.progress);
+    return mix(textureLoad(source, position, 0), textureLoad(incoming, position, 0), t);
+}
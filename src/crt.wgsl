@@ -0,0 +1,25 @@
+const PI: f32 = 3.14159265;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let curvature = settings.values.x;
+    let scanlines = settings.values.y;
+    let line_height = settings.values.z;
+
+    // Barrel distortion, the corners are pushed out of the screen
+    var centered = frame_uv(in.position) * 2.0 - 1.0;
+    centered *= 1.0 + curvature * centered.yx * centered.yx;
+    let uv = centered * 0.5 + 0.5;
+    if any(uv < vec2f(0.0)) || any(uv > vec2f(1.0)) {
+        return vec4f(0.0, 0.0, 0.0, 1.0);
+    }
+
+    let position = uv * settings.frame.resolution;
+    let color = sample_frame(position);
+
+    // Full brightness in the middle of the lines, darker between them
+    let phase = 0.5 - 0.5 * cos(2.0 * PI * position.y / line_height);
+    let factor = 1.0 - scanlines * (1.0 - phase);
+
+    return vec4f(color.rgb * factor, color.a);
+}
@@ -0,0 +1,5 @@
+// Shows the output of the compute shader.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return textureLoad(previous_pass, vec2u(in.foo.xy), 0);
+}
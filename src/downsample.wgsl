@@ -0,0 +1,53 @@
+struct Settings {
+    factor: u32,
+    // Non zero to clamp the samples to [0, 1]
+    clamp: u32,
+    // Axis filtered by the pass, (1, 0) or (0, 1)
+    direction: vec2u,
+}
+
+@group(0) @binding(2) var<uniform> settings: Settings;
+
+// Mitchell-Netravali with B = C = 1/3, `distance` in output pixels
+fn mitchell(distance: f32) -> f32 {
+    let x = abs(distance);
+    if x < 1.0 {
+        return (7.0 * x * x * x - 12.0 * x * x + 16.0 / 3.0) / 6.0;
+    }
+    if x < 2.0 {
+        return (-7.0 / 3.0 * x * x * x + 12.0 * x * x - 20.0 * x + 32.0 / 3.0) / 6.0;
+    }
+    return 0.0;
+}
+
+// One axis of a separable Mitchell-Netravali filter over the samples within 2
+// output pixels. For clipped outputs the samples are clamped first, so a single
+// overexposed sample does not hide the coverage of the others.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let pixel = vec2i(in.position.xy);
+    let size = vec2i(textureDimensions(source));
+    let horizontal = settings.direction.x != 0u;
+    let factor = f32(settings.factor);
+
+    // Center of the output pixel along the axis, in samples
+    let center = (f32(select(pixel.y, pixel.x, horizontal)) + 0.5) * factor;
+    let last = select(size.y, size.x, horizontal) - 1;
+    let first = i32(floor(center - 2.0 * factor));
+
+    var sum = vec4f(0.0);
+    var total = 0.0;
+    for (var i = first; i <= first + 4 * i32(settings.factor); i++) {
+        let weight = mitchell((f32(i) + 0.5 - center) / factor);
+        let along = clamp(i, 0, last);
+        var sample = textureLoad(source, select(vec2i(pixel.x, along), vec2i(along, pixel.y), horizontal), 0);
+        if settings.clamp != 0u {
+            sample = clamp(sample, vec4f(0.0), vec4f(1.0));
+        }
+        sum += weight * sample;
+        total += weight;
+    }
+
+    // The negative lobes would darken below black around highlights
+    return max(sum / total, vec4f(0.0));
+}
@@ -0,0 +1,31 @@
+struct Frame {
+    // Size of the whole image in pixels
+    resolution: vec2f,
+    // Position of the rendered texture in the whole image, not zero for tiles
+    origin: vec2f,
+    time: f32,
+}
+
+struct Settings {
+    frame: Frame,
+    // Meaning depends on the effect
+    values: vec4f,
+}
+
+@group(0) @binding(2) var<uniform> settings: Settings;
+
+// Position of the fragment in the whole image, in pixels.
+fn frame_position(position: vec4f) -> vec2f {
+    return position.xy + settings.frame.origin;
+}
+
+// Position of the fragment in the whole image, in [0, 1].
+fn frame_uv(position: vec4f) -> vec2f {
+    return frame_position(position) / settings.frame.resolution;
+}
+
+// Samples the source at a position of the whole image, in pixels.
+fn sample_frame(position: vec2f) -> vec4f {
+    let local = position - settings.frame.origin;
+    return textureSampleLevel(source, source_sampler, local / vec2f(textureDimensions(source)), 0.0);
+}
@@ -0,0 +1,370 @@
+use crate::pipeline::PASS_FORMAT;
+use crate::post::{self, PostPass};
+use anyhow::{bail, Context, Result};
+use bytemuck::Zeroable;
+use serde::{Deserialize, Serialize};
+
+/// Finishing effect applied to the frames after the shader passes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Effect {
+    Bloom(Bloom),
+    Vignette(Vignette),
+    Grain(Grain),
+    ChromaticAberration(ChromaticAberration),
+    Crt(Crt),
+}
+
+/// Glow around the values above `threshold`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Bloom {
+    pub threshold: f64,
+    /// Blur radius in pixels.
+    pub radius: f64,
+    pub intensity: f64,
+}
+
+/// Darkens the corners.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Vignette {
+    /// 0 leaves the corners untouched, 1 makes them black.
+    pub strength: f64,
+    /// Distance from the center where the darkening starts, 1 is the middle of the sides.
+    pub radius: f64,
+    pub softness: f64,
+}
+
+/// Animated film grain.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Grain {
+    pub amount: f64,
+}
+
+/// Splits the red and blue channels towards the edges.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChromaticAberration {
+    /// Offset of the channels in the corners, in pixels.
+    pub amount: f64,
+}
+
+/// Curved screen with scanlines.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Crt {
+    pub curvature: f64,
+    /// Darkening between the lines, from 0 to 1.
+    pub scanlines: f64,
+    /// Height of a line in pixels.
+    pub line_height: f64,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            radius: 16.0,
+            intensity: 0.5,
+        }
+    }
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Self {
+            strength: 0.5,
+            radius: 0.5,
+            softness: 0.8,
+        }
+    }
+}
+
+impl Default for Grain {
+    fn default() -> Self {
+        Self { amount: 0.05 }
+    }
+}
+
+impl Default for ChromaticAberration {
+    fn default() -> Self {
+        Self { amount: 4.0 }
+    }
+}
+
+impl Default for Crt {
+    fn default() -> Self {
+        Self {
+            curvature: 0.1,
+            scanlines: 0.5,
+            line_height: 3.0,
+        }
+    }
+}
+
+/// Largest bloom radius, each pixel reads twice as many samples.
+pub const MAX_BLOOM_RADIUS: f64 = 128.0;
+
+impl Effect {
+    /// Parses `name` or `name:key=value,key=value`, like `bloom:threshold=0.8`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (name, settings) = spec.split_once(':').unwrap_or((spec, ""));
+
+        let mut table = toml::Table::new();
+        table.insert("type".into(), name.trim().into());
+        for setting in settings.split(',').filter(|s| !s.trim().is_empty()) {
+            let (key, value) = setting
+                .split_once('=')
+                .with_context(|| format!("Invalid setting `{}`, expected `key=value`", setting))?;
+            let value: f64 = value
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid value for `{}`", key))?;
+            table.insert(key.trim().replace('-', "_"), value.into());
+        }
+
+        let effect: Self = toml::Value::Table(table)
+            .try_into()
+            .with_context(|| format!("Invalid effect `{}`", spec))?;
+        effect.validate()?;
+
+        Ok(effect)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Self::Bloom(bloom) if !(0.0..=MAX_BLOOM_RADIUS).contains(&bloom.radius) => {
+                bail!(
+                    "The bloom radius must be between 0 and {}",
+                    MAX_BLOOM_RADIUS
+                )
+            }
+            Self::Crt(crt) if crt.line_height < 1.0 => {
+                bail!("The CRT line height must be at least 1 pixel")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Distance in pixels of the farthest pixel read around each pixel, in an
+    /// image of `resolution`. Tiles are rendered with this margin around them.
+    pub fn apron(&self, resolution: [u32; 2]) -> u32 {
+        let pixels = match self {
+            Self::Bloom(bloom) => bloom.radius.ceil(),
+            // The bilinear samples also read the next pixel
+            Self::ChromaticAberration(aberration) => aberration.amount.abs().ceil() + 1.0,
+            // The corners move by half the curvature of the image size
+            Self::Crt(crt) => {
+                (crt.curvature.abs() * resolution[0].max(resolution[1]) as f64 / 2.0).ceil() + 1.0
+            }
+            Self::Vignette(_) | Self::Grain(_) => 0.0,
+        };
+        pixels.min(u32::MAX as f64) as u32
+    }
+
+    // Settings following the frame in the uniforms
+    fn values(&self) -> [f32; 4] {
+        match self {
+            Self::Bloom(bloom) => [bloom.threshold, bloom.radius, bloom.intensity, 0.0],
+            Self::Vignette(vignette) => {
+                [vignette.strength, vignette.radius, vignette.softness, 0.0]
+            }
+            Self::Grain(grain) => [grain.amount, 0.0, 0.0, 0.0],
+            Self::ChromaticAberration(aberration) => [aberration.amount, 0.0, 0.0, 0.0],
+            Self::Crt(crt) => [crt.curvature, crt.scanlines, crt.line_height, 0.0],
+        }
+        .map(|value| value as f32)
+    }
+}
+
+/// Where the rendered texture sits in the whole image.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Frame {
+    /// Size of the whole image in pixels.
+    pub resolution: [f32; 2],
+    /// Position of the texture in the whole image, not zero for tiles.
+    pub origin: [f32; 2],
+    pub time: f32,
+    _padding: [f32; 3],
+}
+
+impl Frame {
+    pub fn new(resolution: [u32; 2], origin: [u32; 2], time: f32) -> Self {
+        Self {
+            resolution: resolution.map(|v| v as f32),
+            origin: origin.map(|v| v as f32),
+            time,
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    frame: Frame,
+    values: [f32; 4],
+}
+
+struct Stage {
+    values: [f32; 4],
+    passes: Vec<StagePass>,
+}
+
+// Bloom needs scratch textures, the other effects are a single pass.
+enum StagePass {
+    Effect(PostPass),
+    Scratch {
+        pass: PostPass,
+        // Uniform values replacing the ones of the effect
+        values: [f32; 4],
+        target: usize,
+    },
+}
+
+/// The effects in order, ping-ponging between two textures of `PASS_FORMAT`.
+pub struct Chain {
+    stages: Vec<Stage>,
+    targets: [wgpu::TextureView; 2],
+    scratch: Vec<wgpu::TextureView>,
+}
+
+impl Chain {
+    pub fn new(device: &wgpu::Device, size: [u32; 2], effects: &[Effect]) -> Self {
+        let targets = [
+            post::create_target(device, size, PASS_FORMAT),
+            post::create_target(device, size, PASS_FORMAT),
+        ];
+        let mut scratch = Vec::new();
+
+        let stages = effects
+            .iter()
+            .map(|effect| {
+                let values = effect.values();
+                let passes = match effect {
+                    Effect::Bloom(bloom) => {
+                        let first = scratch.len();
+                        let blurred = post::create_texture(device, size, PASS_FORMAT);
+                        scratch.push(post::create_target(device, size, PASS_FORMAT));
+                        scratch.push(blurred.create_view(&Default::default()));
+                        let blur = |label| effect_pass(device, label, include_str!("blur.wgsl"));
+
+                        vec![
+                            // Keeps the bright parts while blurring horizontally
+                            StagePass::Scratch {
+                                pass: blur("Bloom Horizontal"),
+                                values: [1.0, 0.0, bloom.radius as f32, bloom.threshold as f32],
+                                target: first,
+                            },
+                            StagePass::Scratch {
+                                pass: blur("Bloom Vertical"),
+                                values: [0.0, 1.0, bloom.radius as f32, 0.0],
+                                target: first + 1,
+                            },
+                            StagePass::Effect(PostPass::with_textures(
+                                device,
+                                "Bloom",
+                                &effect_source(include_str!("bloom.wgsl")),
+                                PASS_FORMAT,
+                                bytemuck::bytes_of(&Uniforms::zeroed()),
+                                vec![(
+                                    wgpu::TextureViewDimension::D2,
+                                    blurred.create_view(&Default::default()),
+                                )],
+                            )),
+                        ]
+                    }
+                    Effect::Vignette(_) => vec![StagePass::Effect(effect_pass(
+                        device,
+                        "Vignette",
+                        include_str!("vignette.wgsl"),
+                    ))],
+                    Effect::Grain(_) => vec![StagePass::Effect(effect_pass(
+                        device,
+                        "Grain",
+                        include_str!("grain.wgsl"),
+                    ))],
+                    Effect::ChromaticAberration(_) => vec![StagePass::Effect(effect_pass(
+                        device,
+                        "Chromatic Aberration",
+                        include_str!("aberration.wgsl"),
+                    ))],
+                    Effect::Crt(_) => vec![StagePass::Effect(effect_pass(
+                        device,
+                        "CRT",
+                        include_str!("crt.wgsl"),
+                    ))],
+                };
+                Stage { values, passes }
+            })
+            .collect();
+
+        Self {
+            stages,
+            targets,
+            scratch,
+        }
+    }
+
+    /// Texture the frame must be rendered into before `encode`.
+    pub fn source(&self) -> &wgpu::TextureView {
+        &self.targets[0]
+    }
+
+    pub fn write_uniforms(&self, queue: &wgpu::Queue, frame: Frame) {
+        for stage in &self.stages {
+            for pass in &stage.passes {
+                let (pass, values) = match pass {
+                    StagePass::Effect(pass) => (pass, stage.values),
+                    StagePass::Scratch { pass, values, .. } => (pass, *values),
+                };
+                pass.write_uniforms(queue, bytemuck::bytes_of(&Uniforms { frame, values }));
+            }
+        }
+    }
+
+    /// Records the effects and returns the texture holding the result.
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> &wgpu::TextureView {
+        let mut current = 0;
+        for stage in &self.stages {
+            let mut input = &self.targets[current];
+            for pass in &stage.passes {
+                match pass {
+                    StagePass::Scratch { pass, target, .. } => {
+                        pass.encode(device, encoder, input, &self.scratch[*target]);
+                        input = &self.scratch[*target];
+                    }
+                    StagePass::Effect(pass) => pass.encode(
+                        device,
+                        encoder,
+                        &self.targets[current],
+                        &self.targets[1 - current],
+                    ),
+                }
+            }
+            current = 1 - current;
+        }
+        &self.targets[current]
+    }
+}
+
+fn effect_source(source: &str) -> String {
+    format!("{}\n{}", include_str!("effect.wgsl"), source)
+}
+
+fn effect_pass(device: &wgpu::Device, label: &str, source: &str) -> PostPass {
+    PostPass::new(
+        device,
+        label,
+        &effect_source(source),
+        PASS_FORMAT,
+        bytemuck::bytes_of(&Uniforms::zeroed()),
+    )
+}
@@ -0,0 +1,167 @@
+use std::sync::{Arc, Mutex};
+
+pub type Result<T, E = ShaderArtError> = std::result::Result<T, E>;
+
+/// Errors of the rendering, each kind exits the binary with its own code.
+#[derive(Debug, thiserror::Error)]
+pub enum ShaderArtError {
+    /// Invalid project, arguments or input files.
+    #[error("{0}")]
+    Project(String),
+
+    #[error("{0}")]
+    NoAdapter(String),
+
+    /// The device could not be created or reported an error.
+    #[error("{0}")]
+    Device(String),
+
+    /// The device stopped working, after a driver reset for example.
+    #[error("The device was lost: {0}")]
+    DeviceLost(String),
+
+    /// The frames can not be rendered to or read back in this format.
+    #[error("{0:?} textures are not supported")]
+    UnsupportedFormat(wgpu::TextureFormat),
+
+    /// The GPU lacks a capability or a limit the scene needs.
+    #[error("{0}")]
+    Unsupported(String),
+
+    #[error("{0}")]
+    Shader(String),
+
+    #[error("Can not create the window: {0}")]
+    Window(String),
+
+    #[error("Can not present the frame: {0}")]
+    Surface(#[from] wgpu::SurfaceError),
+
+    #[error("Can not read the frame back from the GPU")]
+    Map(#[from] wgpu::BufferAsyncError),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// The frames could not be encoded into the output format.
+    #[error("{0}")]
+    Encoding(String),
+}
+
+impl ShaderArtError {
+    /// Exit code of the binary, 2 is also used by clap for invalid arguments.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Project(_) => 2,
+            Self::NoAdapter(_) => 3,
+            Self::Device(_) | Self::DeviceLost(_) => 4,
+            Self::UnsupportedFormat(_) | Self::Unsupported(_) => 5,
+            Self::Shader(_) => 6,
+            Self::Window(_) | Self::Surface(_) => 7,
+            Self::Map(_) => 8,
+            Self::Io(_) => 9,
+            Self::Encoding(_) => 10,
+        }
+    }
+}
+
+impl From<anyhow::Error> for ShaderArtError {
+    fn from(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<ShaderArtError>() {
+            Ok(error) => return error,
+            Err(error) => error,
+        };
+        // The message keeps the context, like the file that could not be read
+        match error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<std::io::Error>())
+        {
+            Some(io) => Self::Io(std::io::Error::new(io.kind(), format!("{:#}", error))),
+            None => Self::Project(format!("{:#}", error)),
+        }
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for ShaderArtError {
+    fn from(error: wgpu::RequestDeviceError) -> Self {
+        Self::Device(format!("Can not create the device: {}", error))
+    }
+}
+
+impl From<wgpu::CreateSurfaceError> for ShaderArtError {
+    fn from(error: wgpu::CreateSurfaceError) -> Self {
+        Self::Window(error.to_string())
+    }
+}
+
+impl From<std::fmt::Error> for ShaderArtError {
+    fn from(error: std::fmt::Error) -> Self {
+        Self::Encoding(error.to_string())
+    }
+}
+
+macro_rules! encoding_error {
+    ($($error:ty),*) => {
+        $(impl From<$error> for ShaderArtError {
+            fn from(error: $error) -> Self {
+                Self::Encoding(error.to_string())
+            }
+        })*
+    };
+}
+
+encoding_error!(
+    gif::EncodingError,
+    png::EncodingError,
+    tiff::TiffError,
+    image::ImageError,
+    exr::error::Error,
+    hound::Error
+);
+
+/// Records the errors wgpu reports outside of any call, instead of panicking.
+#[derive(Clone, Default)]
+pub struct DeviceErrors(Arc<Mutex<Option<ShaderArtError>>>);
+
+impl DeviceErrors {
+    pub fn watch(device: &wgpu::Device) -> Self {
+        let errors = Self::default();
+
+        let recorder = errors.clone();
+        device.on_uncaptured_error(Box::new(move |error| {
+            recorder.record(ShaderArtError::Device(error.to_string()))
+        }));
+
+        let recorder = errors.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            // Dropping the device or replacing the callback also calls it
+            if matches!(
+                reason,
+                wgpu::DeviceLostReason::Unknown | wgpu::DeviceLostReason::Destroyed
+            ) {
+                recorder.record(ShaderArtError::DeviceLost(if message.is_empty() {
+                    format!("{:?}", reason)
+                } else {
+                    message
+                }));
+            }
+        });
+
+        errors
+    }
+
+    // Keeps the first error, the next ones are usually caused by it
+    fn record(&self, error: ShaderArtError) {
+        if let Ok(mut recorded) = self.0.lock() {
+            recorded.get_or_insert(error);
+        }
+    }
+
+    /// Returns the recorded error, if any, and forgets it.
+    pub fn check(&self) -> Result<()> {
+        match self.0.lock().ok().and_then(|mut error| error.take()) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
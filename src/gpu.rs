@@ -0,0 +1,166 @@
+use crate::error::{Result, ShaderArtError};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Vulkan,
+    Gl,
+    Metal,
+    Dx12,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Power {
+    Low,
+    High,
+}
+
+/// Which adapter renders, the first suitable one of any backend by default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GpuOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<Backend>,
+    /// Index in the list of the `adapters` subcommand, or part of the name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub adapter: Option<String>,
+    /// Ignored when `adapter` picks one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub power: Option<Power>,
+    /// Forces the software adapter of the backend, like lavapipe or WARP.
+    pub software: bool,
+}
+
+impl GpuOptions {
+    fn backends(&self) -> wgpu::Backends {
+        match self.backend {
+            None => wgpu::Backends::all(),
+            Some(Backend::Vulkan) => wgpu::Backends::VULKAN,
+            Some(Backend::Gl) => wgpu::Backends::GL,
+            Some(Backend::Metal) => wgpu::Backends::METAL,
+            Some(Backend::Dx12) => wgpu::Backends::DX12,
+        }
+    }
+
+    pub fn instance(&self) -> wgpu::Instance {
+        wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: self.backends(),
+            dx12_shader_compiler: Default::default(),
+            flags: Default::default(),
+            gles_minor_version: Default::default(),
+        })
+    }
+
+    /// Adapters matching the backend, in the order of the `adapters` subcommand.
+    pub fn adapters(&self, instance: &wgpu::Instance) -> Vec<wgpu::Adapter> {
+        instance.enumerate_adapters(self.backends())
+    }
+
+    /// Picks the adapter, it must be able to present to `surface` when there is one.
+    pub async fn adapter(
+        &self,
+        instance: &wgpu::Instance,
+        surface: Option<&wgpu::Surface<'_>>,
+    ) -> Result<wgpu::Adapter> {
+        let Some(query) = &self.adapter else {
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: match self.power {
+                        None => wgpu::PowerPreference::default(),
+                        Some(Power::Low) => wgpu::PowerPreference::LowPower,
+                        Some(Power::High) => wgpu::PowerPreference::HighPerformance,
+                    },
+                    compatible_surface: surface,
+                    force_fallback_adapter: self.software,
+                })
+                .await;
+
+            return adapter.ok_or_else(|| {
+                ShaderArtError::NoAdapter(if self.software {
+                    "No software adapter found".into()
+                } else {
+                    "No GPU adapter found, `--cpu` renders the bundled shader without one".into()
+                })
+            });
+        };
+
+        let adapters = self.adapters(instance);
+        let adapter = match query.parse::<usize>() {
+            Ok(index) => adapters.into_iter().nth(index),
+            Err(_) => {
+                let query = query.to_lowercase();
+                adapters
+                    .into_iter()
+                    .find(|adapter| adapter.get_info().name.to_lowercase().contains(&query))
+            }
+        };
+
+        let Some(adapter) = adapter else {
+            return Err(ShaderArtError::NoAdapter(format!(
+                "No adapter matches `{}`, the `adapters` subcommand lists them",
+                query
+            )));
+        };
+
+        if self.software && adapter.get_info().device_type != wgpu::DeviceType::Cpu {
+            return Err(ShaderArtError::NoAdapter(format!(
+                "The adapter `{}` is not a software one",
+                adapter.get_info().name
+            )));
+        }
+
+        if let Some(surface) = surface {
+            if !adapter.is_surface_supported(surface) {
+                return Err(ShaderArtError::NoAdapter(format!(
+                    "The adapter `{}` can not present to the window",
+                    adapter.get_info().name
+                )));
+            }
+        }
+
+        Ok(adapter)
+    }
+}
+
+/// Every adapter of the backend with its limits and features.
+pub fn describe_adapters(options: &GpuOptions) -> Result<String> {
+    use std::fmt::Write;
+
+    let instance = options.instance();
+    let mut description = String::new();
+
+    for (i, adapter) in options.adapters(&instance).iter().enumerate() {
+        let info = adapter.get_info();
+        writeln!(description, "{}: {}", i, info.name)?;
+        writeln!(description, "  Backend: {:?}", info.backend)?;
+        writeln!(description, "  Type: {:?}", info.device_type)?;
+        if !info.driver.is_empty() {
+            writeln!(
+                description,
+                "  Driver: {} {}",
+                info.driver, info.driver_info
+            )?;
+        }
+
+        writeln!(description, "  Limits:")?;
+        for line in format!("{:#?}", adapter.limits())
+            .lines()
+            .filter(|line| line.starts_with("    "))
+        {
+            writeln!(description, "  {}", line.trim_end_matches(','))?;
+        }
+
+        writeln!(description, "  Features:")?;
+        for (name, _) in adapter.features().iter_names() {
+            writeln!(description, "    {}", name)?;
+        }
+    }
+
+    if description.is_empty() {
+        return Err(ShaderArtError::NoAdapter("No adapter found".into()));
+    }
+
+    Ok(description)
+}
@@ -0,0 +1,222 @@
+use crate::post::PostPass;
+use anyhow::{bail, Context, Result};
+use half::f16;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use wgpu::util::DeviceExt;
+
+/// Curve compressing the values above 1 into the displayable range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ToneMapping {
+    /// Values above 1 are clipped by the output.
+    #[default]
+    None,
+    Reinhard,
+    /// Filmic fit of the ACES reference rendering transform.
+    Aces,
+    Agx,
+}
+
+/// Color grading applied to the final frames, in the preview and the exports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grading {
+    pub tone_mapping: ToneMapping,
+    /// Exposure adjustment in stops, applied before the tone mapping.
+    pub exposure: f32,
+    pub gamma: f32,
+    /// 0 is grayscale, 1 keeps the colors.
+    pub saturation: f32,
+    pub lut: Option<Lut>,
+}
+
+impl Default for Grading {
+    fn default() -> Self {
+        Self {
+            tone_mapping: ToneMapping::None,
+            exposure: 0.0,
+            gamma: 1.0,
+            saturation: 1.0,
+            lut: None,
+        }
+    }
+}
+
+impl Grading {
+    /// No pass is needed when nothing changes the colors.
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 3D lookup table read from a `.cube` file, applied to sRGB encoded colors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut {
+    pub size: u32,
+    pub domain_min: [f32; 3],
+    pub domain_max: [f32; 3],
+    /// `size`³ colors, red varies the fastest, then green then blue.
+    pub data: Vec<[f32; 3]>,
+}
+
+impl Lut {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Can not read the LUT `{}`", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid LUT `{}`", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut size = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut data = Vec::new();
+
+        fn triplet<'a>(values: impl Iterator<Item = &'a str>, line: usize) -> Result<[f32; 3]> {
+            let values = values
+                .map(|value| value.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .with_context(|| format!("Invalid number on line {}", line))?;
+            match values[..] {
+                [r, g, b] => Ok([r, g, b]),
+                _ => bail!("Expected 3 values on line {}", line),
+            }
+        }
+
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            let mut words = line.split_whitespace();
+            match words.next() {
+                None => {}
+                Some(word) if word.starts_with('#') => {}
+                Some("TITLE") => {}
+                Some("LUT_1D_SIZE") => bail!("1D LUTs are not supported"),
+                Some("LUT_3D_SIZE") => {
+                    let value = words.next().unwrap_or_default();
+                    match value.parse::<u32>() {
+                        Ok(value) if (2..=256).contains(&value) => size = Some(value),
+                        _ => bail!("Invalid LUT_3D_SIZE `{}`", value),
+                    }
+                }
+                Some("DOMAIN_MIN") => domain_min = triplet(words, i + 1)?,
+                Some("DOMAIN_MAX") => domain_max = triplet(words, i + 1)?,
+                Some(word) if word.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                    bail!("Unknown keyword `{}` on line {}", word, i + 1)
+                }
+                Some(_) => data.push(triplet(line.split_whitespace(), i + 1)?),
+            }
+        }
+
+        let Some(size) = size else {
+            bail!("Missing LUT_3D_SIZE");
+        };
+
+        if data.len() != size.pow(3) as usize {
+            bail!(
+                "Expected {} colors for a size of {}, found {}",
+                size.pow(3),
+                size,
+                data.len()
+            );
+        }
+
+        Ok(Self {
+            size,
+            domain_min,
+            domain_max,
+            data,
+        })
+    }
+
+    fn create_texture(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::TextureView {
+        let texels = self
+            .data
+            .iter()
+            .flat_map(|&[r, g, b]| [r, g, b, 1.0].map(f16::from_f32))
+            .collect::<Vec<_>>();
+
+        Self::texture(device, queue, self.size, bytemuck::cast_slice(&texels))
+    }
+
+    fn texture(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: u32,
+        data: &[u8],
+    ) -> wgpu::TextureView {
+        device
+            .create_texture_with_data(
+                queue,
+                &wgpu::TextureDescriptor {
+                    size: wgpu::Extent3d {
+                        width: size,
+                        height: size,
+                        depth_or_array_layers: size,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D3,
+                    format: wgpu::TextureFormat::Rgba16Float,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                    label: Some("LUT Texture"),
+                    view_formats: &[],
+                },
+                wgpu::util::TextureDataOrder::LayerMajor,
+                data,
+            )
+            .create_view(&Default::default())
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Settings {
+    domain_min: [f32; 4],
+    domain_max: [f32; 4],
+    exposure: f32,
+    gamma: f32,
+    saturation: f32,
+    tone_mapping: u32,
+    // Size of the LUT, 0 without one
+    lut_size: u32,
+    _padding: [u32; 3],
+}
+
+fn extend([x, y, z]: [f32; 3]) -> [f32; 4] {
+    [x, y, z, 0.0]
+}
+
+/// Pass writing the graded source into a texture of `texture_format`.
+pub fn pass(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture_format: wgpu::TextureFormat,
+    grading: &Grading,
+) -> PostPass {
+    let lut = grading.lut.as_ref();
+    let settings = Settings {
+        domain_min: extend(lut.map_or([0.0; 3], |lut| lut.domain_min)),
+        domain_max: extend(lut.map_or([1.0; 3], |lut| lut.domain_max)),
+        exposure: grading.exposure,
+        gamma: grading.gamma,
+        saturation: grading.saturation,
+        tone_mapping: grading.tone_mapping as u32,
+        lut_size: lut.map_or(0, |lut| lut.size),
+        _padding: [0; 3],
+    };
+
+    let texture = match lut {
+        Some(lut) => lut.create_texture(device, queue),
+        // Unused but the binding must exist
+        None => Lut::texture(device, queue, 1, bytemuck::cast_slice(&[f16::ZERO; 4])),
+    };
+
+    PostPass::with_textures(
+        device,
+        "Grade",
+        include_str!("grade.wgsl"),
+        texture_format,
+        bytemuck::bytes_of(&settings),
+        vec![(wgpu::TextureViewDimension::D3, texture)],
+    )
+}
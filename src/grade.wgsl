@@ -0,0 +1,105 @@
+struct Settings {
+    domain_min: vec4f,
+    domain_max: vec4f,
+    exposure: f32,
+    gamma: f32,
+    saturation: f32,
+    // 0 none, 1 Reinhard, 2 ACES, 3 AgX
+    tone_mapping: u32,
+    // Size of the LUT, 0 without one
+    lut_size: u32,
+}
+
+@group(0) @binding(2) var<uniform> settings: Settings;
+@group(0) @binding(3) var lut: texture_3d<f32>;
+
+fn reinhard(color: vec3f) -> vec3f {
+    return color / (1.0 + color);
+}
+
+// https://knarkowicz.wordpress.com/2016/01/06/aces-filmic-tone-mapping-curve/
+fn aces(color: vec3f) -> vec3f {
+    let x = color;
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), vec3f(0.0), vec3f(1.0));
+}
+
+// Minimal AgX with a polynomial fit of the default contrast curve
+// https://iolite-engine.com/blog_posts/minimal_agx_implementation
+fn agx_contrast(x: vec3f) -> vec3f {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2
+        + 0.1191 * x - 0.00232;
+}
+
+fn agx(color: vec3f) -> vec3f {
+    let inset = mat3x3f(
+        0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+        0.0784335999999992, 0.878468636469772, 0.0784336,
+        0.0792237451477643, 0.0791661274605434, 0.879142973793104,
+    );
+    let outset = mat3x3f(
+        1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+        -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+        -0.0990297440797205, -0.0989611768448433, 1.15107367264116,
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+
+    var x = inset * color;
+    x = clamp(log2(max(x, vec3f(1e-10))), vec3f(min_ev), vec3f(max_ev));
+    x = (x - min_ev) / (max_ev - min_ev);
+    x = agx_contrast(x);
+    x = outset * x;
+    return pow(max(x, vec3f(0.0)), vec3f(2.2));
+}
+
+fn to_srgb(color: vec3f) -> vec3f {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3f(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3f(0.0031308));
+}
+
+fn to_linear(color: vec3f) -> vec3f {
+    let low = color / 12.92;
+    let high = pow((color + 0.055) / 1.055, vec3f(2.4));
+    return select(high, low, color <= vec3f(0.04045));
+}
+
+// Exposure, tone mapping and saturation work in linear light, the LUT and the
+// gamma on sRGB encoded values. The result is linear again, the output
+// format encodes it.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let source_color = textureLoad(source, vec2u(in.position.xy), 0);
+    var color = source_color.rgb * exp2(settings.exposure);
+
+    switch settings.tone_mapping {
+        case 1u: { color = reinhard(color); }
+        case 2u: { color = aces(color); }
+        case 3u: { color = agx(color); }
+        default: {}
+    }
+
+    let luminance = dot(color, vec3f(0.2126, 0.7152, 0.0722));
+    color = max(mix(vec3f(luminance), color, settings.saturation), vec3f(0.0));
+
+    // Values above 1 only survive without LUT and gamma
+    if settings.lut_size > 0u || settings.gamma != 1.0 {
+        var encoded = to_srgb(clamp(color, vec3f(0.0), vec3f(1.0)));
+
+        if settings.lut_size > 0u {
+            let size = f32(settings.lut_size);
+            let range = settings.domain_max.xyz - settings.domain_min.xyz;
+            let coords = clamp((encoded - settings.domain_min.xyz) / range, vec3f(0.0), vec3f(1.0));
+            // Sample the centers of the first and last texels at 0 and 1
+            let uvw = (coords * (size - 1.0) + 0.5) / size;
+            encoded = textureSampleLevel(lut, source_sampler, uvw, 0.0).rgb;
+        }
+
+        encoded = pow(max(encoded, vec3f(0.0)), vec3f(1.0 / settings.gamma));
+        color = to_linear(encoded);
+    }
+
+    return vec4f(color, source_color.a);
+}
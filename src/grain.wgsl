@@ -0,0 +1,25 @@
+// http://www.jcgt.org/published/0009/03/02/
+fn pcg3d(seed: vec3u) -> vec3u {
+    var v = seed * 1664525u + 1013904223u;
+    v.x += v.y * v.z;
+    v.y += v.z * v.x;
+    v.z += v.x * v.y;
+    v ^= v >> vec3u(16u);
+    v.x += v.y * v.z;
+    v.y += v.z * v.x;
+    v.z += v.x * v.y;
+    return v;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let amount = settings.values.x;
+
+    // Seeded by the position in the whole image, so tiles match
+    let position = vec2u(frame_position(in.position));
+    let seed = vec3u(position, bitcast<u32>(settings.frame.time));
+    let noise = f32(pcg3d(seed).x) / 4294967295.0 - 0.5;
+
+    let color = textureLoad(source, vec2u(in.position.xy), 0);
+    return vec4f(max(color.rgb + noise * amount, vec3f(0.0)), color.a);
+}
@@ -0,0 +1,169 @@
+use crate::audio::AudioFrame;
+use crate::effects::{Chain, Effect, Frame};
+use crate::error::Result;
+use crate::grade;
+use crate::pipeline::{Params, Pipeline, Scene, PASS_FORMAT};
+use crate::post::{self, PostPass};
+
+/// The art rendered with the device of the caller, as a layer of its own
+/// frames. The caller owns the encoder, the target and the time.
+pub struct ArtLayer {
+    pipeline: Pipeline,
+    finish: Option<Finish>,
+    frame: Frame,
+}
+
+impl ArtLayer {
+    /// Builds the passes of `scene` for targets of `texture_format` and `size`.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture_format: wgpu::TextureFormat,
+        size: [u32; 2],
+        scene: &Scene,
+    ) -> Result<Self> {
+        let finish = Finish::new(device, queue, size, texture_format, scene);
+        let pipeline = Pipeline::new(
+            device,
+            queue,
+            if finish.is_some() {
+                PASS_FORMAT
+            } else {
+                texture_format
+            },
+            [size[1] as f32, size[0] as f32],
+            1.0,
+            scene,
+        )?;
+
+        let mut layer = Self {
+            pipeline,
+            finish,
+            frame: Frame::new(size, [0, 0], 0.0),
+        };
+        layer.write_uniforms(queue);
+
+        Ok(layer)
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, size: [u32; 2]) {
+        self.pipeline
+            .resize(device, queue, [size[1] as f32, size[0] as f32]);
+        if let Some(finish) = &mut self.finish {
+            finish.resize(device, size);
+        }
+        self.frame = Frame::new(size, [0, 0], self.frame.time);
+        self.write_uniforms(queue);
+    }
+
+    pub fn set_time(&mut self, queue: &wgpu::Queue, time: f32) {
+        self.pipeline.common.time = time;
+        self.write_uniforms(queue);
+    }
+
+    /// Shows `frame` of an audio track from the next frame on.
+    pub fn set_audio(&mut self, queue: &wgpu::Queue, frame: &AudioFrame) {
+        self.pipeline.set_audio(queue, frame);
+        self.write_uniforms(queue);
+    }
+
+    /// Sets a parameter of the scene, returns `false` if it does not exist.
+    pub fn set_param(&mut self, queue: &wgpu::Queue, name: &str, value: f32) -> bool {
+        let found = self.pipeline.set_param(name, value);
+        self.write_uniforms(queue);
+        found
+    }
+
+    /// Values of every parameter, to `restore_params` them in another layer of
+    /// the same scene.
+    pub fn params(&self) -> Params {
+        self.pipeline.params
+    }
+
+    pub fn restore_params(&mut self, queue: &wgpu::Queue, params: Params) {
+        self.pipeline.params = params;
+        self.write_uniforms(queue);
+    }
+
+    fn write_uniforms(&mut self, queue: &wgpu::Queue) {
+        self.pipeline.write_uniforms(queue);
+        if let Some(finish) = &self.finish {
+            self.frame.time = self.pipeline.common.time;
+            finish.write_uniforms(queue, self.frame);
+        }
+    }
+
+    /// Records the art into `view`, which must have the format and the size
+    /// given to `new` or `resize`. Everything in `view` is overwritten.
+    ///
+    /// Each call is a frame, the compute shader of the scene advances once.
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        self.pipeline.encode_compute(encoder);
+        match &self.finish {
+            Some(finish) => {
+                self.pipeline.encode(encoder, finish.target());
+                finish.encode(device, encoder, view);
+            }
+            None => self.pipeline.encode(encoder, view),
+        }
+    }
+}
+
+// Effects then grading, the frames are rendered into `target()` first
+pub(crate) struct Finish {
+    effects: Vec<Effect>,
+    chain: Chain,
+    // Grading, or a copy to the output format without it
+    pass: PostPass,
+}
+
+impl Finish {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: [u32; 2],
+        texture_format: wgpu::TextureFormat,
+        scene: &Scene,
+    ) -> Option<Self> {
+        if scene.effects.is_empty() && scene.grading.is_identity() {
+            return None;
+        }
+
+        Some(Self {
+            effects: scene.effects.clone(),
+            chain: Chain::new(device, size, &scene.effects),
+            pass: if scene.grading.is_identity() {
+                post::copy(device, texture_format)
+            } else {
+                grade::pass(device, queue, texture_format, &scene.grading)
+            },
+        })
+    }
+
+    pub(crate) fn target(&self) -> &wgpu::TextureView {
+        self.chain.source()
+    }
+
+    pub(crate) fn resize(&mut self, device: &wgpu::Device, size: [u32; 2]) {
+        self.chain = Chain::new(device, size, &self.effects);
+    }
+
+    pub(crate) fn write_uniforms(&self, queue: &wgpu::Queue, frame: Frame) {
+        self.chain.write_uniforms(queue, frame);
+    }
+
+    pub(crate) fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+    ) {
+        let result = self.chain.encode(device, encoder);
+        self.pass.encode(device, encoder, result, target);
+    }
+}
@@ -1,9 +1,49 @@
 pub mod app;
 
+pub mod audio;
+
+pub mod offscreen;
+
+pub mod layer;
+
+pub mod playlist;
+
+pub mod error;
+
+pub mod osc;
+
 pub mod ui;
 
+pub mod server;
+
 pub mod pipeline;
 
+pub mod preprocess;
+
+pub mod watch;
+
+pub mod sound;
+
 pub mod vertex;
 
 pub mod cli;
+
+pub mod project;
+
+pub mod resolution;
+
+pub mod tiled;
+
+pub mod post;
+
+pub mod pixels;
+
+pub mod grade;
+
+pub mod effects;
+
+pub mod gpu;
+
+pub mod cpu;
+
+pub mod presets;
@@ -1,42 +1,89 @@
-use shader_art_rs::cli;
+use shader_art_rs::cli::{self, Mode};
+use shader_art_rs::error::Result;
+use shader_art_rs::gpu;
+use shader_art_rs::presets;
+use shader_art_rs::server::Server;
 use shader_art_rs::ui;
 use spinoff::{spinners, Color, Spinner};
-
-use anyhow::{Context, Result};
+use std::process::ExitCode;
 
 use clap::crate_version;
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn run() -> Result<()> {
     let matches = cli::cli().version(crate_version!()).get_matches();
 
-    let mut animation_speed: u8 = 1;
+    let (mode, matches) = cli::mode(&matches)?;
+
+    let project = cli::project(matches)?;
 
-    if let Some(speed) = matches.get_one::<u8>("speed") {
-        animation_speed = *speed;
+    if matches.get_flag("dump-config") {
+        print!("{}", project.to_toml()?);
+        return Ok(());
     }
 
-    if let Some(filename) = matches.get_one::<String>("save") {
-        let animation_filename = filename;
-        let mut animation_resolution = [512, 512];
-        animation_speed = 10;
+    // The flat invocation exports as soon as there is an output
+    let mode = mode.unwrap_or(if project.is_export() {
+        Mode::Export
+    } else {
+        Mode::Preview
+    });
 
-        if let Some(resolution) = matches.get_one::<String>("resolution") {
-            let parts: Vec<&str> = resolution.split('x').collect();
-            let width: u16 = parts[0].parse().with_context(|| "Invalid width value")?;
-            let height: u16 = parts[1].parse().with_context(|| "Invalid height value")?;
+    match mode {
+        Mode::Preview => pollster::block_on(ui::render(&project))?,
+        Mode::Export | Mode::Still => {
+            let mut spinner = Spinner::new(spinners::Dots, "Generating...", Color::White);
+            let (result, what) = if mode == Mode::Export {
+                (pollster::block_on(ui::run(&project)), "animation")
+            } else {
+                (pollster::block_on(ui::still(&project)), "frame")
+            };
 
-            animation_resolution = [width, height];
+            match result {
+                Ok(()) => {
+                    let outputs = project
+                        .outputs
+                        .iter()
+                        .map(|path| format!("`{}`", path.display()))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    spinner.success(format!("The {} is saved as {}", what, outputs).as_str());
+                }
+                Err(e) => {
+                    spinner.fail(format!("Failed to generate the {}", what).as_str());
+                    return Err(e);
+                }
+            }
         }
-
-        let mut spinner = Spinner::new(spinners::Dots, "Generating...", Color::White);
-        pollster::block_on(ui::run(
-            animation_speed,
-            animation_filename,
-            animation_resolution,
-        ));
-        spinner.success(format!("The animation is saved as `{}`", animation_filename).as_str());
-    } else {
-        pollster::block_on(ui::render(animation_speed));
+        Mode::Validate => {
+            project.scene()?.validate()?;
+            for scene in project.playlist()? {
+                scene.validate()?;
+            }
+            if let Some(sound) = project.sound()? {
+                sound.validate()?;
+            }
+            println!("All the shaders are valid");
+        }
+        Mode::Serve => {
+            let server = Server::start(&project, &project.serve.listen)?;
+            if let Some(address) = server.address() {
+                println!("Listening on http://{}", address);
+            }
+            server.wait();
+        }
+        Mode::Info => print!("{}", ui::info(&project)?),
+        Mode::List => print!("{}", presets::describe()),
+        Mode::Adapters => print!("{}", gpu::describe_adapters(&project.gpu)?),
     }
 
     Ok(())
@@ -0,0 +1,133 @@
+use crate::app::{App, RenderOptions};
+use crate::audio::AudioFrame;
+use crate::error::{Result, ShaderArtError};
+use crate::pipeline::Scene;
+use crate::pixels;
+use std::ops::Range;
+
+/// A rendered frame, rows are tightly packed from top to bottom.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    pub data: Vec<u8>,
+}
+
+impl Frame {
+    /// 8-bit sRGB image, the values above 1 of float frames are clipped.
+    pub fn to_image(&self) -> Result<image::RgbaImage> {
+        image::RgbaImage::from_raw(
+            self.width,
+            self.height,
+            pixels::to_rgba8(&self.data, self.format)?,
+        )
+        .ok_or_else(|| ShaderArtError::Encoding("The frame is smaller than its size".into()))
+    }
+}
+
+/// Renders frames of a scene without any window, at any time.
+pub struct OffscreenRenderer {
+    app: App<'static>,
+    resolution: [u32; 2],
+}
+
+impl OffscreenRenderer {
+    pub async fn new(
+        speed: f32,
+        resolution: [u32; 2],
+        scene: &Scene,
+        options: &RenderOptions,
+    ) -> Result<Self> {
+        Ok(Self {
+            app: App::new_without_window(speed, resolution, scene, options).await?,
+            resolution,
+        })
+    }
+
+    /// Shader time between two frames at the speed and the frame rate of
+    /// the options, motion blur spreads the sub-frames over it.
+    pub fn frame_interval(&self) -> f32 {
+        self.app.frame_interval()
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.app.pipeline.common.speed()
+    }
+
+    /// Speed factor of the animation, it changes the time step.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.app.set_speed(speed);
+    }
+
+    /// Sets a parameter of the scene, returns `false` if it does not exist.
+    pub fn set_param(&mut self, name: &str, value: f32) -> bool {
+        self.app.pipeline.set_param(name, value)
+    }
+
+    /// Shows `frame` of an audio track in the next renders.
+    pub fn set_audio(&mut self, frame: &AudioFrame) {
+        self.app.set_audio(frame);
+    }
+
+    /// Renders the frame at `time` and reads its pixels back.
+    pub async fn render_frame_at(&mut self, time: f32) -> Result<Frame> {
+        self.app.set_time(time);
+
+        let mut frames = Vec::with_capacity(1);
+        self.app.run(&mut frames).await?;
+        let data = frames
+            .pop()
+            .ok_or(ShaderArtError::Map(wgpu::BufferAsyncError))?;
+
+        Ok(Frame {
+            width: self.resolution[0],
+            height: self.resolution[1],
+            format: self.app.output_format(),
+            data,
+        })
+    }
+
+    /// `render_frame_at` blocking the calling thread until the pixels are
+    /// read, for callers without an async runtime. Called from an async task,
+    /// it stalls the executor for the whole render.
+    pub fn render_frame_at_blocking(&mut self, time: f32) -> Result<Frame> {
+        pollster::block_on(self.render_frame_at(time))
+    }
+
+    /// Frames of the shader times `times` at `fps`, spaced like the exports
+    /// by `frame_interval`, the end is excluded. The frame rate replaces the
+    /// one of the options, for the motion blur too. Each frame is rendered
+    /// when the iterator reaches it, blocking like `render_frame_at_blocking`.
+    /// A frame rate that is not finite and positive, or an animation that does
+    /// not advance, yields a single error.
+    pub fn frames(
+        &mut self,
+        times: Range<f32>,
+        fps: f32,
+    ) -> impl Iterator<Item = Result<Frame>> + '_ {
+        let invalid = if !(fps.is_finite() && fps > 0.0) {
+            Some(format!(
+                "The frame rate {} must be finite and positive",
+                fps
+            ))
+        } else {
+            self.app.set_fps(fps);
+            let step = self.frame_interval();
+            (!(step.is_finite() && step > 0.0))
+                .then(|| format!("The animation does not advance at speed {}", self.speed()))
+        };
+        let valid = invalid.is_none();
+        let step = self.frame_interval();
+
+        invalid
+            .map(|message| Err(ShaderArtError::Project(message)))
+            .into_iter()
+            .chain(
+                (0..)
+                    .map(move |i| times.start + i as f32 * step)
+                    .take_while(move |&time| valid && time < times.end)
+                    .map(|time| self.render_frame_at_blocking(time)),
+            )
+    }
+}
@@ -0,0 +1,342 @@
+use crate::app::App;
+use crate::error::{Result, ShaderArtError};
+use std::io::ErrorKind;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+
+// How often the listening thread checks that the server is still used
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Delay before receiving again after a socket error
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+// Bundles nested deeper are rejected, each level recurses
+const MAX_BUNDLE_DEPTH: usize = 8;
+
+/// An argument of an OSC message.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Argument {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    /// Nil, impulse and blob arguments, which carry no value used here.
+    Other,
+}
+
+/// A decoded OSC message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+    pub address: String,
+    pub arguments: Vec<Argument>,
+}
+
+/// What the preview does on a message.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// `/param/<name> <value>`
+    Param(String, f32),
+    /// `/speed <factor>`
+    Speed(f32),
+    /// `/time <time>`
+    Time(f32),
+    /// `/preset next`, `/preset previous`, `/preset <index>` or
+    /// `/preset <entry>` switches the playlist entry.
+    Preset(Switch),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Switch {
+    Next,
+    Previous,
+    Index(usize),
+    Entry(String),
+}
+
+fn invalid(message: impl std::fmt::Display) -> ShaderArtError {
+    ShaderArtError::Project(format!("Invalid OSC packet: {}", message))
+}
+
+/// Decodes the messages of a packet, bundles are flattened.
+pub fn decode(packet: &[u8]) -> Result<Vec<Message>> {
+    decode_nested(packet, 0)
+}
+
+// Decodes a packet within `depth` bundles
+fn decode_nested(packet: &[u8], depth: usize) -> Result<Vec<Message>> {
+    let mut reader = Reader { data: packet };
+
+    if packet.starts_with(b"#bundle\0") {
+        if depth >= MAX_BUNDLE_DEPTH {
+            return Err(invalid(format!(
+                "bundles are nested deeper than {} levels",
+                MAX_BUNDLE_DEPTH
+            )));
+        }
+        reader.string()?;
+        // The time tag is ignored, the messages apply immediately
+        reader.take(8)?;
+        let mut messages = Vec::new();
+        while !reader.data.is_empty() {
+            let size = usize::try_from(reader.int()?).map_err(invalid)?;
+            messages.extend(decode_nested(reader.take(size)?, depth + 1)?);
+        }
+        return Ok(messages);
+    }
+
+    let address = reader.string()?;
+    if !address.starts_with('/') {
+        return Err(invalid(format!(
+            "the address `{}` must start with `/`",
+            address
+        )));
+    }
+
+    // Old senders omit the type tags of messages without arguments
+    let tags = if reader.data.is_empty() {
+        String::from(",")
+    } else {
+        reader.string()?
+    };
+    let Some(tags) = tags.strip_prefix(',') else {
+        return Err(invalid(format!("`{}` is not a type tag string", tags)));
+    };
+
+    let arguments = tags
+        .chars()
+        .map(|tag| {
+            Ok(match tag {
+                'i' => Argument::Int(reader.int()?.into()),
+                'h' => Argument::Int(i64::from_be_bytes(reader.array()?)),
+                'f' => Argument::Float(f32::from_be_bytes(reader.array()?).into()),
+                'd' => Argument::Float(f64::from_be_bytes(reader.array()?)),
+                's' | 'S' => Argument::String(reader.string()?),
+                'T' => Argument::Bool(true),
+                'F' => Argument::Bool(false),
+                'N' | 'I' => Argument::Other,
+                'b' => {
+                    let size = usize::try_from(reader.int()?).map_err(invalid)?;
+                    reader.take(size.next_multiple_of(4))?;
+                    Argument::Other
+                }
+                _ => return Err(invalid(format!("unsupported type tag `{}`", tag))),
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(vec![Message { address, arguments }])
+}
+
+// Reads the big endian and 4 bytes aligned values of a packet
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, size: usize) -> Result<&'a [u8]> {
+        if size > self.data.len() {
+            return Err(invalid("truncated"));
+        }
+        let (taken, rest) = self.data.split_at(size);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap_or([0; N]))
+    }
+
+    fn int(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.array()?))
+    }
+
+    // Null terminated and padded to 4 bytes
+    fn string(&mut self) -> Result<String> {
+        let end = self
+            .data
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or_else(|| invalid("unterminated string"))?;
+        let string = std::str::from_utf8(&self.data[..end])
+            .map_err(invalid)?
+            .to_string();
+        self.take((end + 1).next_multiple_of(4).min(self.data.len()))?;
+        Ok(string)
+    }
+}
+
+impl Argument {
+    fn number(&self) -> Option<f32> {
+        match self {
+            Self::Int(value) => Some(*value as f32),
+            Self::Float(value) => Some(*value as f32),
+            _ => None,
+        }
+    }
+}
+
+impl Command {
+    pub fn parse(message: &Message) -> Result<Self> {
+        let unknown = || {
+            ShaderArtError::Project(format!(
+                "Unknown OSC message `{}` with {:?}",
+                message.address, message.arguments
+            ))
+        };
+        let number = || {
+            match message.arguments.as_slice() {
+                [argument] => argument.number(),
+                _ => None,
+            }
+            .ok_or_else(unknown)
+        };
+
+        if let Some(name) = message.address.strip_prefix("/param/") {
+            return Ok(Self::Param(name.to_string(), number()?));
+        }
+
+        match message.address.as_str() {
+            "/speed" => Ok(Self::Speed(number()?)),
+            "/time" => Ok(Self::Time(number()?)),
+            "/preset" => match message.arguments.as_slice() {
+                [Argument::String(next)] if next == "next" => Ok(Self::Preset(Switch::Next)),
+                [Argument::String(previous)] if previous == "previous" => {
+                    Ok(Self::Preset(Switch::Previous))
+                }
+                [Argument::String(entry)] => Ok(Self::Preset(Switch::Entry(entry.clone()))),
+                [Argument::Int(index)] => usize::try_from(*index)
+                    .map(|index| Self::Preset(Switch::Index(index)))
+                    .map_err(|_| unknown()),
+                _ => Err(unknown()),
+            },
+            _ => Err(unknown()),
+        }
+    }
+
+    /// Applies the command to the preview, `entries` are the names of the
+    /// playlist entries.
+    pub fn apply(&self, app: &mut App, entries: &[String]) -> Result<()> {
+        match self {
+            Self::Param(name, value) => {
+                if !app.set_param(name, *value) {
+                    return Err(ShaderArtError::Project(format!(
+                        "Unknown parameter `{}`",
+                        name
+                    )));
+                }
+            }
+            Self::Speed(speed) => app.set_speed(*speed),
+            Self::Time(time) => app.set_time(*time),
+            Self::Preset(switch) => {
+                let playlist = app.playlist.as_mut().ok_or_else(|| {
+                    ShaderArtError::Project("Presets are switched within the playlist".into())
+                })?;
+                let index = match switch {
+                    Switch::Next => {
+                        playlist.next();
+                        return Ok(());
+                    }
+                    Switch::Previous => {
+                        playlist.previous();
+                        return Ok(());
+                    }
+                    Switch::Index(index) => Some(*index).filter(|index| *index < playlist.len()),
+                    Switch::Entry(entry) => entries.iter().position(|name| name == entry),
+                };
+                let index = index.ok_or_else(|| {
+                    ShaderArtError::Project(format!("No playlist entry {:?}", switch))
+                })?;
+                playlist.switch_to(index);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Listens to OSC packets on a UDP socket in a background thread.
+pub struct Server {
+    address: SocketAddr,
+    commands: mpsc::Receiver<Result<Command>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Server {
+    /// Binds `address` like `0.0.0.0:9000`, port 0 picks a free one.
+    pub fn bind(address: &str) -> Result<Self> {
+        let socket = UdpSocket::bind(address).map_err(|e| {
+            std::io::Error::new(
+                e.kind(),
+                format!("Can not listen to OSC messages on `{}`: {}", address, e),
+            )
+        })?;
+        socket.set_read_timeout(Some(POLL_INTERVAL))?;
+        let address = socket.local_addr()?;
+
+        let (sender, commands) = mpsc::channel();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let stop = stopped.clone();
+        std::thread::spawn(move || {
+            // The largest UDP payload
+            let mut buffer = vec![0; 65536];
+            while !stop.load(Ordering::Relaxed) {
+                let size = match socket.recv(&mut buffer) {
+                    Ok(size) => size,
+                    // The read timeout, the loop checks `stop` again
+                    Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                        continue
+                    }
+                    Err(e) => {
+                        let error = std::io::Error::new(
+                            e.kind(),
+                            format!("Can not receive OSC messages: {}", e),
+                        );
+                        if sender.send(Err(error.into())).is_err() {
+                            return;
+                        }
+                        std::thread::sleep(RETRY_DELAY);
+                        continue;
+                    }
+                };
+                let commands = match decode(&buffer[..size]) {
+                    Ok(messages) => messages.iter().map(Command::parse).collect(),
+                    Err(e) => vec![Err(e)],
+                };
+                for command in commands {
+                    if sender.send(command).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            address,
+            commands,
+            stopped,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Commands received since the last call, with the invalid messages.
+    pub fn commands(&self) -> Vec<Result<Command>> {
+        self.commands.try_iter().collect()
+    }
+
+    /// Waits up to `timeout` for the next command.
+    pub fn next_command(&self, timeout: Duration) -> Option<Result<Command>> {
+        self.commands.recv_timeout(timeout).ok()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
@@ -1,14 +1,62 @@
+use crate::audio::{AudioFrame, Bands, AUDIO_SIZE};
+use crate::effects::Effect;
+use crate::error::{Result, ShaderArtError};
+use crate::grade::Grading;
+use crate::post::create_target;
+use crate::preprocess::SourceMap;
 use crate::vertex::{Vertex, INDICES, VERTICES};
 use wgpu::{util::DeviceExt, PipelineCompilationOptions};
 
+pub const MAX_INPUTS: usize = 4;
+
+pub const MAX_PARAMS: usize = 16;
+
+// Default limits of wgpu for the compute shader
+pub const MAX_STORAGE_BUFFERS: usize = 8;
+
+pub const MAX_STORAGE_BUFFER_SIZE: u64 = 128 << 20;
+
+pub const MAX_WORKGROUP_INVOCATIONS: u32 = 256;
+
+/// Shader time units per second of animation at speed 1. Exported frames are
+/// `frame_interval(speed, fps)` apart, so a duration covers the same shader
+/// time at any frame rate.
+pub const TIME_SCALE: f32 = 0.01;
+
+/// Format of the intermediate textures.
+pub const PASS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
 pub struct Pipeline {
-    pub render_pipeline: wgpu::RenderPipeline,
+    pub render_pipelines: Vec<wgpu::RenderPipeline>,
     pub vertex_buffer: wgpu::Buffer,
     pub uniform_buffer: wgpu::Buffer,
+    pub params_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
     pub num_indices: u32,
-    pub shader_binding_group: wgpu::BindGroup,
+    pub shader_binding_groups: Vec<wgpu::BindGroup>,
     pub common: Common,
+    pub params: Params,
+    param_names: Vec<String>,
+    shader_binding_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    inputs: Vec<wgpu::TextureView>,
+    empty: wgpu::TextureView,
+    audio: wgpu::Texture,
+    audio_view: wgpu::TextureView,
+    pass_targets: Vec<wgpu::TextureView>,
+    compute: Option<ComputePass>,
+}
+
+struct ComputePass {
+    pipeline: wgpu::ComputePipeline,
+    workgroup_size: [u32; 2],
+    output_layout: wgpu::BindGroupLayout,
+    buffers: Vec<wgpu::Buffer>,
+    // Recreated with the size of the render target
+    size: [u32; 2],
+    target: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+    output_bind_group: wgpu::BindGroup,
 }
 
 #[repr(C)]
@@ -17,37 +65,335 @@ pub struct Common {
     dimensions: [f32; 2],
     pub time: f32,
     speed: f32,
+    // Offset and scale from the render target to the whole image, in clip space
+    viewport: [f32; 4],
+    pub audio: Bands,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Params {
+    values: [f32; MAX_PARAMS],
+}
+
+/// What gets rendered: the shader passes, their texture inputs and parameters.
+#[derive(Clone)]
+pub struct Scene {
+    /// WGSL sources of the passes, each one can sample the output of the
+    /// previous one through `previous_pass`.
+    pub passes: Vec<String>,
+    /// Origins of the lines of the preprocessed passes, in the same order.
+    /// The errors of passes without one report their own lines.
+    pub source_maps: Vec<SourceMap>,
+    /// Images bound as `channel0` to `channel3`.
+    pub inputs: Vec<image::RgbaImage>,
+    /// Parameters exposed to the shaders as `param(PARAM_<NAME>)`.
+    pub params: Vec<(String, f32)>,
+    /// Runs before the passes, which read its output as `previous_pass`.
+    pub compute: Option<Compute>,
+    /// Applied in order after the last pass.
+    pub effects: Vec<Effect>,
+    /// Applied after the effects.
+    pub grading: Grading,
+    /// The passes are the bundled shader, which the CPU renders too. Only set
+    /// by `Project::scene`, so that other passes are never taken for it.
+    pub bundled: bool,
+}
+
+/// Art written by a compute shader into a storage texture.
+///
+/// The shader defines `cs_main` with `@workgroup_size(WORKGROUP_X, WORKGROUP_Y)`,
+/// one invocation per pixel, and writes `output`.
+#[derive(Clone, Debug)]
+pub struct Compute {
+    pub source: String,
+    pub workgroup_size: [u32; 2],
+    /// Sizes in bytes of the storage buffers bound from `@group(1) @binding(1)`,
+    /// zeroed at start and kept between frames.
+    pub buffers: Vec<u64>,
+    pub source_map: SourceMap,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self {
+            passes: vec![include_str!("shader.wgsl").to_string()],
+            source_maps: Vec::new(),
+            inputs: Vec::new(),
+            params: Vec::new(),
+            compute: None,
+            effects: Vec::new(),
+            grading: Grading::default(),
+            bundled: false,
+        }
+    }
+}
+
+impl Scene {
+    // Declarations shared by every pass, followed by one constant per parameter.
+    fn prelude(&self) -> String {
+        let mut prelude = include_str!("prelude.wgsl").to_string();
+        for (i, (name, _)) in self.params.iter().enumerate() {
+            prelude.push_str(&format!(
+                "const PARAM_{}: u32 = {}u;\n",
+                name.to_uppercase(),
+                i
+            ));
+        }
+        prelude
+    }
+
+    // Everything before the source of the compute shader
+    fn compute_header(&self, compute: &Compute) -> String {
+        format!(
+            "{}\n{}\nconst WORKGROUP_X: u32 = {}u;\nconst WORKGROUP_Y: u32 = {}u;\n",
+            self.prelude(),
+            include_str!("compute.wgsl"),
+            compute.workgroup_size[0],
+            compute.workgroup_size[1],
+        )
+    }
+
+    /// Complete WGSL source of every pass.
+    pub fn sources(&self) -> Vec<String> {
+        let prelude = self.prelude();
+        self.passes
+            .iter()
+            .map(|source| format!("{}\n{}", prelude, source))
+            .collect()
+    }
+
+    /// Complete WGSL source of the compute shader.
+    pub fn compute_source(&self) -> Option<String> {
+        self.compute
+            .as_ref()
+            .map(|compute| format!("{}{}", self.compute_header(compute), compute.source))
+    }
+
+    /// Checks that every pass compiles and defines `fs_main`, and the compute
+    /// shader `cs_main`, without any GPU.
+    pub fn validate(&self) -> Result<()> {
+        use wgpu::naga::ShaderStage;
+
+        if let (Some(compute), Some(source)) = (&self.compute, self.compute_source()) {
+            let header = lines(&self.compute_header(compute));
+            check(
+                "Compute shader",
+                &source,
+                Lines::new(header, &compute.source_map),
+                "cs_main",
+                ShaderStage::Compute,
+            )?;
+        }
+
+        let header = lines(&self.prelude()) + 1;
+        let identity = SourceMap::default();
+        for (i, source) in self.sources().iter().enumerate() {
+            check(
+                &format!("Pass {}", i),
+                source,
+                Lines::new(header, self.source_maps.get(i).unwrap_or(&identity)),
+                "fs_main",
+                ShaderStage::Fragment,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+// The device may come from the caller, its limits are those it was requested with
+fn check_compute(device: &wgpu::Device, compute: &Compute) -> Result<()> {
+    let limits = device.limits();
+    let [x, y] = compute.workgroup_size;
+    let largest_buffer = compute.buffers.iter().copied().max().unwrap_or(0);
+
+    let missing = if limits.max_storage_textures_per_shader_stage < 1 {
+        "storage textures in compute shaders".to_string()
+    } else if limits.max_storage_buffers_per_shader_stage < compute.buffers.len() as u32 {
+        format!("{} storage buffers", compute.buffers.len())
+    } else if (limits.max_storage_buffer_binding_size as u64) < largest_buffer {
+        format!("storage buffers of {} bytes", largest_buffer)
+    } else if x > limits.max_compute_workgroup_size_x
+        || y > limits.max_compute_workgroup_size_y
+        || x.saturating_mul(y) > limits.max_compute_invocations_per_workgroup
+    {
+        format!("workgroups of {}x{}", x, y)
+    } else {
+        return Ok(());
+    };
+
+    Err(ShaderArtError::Unsupported(format!(
+        "The device does not support {}",
+        missing
+    )))
+}
+
+pub(crate) fn lines(header: &str) -> usize {
+    header.matches('\n').count()
+}
+
+// Maps the lines of a complete source to the files it was made of
+pub(crate) struct Lines<'a> {
+    header: usize,
+    map: &'a SourceMap,
+}
+
+impl<'a> Lines<'a> {
+    pub(crate) fn new(header: usize, map: &'a SourceMap) -> Self {
+        Self { header, map }
+    }
+
+    fn describe(&self, source: &str, span: wgpu::naga::Span) -> String {
+        let location = span.location(source);
+        let line = location.line_number as usize;
+        match line.checked_sub(self.header).filter(|line| *line > 0) {
+            None => format!("prelude:{}", line),
+            Some(line) => match self.map.locate(line) {
+                Some((file, line)) => format!("{}:{}:{}", file, line, location.line_position),
+                None => format!("line {}:{}", line, location.line_position),
+            },
+        }
+    }
+}
+
+pub(crate) fn check(
+    name: &str,
+    source: &str,
+    lines: Lines,
+    entry_point: &str,
+    stage: wgpu::naga::ShaderStage,
+) -> Result<()> {
+    use wgpu::naga;
+
+    let module = naga::front::wgsl::parse_str(source).map_err(|e| {
+        let labels = e
+            .labels()
+            .filter(|(span, _)| span.is_defined())
+            .map(|(span, label)| format!("\n  {}: {}", lines.describe(source, span), label))
+            .collect::<String>();
+        ShaderArtError::Shader(format!("{}: {}{}", name, e.message(), labels))
+    })?;
+
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .map_err(|e| {
+        let mut message = e.as_inner().to_string();
+        let mut cause = std::error::Error::source(e.as_inner());
+        while let Some(error) = cause {
+            message.push_str(&format!(": {}", error));
+            cause = error.source();
+        }
+        let labels = e
+            .spans()
+            .filter(|(span, _)| span.is_defined())
+            .map(|(span, label)| format!("\n  {}: {}", lines.describe(source, *span), label))
+            .collect::<String>();
+        ShaderArtError::Shader(format!("{}: {}{}", name, message, labels))
+    })?;
+
+    if !module
+        .entry_points
+        .iter()
+        .any(|entry| entry.name == entry_point && entry.stage == stage)
+    {
+        return Err(ShaderArtError::Shader(format!(
+            "{}: missing the `{}` {} entry point",
+            name,
+            entry_point,
+            format!("{:?}", stage).to_lowercase()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Shader time between two frames at `fps`, for an animation at `speed`.
+pub fn frame_interval(speed: f32, fps: f32) -> f32 {
+    speed * TIME_SCALE / fps
 }
 
 impl Common {
-    fn new(dims: [f32; 2], speed: u8) -> Self {
+    /// `dims` is the height then the width of the whole image, in pixels.
+    pub fn new(dims: [f32; 2], speed: f32) -> Self {
         Self {
             dimensions: dims,
             time: 1.0,
-            speed: speed.into(),
+            speed,
+            viewport: [0.0, 0.0, 1.0, 1.0],
+            audio: Bands::default(),
         }
     }
 
+    /// Time elapsed between two frames of the preview.
+    pub fn time_step(&self) -> f32 {
+        self.speed * 0.0005
+    }
+
+    /// Time elapsed between two exported frames at `fps`.
+    pub fn frame_interval(&self, fps: f32) -> f32 {
+        frame_interval(self.speed, fps)
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
     pub fn update_time(&mut self) {
-        self.time += self.speed * 0.0005;
+        self.time += self.time_step();
+    }
+
+    /// Height then width of the whole image.
+    pub fn dimensions(&self) -> [f32; 2] {
+        self.dimensions
+    }
+
+    /// Offset then scale from the render target to the whole image, in clip space.
+    pub fn viewport(&self) -> [f32; 4] {
+        self.viewport
     }
 
     pub fn update_dimensions(&mut self, new_dimensions: [f32; 2]) {
         self.dimensions = new_dimensions;
     }
+
+    /// Renders the region at `origin` of `size` pixels instead of the whole image.
+    pub fn update_viewport(&mut self, origin: [u32; 2], size: [u32; 2]) {
+        let [height, width] = self.dimensions;
+        let [x, y] = origin.map(|v| v as f32);
+        let [w, h] = size.map(|v| v as f32);
+        self.viewport = [
+            (2.0 * x + w) / width - 1.0,
+            1.0 - (2.0 * y + h) / height,
+            w / width,
+            h / height,
+        ];
+    }
+
+    pub fn reset_viewport(&mut self) {
+        self.viewport = [0.0, 0.0, 1.0, 1.0];
+    }
 }
 
 impl Pipeline {
+    /// Builds the passes of `scene`, which are validated first so that
+    /// shader errors are returned instead of reported by wgpu.
     pub fn new(
         device: &wgpu::Device,
+        queue: &wgpu::Queue,
         texture_format: wgpu::TextureFormat,
         texture_dimensions: [f32; 2],
-        speed: u8,
-    ) -> Self {
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-        });
+        speed: f32,
+        scene: &Scene,
+    ) -> Result<Self> {
+        scene.validate()?;
 
         // Vertex buffer
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -73,30 +419,101 @@ impl Pipeline {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
+        let mut params = Params::default();
+        for (i, (_, value)) in scene.params.iter().take(MAX_PARAMS).enumerate() {
+            params.values[i] = *value;
+        }
+
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Params Buffer"),
+            contents: bytemuck::cast_slice(&[params]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Texture inputs
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Channel Sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let empty = image::RgbaImage::new(1, 1);
+        let inputs = (0..MAX_INPUTS)
+            .map(|i| Self::create_input(device, queue, scene.inputs.get(i).unwrap_or(&empty)))
+            .collect::<Vec<_>>();
+        let empty = Self::create_input(device, queue, &empty);
+
+        // Spectrum then waveform of the track, silence without one
+        let silence = AudioFrame::default();
+        let audio = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                size: wgpu::Extent3d {
+                    width: AUDIO_SIZE as u32,
+                    height: 2,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::R8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                label: Some("Audio Texture"),
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            silence.texture.as_flattened(),
+        );
+        let audio_view = audio.create_view(&Default::default());
+
+        let texture_entry = |binding: u32| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        let uniform_entry = |binding: u32| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX
+                | wgpu::ShaderStages::FRAGMENT
+                | wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
         let shader_binding_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
+                entries: &[
+                    uniform_entry(0),
+                    uniform_entry(1),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
                     },
-                    count: None,
-                }],
+                    texture_entry(3),
+                    texture_entry(4),
+                    texture_entry(5),
+                    texture_entry(6),
+                    texture_entry(7),
+                    texture_entry(8),
+                ],
                 label: Some("shader binding group"),
             });
 
-        let shader_binding_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &shader_binding_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: uniform_buffer.as_entire_binding(),
-            }],
-            label: Some("shader_binding_group"),
-        });
-
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
@@ -104,21 +521,81 @@ impl Pipeline {
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let render_pipelines = scene
+            .sources()
+            .into_iter()
+            .enumerate()
+            .map(|(i, source)| {
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("Shader"),
+                    source: wgpu::ShaderSource::Wgsl(source.into()),
+                });
+
+                // Only the last pass renders into the output, the others
+                // render into intermediate textures.
+                let format = if i + 1 == scene.passes.len() {
+                    texture_format
+                } else {
+                    PASS_FORMAT
+                };
+
+                Self::create_render_pipeline(device, &render_pipeline_layout, &shader, format)
+            })
+            .collect();
+
+        let mut pipeline = Self {
+            render_pipelines,
+            vertex_buffer,
+            uniform_buffer,
+            params_buffer,
+            index_buffer,
+            num_indices,
+            shader_binding_groups: Vec::new(),
+            common,
+            params,
+            param_names: scene.params.iter().map(|(name, _)| name.clone()).collect(),
+            shader_binding_group_layout,
+            sampler,
+            inputs,
+            empty,
+            audio,
+            audio_view,
+            pass_targets: Vec::new(),
+            compute: None,
+        };
+
+        if let (Some(compute), Some(source)) = (&scene.compute, scene.compute_source()) {
+            check_compute(device, compute)?;
+            pipeline.compute = Some(pipeline.create_compute_pass(device, &source, compute));
+        }
+
+        pipeline.create_pass_targets(device, texture_dimensions);
+
+        Ok(pipeline)
+    }
+
+    fn create_render_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        texture_format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
+            layout: Some(layout),
             vertex: wgpu::VertexState {
-                module: &shader,
+                module: shader,
                 entry_point: "vs_main",
                 buffers: &[Vertex::desc()],
                 compilation_options: PipelineCompilationOptions::default(),
             },
             fragment: Some(wgpu::FragmentState {
-                module: &shader,
+                module: shader,
                 entry_point: "fs_main",
                 targets: &[Some(wgpu::ColorTargetState {
                     format: texture_format,
-                    blend: Some(wgpu::BlendState::REPLACE),
+                    // Float32 formats can not be blended
+                    blend: None,
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
                 compilation_options: PipelineCompilationOptions::default(),
@@ -140,16 +617,345 @@ impl Pipeline {
             },
             multiview: None,
             cache: None,
+        })
+    }
+
+    fn create_compute_pass(
+        &self,
+        device: &wgpu::Device,
+        source: &str,
+        compute: &Compute,
+    ) -> ComputePass {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Compute Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
         });
 
-        Self {
-            render_pipeline,
-            vertex_buffer,
-            uniform_buffer,
-            index_buffer,
-            num_indices,
-            shader_binding_group,
-            common,
+        let buffers = compute
+            .buffers
+            .iter()
+            .map(|size| {
+                device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Storage Buffer"),
+                    size: *size,
+                    usage: wgpu::BufferUsages::STORAGE,
+                    mapped_at_creation: false,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut entries = vec![wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::StorageTexture {
+                access: wgpu::StorageTextureAccess::WriteOnly,
+                format: PASS_FORMAT,
+                view_dimension: wgpu::TextureViewDimension::D2,
+            },
+            count: None,
+        }];
+        entries.extend((0..buffers.len()).map(|i| wgpu::BindGroupLayoutEntry {
+            binding: 1 + i as u32,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }));
+
+        let output_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: Some("compute output binding group"),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Compute Pipeline Layout"),
+            bind_group_layouts: &[&self.shader_binding_group_layout, &output_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Compute Pipeline"),
+            layout: Some(&layout),
+            module: &shader,
+            entry_point: "cs_main",
+            compilation_options: PipelineCompilationOptions::default(),
+            cache: None,
+        });
+
+        let [height, width] = self.common.dimensions;
+        let size = [width as u32, height as u32];
+        let (target, output_bind_group) =
+            Self::create_compute_target(device, &output_layout, &buffers, size);
+
+        ComputePass {
+            pipeline,
+            workgroup_size: compute.workgroup_size,
+            bind_group: self.create_bind_group(device, &self.empty),
+            output_layout,
+            buffers,
+            size,
+            target,
+            output_bind_group,
+        }
+    }
+
+    fn create_compute_target(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        buffers: &[wgpu::Buffer],
+        size: [u32; 2],
+    ) -> (wgpu::TextureView, wgpu::BindGroup) {
+        let target = device
+            .create_texture(&wgpu::TextureDescriptor {
+                size: wgpu::Extent3d {
+                    width: size[0].max(1),
+                    height: size[1].max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: PASS_FORMAT,
+                usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+                label: Some("Compute Texture"),
+                view_formats: &[],
+            })
+            .create_view(&Default::default());
+
+        let mut entries = vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(&target),
+        }];
+        entries.extend(
+            buffers
+                .iter()
+                .enumerate()
+                .map(|(i, buffer)| wgpu::BindGroupEntry {
+                    binding: 1 + i as u32,
+                    resource: buffer.as_entire_binding(),
+                }),
+        );
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &entries,
+            label: Some("compute_output_binding_group"),
+        });
+
+        (target, bind_group)
+    }
+
+    fn create_input(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: &image::RgbaImage,
+    ) -> wgpu::TextureView {
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                size: wgpu::Extent3d {
+                    width: image.width(),
+                    height: image.height(),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                label: Some("Input Texture"),
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            image.as_raw(),
+        );
+        texture.create_view(&Default::default())
+    }
+
+    // Intermediate textures between passes and the bind groups reading them.
+    fn create_pass_targets(&mut self, device: &wgpu::Device, dimensions: [f32; 2]) {
+        let [height, width] = dimensions;
+
+        self.pass_targets = (1..self.render_pipelines.len())
+            .map(|_| create_target(device, [width as u32, height as u32], PASS_FORMAT))
+            .collect();
+
+        if let Some(compute) = &mut self.compute {
+            compute.size = [width as u32, height as u32];
+            (compute.target, compute.output_bind_group) = Self::create_compute_target(
+                device,
+                &compute.output_layout,
+                &compute.buffers,
+                compute.size,
+            );
+        }
+
+        self.shader_binding_groups = (0..self.render_pipelines.len())
+            .map(|i| {
+                let previous = match (i, &self.compute) {
+                    (0, Some(compute)) => &compute.target,
+                    (0, None) => &self.empty,
+                    _ => &self.pass_targets[i - 1],
+                };
+                self.create_bind_group(device, previous)
+            })
+            .collect();
+    }
+
+    fn create_bind_group(
+        &self,
+        device: &wgpu::Device,
+        previous: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.shader_binding_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(previous),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&self.inputs[0]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(&self.inputs[1]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::TextureView(&self.inputs[2]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 7,
+                    resource: wgpu::BindingResource::TextureView(&self.inputs[3]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 8,
+                    resource: wgpu::BindingResource::TextureView(&self.audio_view),
+                },
+            ],
+            label: Some("shader_binding_group"),
+        })
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, dimensions: [f32; 2]) {
+        self.common.update_dimensions(dimensions);
+        self.create_pass_targets(device, dimensions);
+        self.write_uniforms(queue);
+    }
+
+    /// Value of the parameter `name`, if it exists.
+    pub fn param(&self, name: &str) -> Option<f32> {
+        let i = self.param_names.iter().position(|n| n == name)?;
+        self.params.values.get(i).copied()
+    }
+
+    pub fn set_param(&mut self, name: &str, value: f32) -> bool {
+        match self.param_names.iter().position(|n| n == name) {
+            Some(i) if i < MAX_PARAMS => {
+                self.params.values[i] = value;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Uploads the analysis of the track at the next frame, call
+    /// `write_uniforms` after it.
+    pub fn set_audio(&mut self, queue: &wgpu::Queue, frame: &AudioFrame) {
+        queue.write_texture(
+            self.audio.as_image_copy(),
+            frame.texture.as_flattened(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(AUDIO_SIZE as u32),
+                rows_per_image: None,
+            },
+            self.audio.size(),
+        );
+        self.common.audio = frame.bands;
+    }
+
+    pub fn write_uniforms(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[self.common]),
+        );
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[self.params]));
+    }
+
+    /// Whether a compute shader renders the art read by the passes.
+    pub fn has_compute(&self) -> bool {
+        self.compute.is_some()
+    }
+
+    /// Records the compute shader if any. It advances its storage buffers, so
+    /// it is recorded once per frame, before the passes of every supersample
+    /// and sub-frame of it.
+    pub fn encode_compute(&self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(compute) = &self.compute {
+            let [width, height] = compute.size;
+            let [x, y] = compute.workgroup_size;
+
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Compute Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&compute.pipeline);
+            compute_pass.set_bind_group(0, &compute.bind_group, &[]);
+            compute_pass.set_bind_group(1, &compute.output_bind_group, &[]);
+            compute_pass.dispatch_workgroups(width.div_ceil(x), height.div_ceil(y), 1);
+        }
+    }
+
+    /// Records every pass, the last one renders into `view`. They read the
+    /// output of the last `encode_compute`.
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        for (i, render_pipeline) in self.render_pipelines.iter().enumerate() {
+            let target = self.pass_targets.get(i).unwrap_or(view);
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.0,
+                            g: 0.0,
+                            b: 0.0,
+                            a: 1.0,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            render_pass.set_pipeline(render_pipeline);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            render_pass.set_bind_group(0, &self.shader_binding_groups[i], &[]);
+            render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
         }
     }
 }
@@ -0,0 +1,132 @@
+use crate::error::{Result, ShaderArtError};
+use half::f16;
+use std::path::Path;
+
+/// Formats the frames can be read back and saved in.
+pub const FORMATS: [wgpu::TextureFormat; 4] = [
+    wgpu::TextureFormat::Rgba32Float,
+    wgpu::TextureFormat::Rgba16Float,
+    wgpu::TextureFormat::Rgba8UnormSrgb,
+    wgpu::TextureFormat::Rgba8Unorm,
+];
+
+/// Decodes RGBA pixels read back in `format` to linear floats.
+pub fn to_rgba32f(data: &[u8], format: wgpu::TextureFormat) -> Result<Vec<f32>> {
+    Ok(match format {
+        wgpu::TextureFormat::Rgba32Float => bytemuck::pod_collect_to_vec(data),
+        wgpu::TextureFormat::Rgba16Float => data
+            .chunks_exact(2)
+            .map(|c| f16::from_le_bytes([c[0], c[1]]).to_f32())
+            .collect(),
+        wgpu::TextureFormat::Rgba8UnormSrgb => data
+            .chunks_exact(4)
+            .flat_map(|c| {
+                [
+                    srgb_to_linear(c[0]),
+                    srgb_to_linear(c[1]),
+                    srgb_to_linear(c[2]),
+                    c[3] as f32 / 255.0,
+                ]
+            })
+            .collect(),
+        wgpu::TextureFormat::Rgba8Unorm => data.iter().map(|&c| c as f32 / 255.0).collect(),
+        _ => return Err(ShaderArtError::UnsupportedFormat(format)),
+    })
+}
+
+/// Encodes linear RGBA floats in `format`, the inverse of `to_rgba32f`.
+pub fn from_rgba32f(data: &[f32], format: wgpu::TextureFormat) -> Result<Vec<u8>> {
+    Ok(match format {
+        wgpu::TextureFormat::Rgba32Float => bytemuck::cast_slice(data).to_vec(),
+        wgpu::TextureFormat::Rgba16Float => data
+            .iter()
+            .flat_map(|&value| f16::from_f32(value).to_le_bytes())
+            .collect(),
+        wgpu::TextureFormat::Rgba8UnormSrgb => data
+            .chunks_exact(4)
+            .flat_map(|c| {
+                [
+                    linear_to_srgb(c[0]),
+                    linear_to_srgb(c[1]),
+                    linear_to_srgb(c[2]),
+                    (c[3].clamp(0.0, 1.0) * 255.0).round() as u8,
+                ]
+            })
+            .collect(),
+        wgpu::TextureFormat::Rgba8Unorm => data
+            .iter()
+            .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect(),
+        _ => return Err(ShaderArtError::UnsupportedFormat(format)),
+    })
+}
+
+/// Encodes RGBA pixels read back in `format` to 8-bit sRGB, clipping the
+/// values above 1.
+pub fn to_rgba8(data: &[u8], format: wgpu::TextureFormat) -> Result<Vec<u8>> {
+    if format == wgpu::TextureFormat::Rgba8UnormSrgb {
+        return Ok(data.to_vec());
+    }
+
+    Ok(to_rgba32f(data, format)?
+        .chunks_exact(4)
+        .flat_map(|c| {
+            [
+                linear_to_srgb(c[0]),
+                linear_to_srgb(c[1]),
+                linear_to_srgb(c[2]),
+                (c[3].clamp(0.0, 1.0) * 255.0).round() as u8,
+            ]
+        })
+        .collect())
+}
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let value = value as f32 / 255.0;
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> u8 {
+    let value = value.clamp(0.0, 1.0);
+    let value = if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+    (value * 255.0).round() as u8
+}
+
+/// Writes a linear RGBA image, in half floats for `Rgba16Float`.
+pub fn save_exr(
+    path: &Path,
+    data: &[u8],
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+) -> Result<()> {
+    let pixels = to_rgba32f(data, format)?;
+    let pixel = |x: usize, y: usize| {
+        let i = (y * width as usize + x) * 4;
+        [pixels[i], pixels[i + 1], pixels[i + 2], pixels[i + 3]]
+    };
+    let size = (width as usize, height as usize);
+
+    let result = if format == wgpu::TextureFormat::Rgba16Float {
+        exr::prelude::write_rgba_file(path, size.0, size.1, |x, y| {
+            let [r, g, b, a] = pixel(x, y).map(f16::from_f32);
+            (r, g, b, a)
+        })
+    } else {
+        exr::prelude::write_rgba_file(path, size.0, size.1, |x, y| {
+            let [r, g, b, a] = pixel(x, y);
+            (r, g, b, a)
+        })
+    };
+
+    result
+        .map_err(|e| ShaderArtError::Encoding(format!("Can not write `{}`: {}", path.display(), e)))
+}
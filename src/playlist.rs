@@ -0,0 +1,227 @@
+use crate::audio::AudioFrame;
+use crate::error::{Result, ShaderArtError};
+use crate::layer::ArtLayer;
+use crate::pipeline::{Params, Scene, PASS_FORMAT};
+use crate::post::{self, PostPass};
+use std::time::{Duration, Instant};
+
+/// When the playlist switches and how long the crossfade lasts.
+#[derive(Clone, Copy, Debug)]
+pub struct Transition {
+    /// Time each entry is shown, only `next` and `previous` switch when `None`.
+    pub interval: Option<Duration>,
+    pub crossfade: Duration,
+}
+
+// Crossfade from the entry shown before
+#[derive(Clone, Copy, Debug)]
+struct Fade {
+    from: usize,
+    started_at: Instant,
+}
+
+/// Entry shown, crossfade and parameters of every entry, kept when the
+/// playlist is rebuilt for the same scenes.
+#[derive(Clone, Debug)]
+pub struct PlaylistState {
+    current: usize,
+    shown_at: Instant,
+    fade: Option<Fade>,
+    params: Vec<Params>,
+}
+
+/// Scenes shown in turn with a crossfade between them. Every scene is built
+/// upfront, so a switch never waits for a shader to compile.
+pub struct Playlist {
+    layers: Vec<ArtLayer>,
+    current: usize,
+    shown_at: Instant,
+    fade: Option<Fade>,
+    transition: Transition,
+    texture_format: wgpu::TextureFormat,
+    // Both entries are rendered during a crossfade, then blended
+    outgoing: wgpu::TextureView,
+    incoming: wgpu::TextureView,
+    blend: PostPass,
+}
+
+impl Playlist {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture_format: wgpu::TextureFormat,
+        size: [u32; 2],
+        scenes: &[Scene],
+        transition: Transition,
+    ) -> Result<Self> {
+        if scenes.is_empty() {
+            return Err(ShaderArtError::Project("The playlist is empty".into()));
+        }
+
+        let layers = scenes
+            .iter()
+            .map(|scene| ArtLayer::new(device, queue, PASS_FORMAT, size, scene))
+            .collect::<Result<_>>()?;
+        let (outgoing, incoming, blend) = targets(device, texture_format, size);
+
+        Ok(Self {
+            layers,
+            current: 0,
+            shown_at: Instant::now(),
+            fade: None,
+            transition,
+            texture_format,
+            outgoing,
+            incoming,
+            blend,
+        })
+    }
+
+    /// Index of the entry shown, or faded in.
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    pub fn next(&mut self) {
+        self.switch_to((self.current + 1) % self.len());
+    }
+
+    pub fn previous(&mut self) {
+        self.switch_to((self.current + self.len() - 1) % self.len());
+    }
+
+    /// Crossfades to the entry at `index`.
+    pub fn switch_to(&mut self, index: usize) {
+        let index = index % self.len();
+        if index != self.current {
+            self.fade = Some(Fade {
+                from: self.current,
+                started_at: Instant::now(),
+            });
+            self.current = index;
+        }
+        self.shown_at = Instant::now();
+    }
+
+    /// Shows the entry at `index` right away, without a crossfade.
+    pub fn jump_to(&mut self, index: usize) {
+        self.current = index % self.len();
+        self.shown_at = Instant::now();
+        self.fade = None;
+    }
+
+    pub fn state(&self) -> PlaylistState {
+        PlaylistState {
+            current: self.current,
+            shown_at: self.shown_at,
+            fade: self.fade,
+            params: self.layers.iter().map(ArtLayer::params).collect(),
+        }
+    }
+
+    /// Continues from `state`, the crossfade included.
+    pub fn restore(&mut self, queue: &wgpu::Queue, state: PlaylistState) {
+        self.current = state.current % self.len();
+        self.shown_at = state.shown_at;
+        self.fade = state.fade.filter(|fade| fade.from < self.len());
+        for (layer, params) in self.layers.iter_mut().zip(state.params) {
+            layer.restore_params(queue, params);
+        }
+    }
+
+    /// Switches when the interval is over and advances the crossfade, then
+    /// sets the time of the visible entries.
+    pub fn update(&mut self, queue: &wgpu::Queue, time: f32) {
+        if let Some(interval) = self.transition.interval {
+            if self.shown_at.elapsed() >= interval {
+                self.next();
+            }
+        }
+
+        let progress = match &self.fade {
+            Some(fade) if !self.transition.crossfade.is_zero() => {
+                fade.started_at.elapsed().as_secs_f32() / self.transition.crossfade.as_secs_f32()
+            }
+            _ => 1.0,
+        };
+        if progress >= 1.0 {
+            self.fade = None;
+        }
+
+        self.layers[self.current].set_time(queue, time);
+        if let Some(fade) = &self.fade {
+            self.layers[fade.from].set_time(queue, time);
+        }
+        self.blend
+            .write_uniforms(queue, bytemuck::bytes_of(&progress.min(1.0)));
+    }
+
+    /// Sets a parameter of every entry, returns `false` if none has it.
+    pub fn set_param(&mut self, queue: &wgpu::Queue, name: &str, value: f32) -> bool {
+        let mut found = false;
+        for layer in &mut self.layers {
+            found |= layer.set_param(queue, name, value);
+        }
+        found
+    }
+
+    /// Shows `frame` of the audio track in every entry.
+    pub fn set_audio(&mut self, queue: &wgpu::Queue, frame: &AudioFrame) {
+        for layer in &mut self.layers {
+            layer.set_audio(queue, frame);
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, size: [u32; 2]) {
+        for layer in &mut self.layers {
+            layer.resize(device, queue, size);
+        }
+        (self.outgoing, self.incoming, self.blend) = targets(device, self.texture_format, size);
+    }
+
+    /// Records the visible entries into `view`, of the format and the size
+    /// given to `new` or `resize`.
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        if let Some(fade) = &self.fade {
+            self.layers[fade.from].encode(device, encoder, &self.outgoing);
+        }
+        self.layers[self.current].encode(device, encoder, &self.incoming);
+        self.blend.encode(device, encoder, &self.outgoing, view);
+    }
+}
+
+// Outgoing and incoming targets, and the pass blending them into the output
+fn targets(
+    device: &wgpu::Device,
+    texture_format: wgpu::TextureFormat,
+    size: [u32; 2],
+) -> (wgpu::TextureView, wgpu::TextureView, PostPass) {
+    let outgoing = post::create_target(device, size, PASS_FORMAT);
+    let incoming = post::create_texture(device, size, PASS_FORMAT);
+    let blend = PostPass::with_textures(
+        device,
+        "Crossfade",
+        include_str!("crossfade.wgsl"),
+        texture_format,
+        bytemuck::bytes_of(&1.0f32),
+        vec![(
+            wgpu::TextureViewDimension::D2,
+            incoming.create_view(&Default::default()),
+        )],
+    );
+
+    (outgoing, incoming.create_view(&Default::default()), blend)
+}
@@ -0,0 +1,406 @@
+use crate::pipeline::PASS_FORMAT;
+use wgpu::{util::DeviceExt, PipelineCompilationOptions};
+
+/// A full screen pass reading a texture and writing into another one.
+///
+/// The WGSL source only defines `fs_main`, it reads `source` through
+/// `source_sampler` and its own uniforms at binding 2. Extra textures, like a
+/// lookup table, follow from binding 3.
+pub struct PostPass {
+    additive: bool,
+    textures: Vec<wgpu::TextureView>,
+    render_pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    uniform_buffer: wgpu::Buffer,
+}
+
+impl PostPass {
+    pub fn new(
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+        texture_format: wgpu::TextureFormat,
+        uniforms: &[u8],
+    ) -> Self {
+        Self::build(
+            device,
+            label,
+            source,
+            texture_format,
+            uniforms,
+            false,
+            Vec::new(),
+        )
+    }
+
+    /// Also binds `textures`, of the given dimensions, from binding 3.
+    pub fn with_textures(
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+        texture_format: wgpu::TextureFormat,
+        uniforms: &[u8],
+        textures: Vec<(wgpu::TextureViewDimension, wgpu::TextureView)>,
+    ) -> Self {
+        Self::build(
+            device,
+            label,
+            source,
+            texture_format,
+            uniforms,
+            false,
+            textures,
+        )
+    }
+
+    /// Adds its output to the content of the target instead of replacing it.
+    pub fn new_additive(
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+        texture_format: wgpu::TextureFormat,
+        uniforms: &[u8],
+    ) -> Self {
+        Self::build(
+            device,
+            label,
+            source,
+            texture_format,
+            uniforms,
+            true,
+            Vec::new(),
+        )
+    }
+
+    fn build(
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+        texture_format: wgpu::TextureFormat,
+        uniforms: &[u8],
+        additive: bool,
+        textures: Vec<(wgpu::TextureViewDimension, wgpu::TextureView)>,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(
+                format!("{}\n{}", include_str!("post.wgsl"), source).into(),
+            ),
+        });
+
+        // Uniform buffers can not be empty
+        let mut contents = uniforms.to_vec();
+        contents.resize(contents.len().max(16).next_multiple_of(16), 0);
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: &contents,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(label),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let mut entries = vec![
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ];
+        entries.extend(textures.iter().enumerate().map(|(i, (dimension, _))| {
+            wgpu::BindGroupLayoutEntry {
+                binding: 3 + i as u32,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: *dimension,
+                    multisampled: false,
+                },
+                count: None,
+            }
+        }));
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: Some(label),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(label),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: texture_format,
+                    // No blending at all otherwise, Float32 formats can not be blended
+                    blend: additive.then(|| {
+                        let add = wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::One,
+                            dst_factor: wgpu::BlendFactor::One,
+                            operation: wgpu::BlendOperation::Add,
+                        };
+                        wgpu::BlendState {
+                            color: add,
+                            alpha: add,
+                        }
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            additive,
+            textures: textures.into_iter().map(|(_, view)| view).collect(),
+            render_pipeline,
+            bind_group_layout,
+            sampler,
+            uniform_buffer,
+        }
+    }
+
+    pub fn write_uniforms(&self, queue: &wgpu::Queue, uniforms: &[u8]) {
+        queue.write_buffer(&self.uniform_buffer, 0, uniforms);
+    }
+
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::TextureView,
+        target: &wgpu::TextureView,
+    ) {
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(source),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&self.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: self.uniform_buffer.as_entire_binding(),
+            },
+        ];
+        entries.extend(
+            self.textures
+                .iter()
+                .enumerate()
+                .map(|(i, view)| wgpu::BindGroupEntry {
+                    binding: 3 + i as u32,
+                    resource: wgpu::BindingResource::TextureView(view),
+                }),
+        );
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &entries,
+            label: None,
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Post Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: if self.additive {
+                        wgpu::LoadOp::Load
+                    } else {
+                        wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+                    },
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+/// Resolves frames rendered at `factor` times the size of the target, used
+/// for supersampling. A separable Mitchell-Netravali filter reads the samples
+/// within 2 pixels of the target, one axis at a time.
+pub struct Downsample {
+    horizontal: PostPass,
+    vertical: PostPass,
+    // Filtered horizontally only, `factor` times as high as the target
+    intermediate: wgpu::TextureView,
+}
+
+/// Pixels around each pixel of the target read by `Downsample`.
+pub const DOWNSAMPLE_RADIUS: u32 = 2;
+
+impl Downsample {
+    /// With `clamp` the samples are limited to 1 first, which suits outputs
+    /// that clip anyway.
+    pub fn new(
+        device: &wgpu::Device,
+        size: [u32; 2],
+        texture_format: wgpu::TextureFormat,
+        factor: u32,
+        clamp: bool,
+    ) -> Self {
+        let pass = |label, texture_format, clamp: bool, direction: [u32; 2]| {
+            PostPass::new(
+                device,
+                label,
+                include_str!("downsample.wgsl"),
+                texture_format,
+                bytemuck::cast_slice(&[factor, clamp as u32, direction[0], direction[1]]),
+            )
+        };
+
+        Self {
+            horizontal: pass("Downsample Horizontal", PASS_FORMAT, clamp, [1, 0]),
+            vertical: pass("Downsample Vertical", texture_format, false, [0, 1]),
+            intermediate: create_target(device, [size[0], size[1] * factor], PASS_FORMAT),
+        }
+    }
+
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::TextureView,
+        target: &wgpu::TextureView,
+    ) {
+        self.horizontal
+            .encode(device, encoder, source, &self.intermediate);
+        self.vertical
+            .encode(device, encoder, &self.intermediate, target);
+    }
+}
+
+/// Copies a texture of the same size, converting its format.
+pub fn copy(device: &wgpu::Device, texture_format: wgpu::TextureFormat) -> PostPass {
+    PostPass::new(
+        device,
+        "Copy",
+        include_str!("copy.wgsl"),
+        texture_format,
+        &[],
+    )
+}
+
+/// Adds `weight` times the source to the target, used to average sub-frames.
+///
+/// With `clamp` the source is limited to 1 first, like a camera sensor, which
+/// suits outputs that clip anyway.
+pub fn accumulate(
+    device: &wgpu::Device,
+    texture_format: wgpu::TextureFormat,
+    weight: f32,
+    clamp: bool,
+) -> PostPass {
+    PostPass::new_additive(
+        device,
+        "Accumulate",
+        include_str!("accumulate.wgsl"),
+        texture_format,
+        bytemuck::cast_slice(&[weight.to_bits(), clamp as u32]),
+    )
+}
+
+pub fn clear(encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Clear Pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+}
+
+/// Creates a texture that passes can render into and sample from.
+pub fn create_texture(
+    device: &wgpu::Device,
+    size: [u32; 2],
+    texture_format: wgpu::TextureFormat,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        size: wgpu::Extent3d {
+            width: size[0].max(1),
+            height: size[1].max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: texture_format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        label: Some("Post Texture"),
+        view_formats: &[],
+    })
+}
+
+/// View of a new texture from `create_texture`.
+pub fn create_target(
+    device: &wgpu::Device,
+    size: [u32; 2],
+    texture_format: wgpu::TextureFormat,
+) -> wgpu::TextureView {
+    create_texture(device, size, texture_format).create_view(&Default::default())
+}
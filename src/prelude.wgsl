@@ -0,0 +1,54 @@
+struct Infos {
+    dimensions: vec2<f32>,
+    time: f32,
+    speed: f32,
+    // Offset and scale from the render target to the whole image
+    viewport: vec4<f32>,
+    // Energies of the bands of the audio track and its loudness, in [0, 1]
+    bass: f32,
+    mid: f32,
+    treble: f32,
+    volume: f32,
+}
+
+struct Params {
+    values: array<vec4<f32>, 4>,
+}
+
+@group(0) @binding(0) var<uniform> infos: Infos;
+@group(0) @binding(1) var<uniform> params: Params;
+@group(0) @binding(2) var channel_sampler: sampler;
+@group(0) @binding(3) var previous_pass: texture_2d<f32>;
+@group(0) @binding(4) var channel0: texture_2d<f32>;
+@group(0) @binding(5) var channel1: texture_2d<f32>;
+@group(0) @binding(6) var channel2: texture_2d<f32>;
+@group(0) @binding(7) var channel3: texture_2d<f32>;
+// Spectrum in the first row and waveform in the second, 512 texels wide
+@group(0) @binding(8) var audio: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) foo: vec4f,
+    // Position in the whole image, in [-1, 1]
+    @location(0) pos: vec2f,
+    // Position in the render target, use it to sample `previous_pass`
+    @location(1) local: vec2f,
+};
+
+@vertex
+fn vs_main(@location(0) position: vec2<f32>) -> VertexOutput {
+    var out: VertexOutput;
+    out.foo = vec4f(position, 0.0, 1.0);
+    out.pos = infos.viewport.xy + position * infos.viewport.zw;
+    out.local = position;
+    return out;
+}
+
+// Value of the parameter at `index`, use the generated `PARAM_*` constants.
+fn param(index: u32) -> f32 {
+    return params.values[index / 4u][index % 4u];
+}
+
+// Texture coordinates in [0, 1] with the origin at the top left corner.
+fn screen_uv(pos: vec2f) -> vec2f {
+    return pos * vec2f(0.5, -0.5) + 0.5;
+}
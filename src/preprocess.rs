@@ -0,0 +1,238 @@
+use crate::error::{Result, ShaderArtError};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Bundled files, included with `#include "std/<name>"`.
+pub const LIBRARY: &[(&str, &str)] = &[
+    ("std/color.wgsl", include_str!("std/color.wgsl")),
+    ("std/hash.wgsl", include_str!("std/hash.wgsl")),
+    ("std/noise.wgsl", include_str!("std/noise.wgsl")),
+    ("std/palette.wgsl", include_str!("std/palette.wgsl")),
+    ("std/sdf.wgsl", include_str!("std/sdf.wgsl")),
+    ("std/transform.wgsl", include_str!("std/transform.wgsl")),
+    ("std/voronoi.wgsl", include_str!("std/voronoi.wgsl")),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Origin {
+    file: usize,
+    line: usize,
+}
+
+/// Where the lines of a preprocessed source come from, so that the errors
+/// point to the original files.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SourceMap {
+    // Names of the files as shown in the errors
+    names: Vec<String>,
+    lines: Vec<Origin>,
+    files: Vec<PathBuf>,
+}
+
+impl SourceMap {
+    /// File and line of the 1-based `line` of the preprocessed source, `None`
+    /// for sources that were not preprocessed.
+    pub fn locate(&self, line: usize) -> Option<(&str, usize)> {
+        let origin = self.lines.get(line.checked_sub(1)?)?;
+        Some((&self.names[origin.file], origin.line))
+    }
+
+    /// Files read from disk, the shader first then its includes, to watch
+    /// them for changes.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+}
+
+/// Resolves the directives of `source`, named `name` in the errors and read
+/// from `path` if any:
+///
+/// - `#include "file"` relative to the including file, or from the bundled
+///   library for `std/` paths. Each file is included once.
+/// - `#define NAME [value]` and `#undef NAME`, the value replaces the name in
+///   the following lines.
+/// - `#ifdef NAME`, `#ifndef NAME`, `#else` and `#endif`.
+///
+/// `defines` are defined before the first line.
+pub fn preprocess(
+    source: &str,
+    name: &str,
+    path: Option<&Path>,
+    defines: &BTreeMap<String, String>,
+) -> Result<(String, SourceMap)> {
+    let mut preprocessor = Preprocessor {
+        defines: defines.clone(),
+        included: HashSet::new(),
+        code: String::new(),
+        map: SourceMap::default(),
+    };
+    if let Some(path) = path {
+        preprocessor.included.insert(key(path));
+    }
+    preprocessor.process(source, name, path)?;
+
+    Ok((preprocessor.code, preprocessor.map))
+}
+
+struct Preprocessor {
+    defines: BTreeMap<String, String>,
+    // Files already included, by canonical path or library name
+    included: HashSet<String>,
+    code: String,
+    map: SourceMap,
+}
+
+impl Preprocessor {
+    fn process(&mut self, source: &str, name: &str, path: Option<&Path>) -> Result<()> {
+        let file = self.map.names.len();
+        self.map.names.push(name.to_string());
+        if let Some(path) = path {
+            self.map.files.push(path.to_path_buf());
+        }
+
+        // Whether each open conditional keeps its lines, and if it saw `#else`
+        let mut conditions: Vec<(bool, bool)> = Vec::new();
+
+        for (i, line) in source.lines().enumerate() {
+            let error =
+                |message: &str| ShaderArtError::Shader(format!("{}:{}: {}", name, i + 1, message));
+            let active = conditions.iter().all(|(keep, _)| *keep);
+
+            let Some(directive) = line.trim_start().strip_prefix('#') else {
+                if active {
+                    self.code.push_str(&self.substitute(line));
+                    self.code.push('\n');
+                    self.map.lines.push(Origin { file, line: i + 1 });
+                }
+                continue;
+            };
+
+            let (keyword, argument) = directive
+                .trim()
+                .split_once(char::is_whitespace)
+                .map_or((directive.trim(), ""), |(keyword, argument)| {
+                    (keyword, argument.trim())
+                });
+
+            match keyword {
+                "ifdef" | "ifndef" => {
+                    let defined = self.defines.contains_key(identifier(argument, &error)?);
+                    conditions.push((defined == (keyword == "ifdef"), false));
+                }
+                "else" => match conditions.last_mut() {
+                    Some((keep, seen @ false)) => {
+                        *keep = !*keep;
+                        *seen = true;
+                    }
+                    Some(_) => return Err(error("`#else` after `#else`")),
+                    None => return Err(error("`#else` without `#ifdef`")),
+                },
+                "endif" => {
+                    conditions
+                        .pop()
+                        .ok_or_else(|| error("`#endif` without `#ifdef`"))?;
+                }
+                _ if !active => {}
+                "define" => {
+                    let (name, value) = argument
+                        .split_once(char::is_whitespace)
+                        .unwrap_or((argument, ""));
+                    let value = self.substitute(value.trim());
+                    self.defines
+                        .insert(identifier(name, &error)?.to_string(), value);
+                }
+                "undef" => {
+                    self.defines.remove(identifier(argument, &error)?);
+                }
+                "include" => {
+                    let target = argument
+                        .strip_prefix('"')
+                        .and_then(|argument| argument.strip_suffix('"'))
+                        .ok_or_else(|| error("expected `#include \"file\"`"))?;
+                    self.include(target, path, &error)?;
+                }
+                _ => return Err(error(&format!("unknown directive `#{}`", keyword))),
+            }
+        }
+
+        if !conditions.is_empty() {
+            return Err(ShaderArtError::Shader(format!(
+                "{}: missing `#endif`",
+                name
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn include(
+        &mut self,
+        target: &str,
+        from: Option<&Path>,
+        error: &impl Fn(&str) -> ShaderArtError,
+    ) -> Result<()> {
+        if target.starts_with("std/") {
+            let (_, source) = LIBRARY
+                .iter()
+                .find(|(name, _)| *name == target)
+                .ok_or_else(|| error(&format!("`{}` is not in the library", target)))?;
+            if self.included.insert(target.to_string()) {
+                self.process(source, target, None)?;
+            }
+            return Ok(());
+        }
+
+        let directory = from
+            .and_then(Path::parent)
+            .ok_or_else(|| error("only shader files include other files"))?;
+        let path = directory.join(target);
+        if self.included.insert(key(&path)) {
+            let source = std::fs::read_to_string(&path)
+                .map_err(|e| error(&format!("can not include `{}`: {}", path.display(), e)))?;
+            self.process(&source, &path.display().to_string(), Some(&path))?;
+        }
+
+        Ok(())
+    }
+
+    // Replaces the defined names that have a value
+    fn substitute(&self, line: &str) -> String {
+        let mut result = String::with_capacity(line.len());
+        let mut rest = line;
+
+        while let Some(start) = rest.find(|c: char| c.is_ascii_alphanumeric() || c == '_') {
+            result.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            // Numbers like `1e5` are whole words, never names
+            match self.defines.get(word) {
+                Some(value) if !value.is_empty() => result.push_str(value),
+                _ => result.push_str(word),
+            }
+            rest = &rest[end..];
+        }
+        result.push_str(rest);
+
+        result
+    }
+}
+
+fn identifier<'a>(name: &'a str, error: &impl Fn(&str) -> ShaderArtError) -> Result<&'a str> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(name)
+    } else {
+        Err(error(&format!("invalid name `{}`", name)))
+    }
+}
+
+fn key(path: &Path) -> String {
+    path.canonicalize()
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
@@ -0,0 +1,135 @@
+/// Art bundled in the binary, selected with `--preset`.
+#[derive(Debug)]
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Source of the single pass.
+    pub source: &'static str,
+    pub params: &'static [PresetParam],
+}
+
+/// Parameter of a preset, overridden like the other parameters with `--param`.
+#[derive(Debug)]
+pub struct PresetParam {
+    pub name: &'static str,
+    pub default: f32,
+    pub description: &'static str,
+}
+
+/// The bundled shader, used without `--preset` nor `--shader`.
+pub const DEFAULT_PRESET: &str = "fractal-rings";
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "fractal-rings",
+        description: "Glowing rings repeated in a fractal grid",
+        source: include_str!("shader.wgsl"),
+        params: &[],
+    },
+    Preset {
+        name: "plasma",
+        description: "Classic demoscene plasma of interfering sine waves",
+        source: include_str!("presets/plasma.wgsl"),
+        params: &[
+            PresetParam {
+                name: "scale",
+                default: 3.0,
+                description: "Number of waves across the screen",
+            },
+            PresetParam {
+                name: "contrast",
+                default: 1.5,
+                description: "Exponent applied to the colors",
+            },
+        ],
+    },
+    Preset {
+        name: "voronoi",
+        description: "Moving cells colored by their nearest point",
+        source: include_str!("presets/voronoi.wgsl"),
+        params: &[
+            PresetParam {
+                name: "cells",
+                default: 4.0,
+                description: "Number of cells across the height",
+            },
+            PresetParam {
+                name: "edge",
+                default: 0.05,
+                description: "Width of the dark borders",
+            },
+        ],
+    },
+    Preset {
+        name: "spheres",
+        description: "Raymarched flight through an infinite grid of spheres",
+        source: include_str!("presets/spheres.wgsl"),
+        params: &[
+            PresetParam {
+                name: "radius",
+                default: 0.4,
+                description: "Radius of the spheres",
+            },
+            PresetParam {
+                name: "spacing",
+                default: 2.0,
+                description: "Distance between the centers of the spheres",
+            },
+        ],
+    },
+    Preset {
+        name: "warp",
+        description: "Fractal noise warped by itself, like marble or smoke",
+        source: include_str!("presets/warp.wgsl"),
+        params: &[
+            PresetParam {
+                name: "scale",
+                default: 2.0,
+                description: "Size of the features, larger is finer",
+            },
+            PresetParam {
+                name: "warp",
+                default: 4.0,
+                description: "Strength of the distortion",
+            },
+        ],
+    },
+    Preset {
+        name: "kaleidoscope",
+        description: "Rotating rings mirrored into symmetric segments",
+        source: include_str!("presets/kaleidoscope.wgsl"),
+        params: &[
+            PresetParam {
+                name: "segments",
+                default: 6.0,
+                description: "Number of mirrored segments",
+            },
+            PresetParam {
+                name: "zoom",
+                default: 1.5,
+                description: "Magnification of the pattern",
+            },
+        ],
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|preset| preset.name == name)
+}
+
+/// Names, descriptions and parameters of every preset, for the `list` subcommand.
+pub fn describe() -> String {
+    let mut description = String::new();
+
+    for preset in PRESETS {
+        description.push_str(&format!("{:<14} {}\n", preset.name, preset.description));
+        for param in preset.params {
+            description.push_str(&format!(
+                "  {:<12} {} (default {})\n",
+                param.name, param.description, param.default
+            ));
+        }
+    }
+
+    description
+}
@@ -0,0 +1,27 @@
+fn palette(t: f32) -> vec3f {
+    return 0.5 + 0.5 * cos(6.28318 * (t + vec3f(0.263, 0.416, 0.557)));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let aspect_ratio = infos.dimensions.y / infos.dimensions.x;
+    let uv = vec2f(in.pos.x * aspect_ratio, in.pos.y);
+    let t = infos.time;
+
+    // Folds the angle into one mirrored segment
+    let segment = 6.28318 / max(param(PARAM_SEGMENTS), 1.0);
+    var angle = atan2(uv.y, uv.x) + 0.2 * t;
+    angle = angle - segment * floor(angle / segment);
+    angle = abs(angle - 0.5 * segment);
+    let radius = length(uv);
+    var p = radius * vec2f(cos(angle), sin(angle)) * param(PARAM_ZOOM);
+
+    var col = vec3f(0.0);
+    for (var i = 0; i < 4; i++) {
+        p = fract(p * 1.4) - 0.5;
+        let d = abs(sin(length(p) * 10.0 - t) / 10.0);
+        col += palette(radius + f32(i) * 0.3 + 0.5 * t) * pow(0.01 / max(d, 0.0001), 1.2);
+    }
+
+    return vec4f(col * exp(-radius), 1.0);
+}
@@ -0,0 +1,23 @@
+const PI: f32 = 3.14159265;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let aspect_ratio = infos.dimensions.y / infos.dimensions.x;
+    let p = vec2f(in.pos.x * aspect_ratio, in.pos.y) * param(PARAM_SCALE);
+    let t = infos.time;
+
+    var v = sin(p.x + t);
+    v += sin((p.y + t) * 0.5);
+    v += sin((p.x + p.y + t) * 0.5);
+    let c = p + 0.5 * vec2f(sin(t / 5.0), cos(t / 3.0)) * param(PARAM_SCALE);
+    v += sin(sqrt(dot(c, c) + 1.0) + t);
+    v *= 0.5;
+
+    let col = 0.5 + 0.5 * vec3f(
+        sin(PI * v),
+        sin(PI * v + 2.0 * PI / 3.0),
+        sin(PI * v + 4.0 * PI / 3.0),
+    );
+
+    return vec4f(pow(col, vec3f(param(PARAM_CONTRAST))), 1.0);
+}
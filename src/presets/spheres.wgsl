@@ -0,0 +1,56 @@
+// Distance to a grid of spheres
+fn scene(p: vec3f) -> f32 {
+    let spacing = param(PARAM_SPACING);
+    let q = p - spacing * round(p / spacing);
+    return length(q) - param(PARAM_RADIUS);
+}
+
+fn normal(p: vec3f) -> vec3f {
+    let e = vec2f(0.001, 0.0);
+    return normalize(vec3f(
+        scene(p + e.xyy) - scene(p - e.xyy),
+        scene(p + e.yxy) - scene(p - e.yxy),
+        scene(p + e.yyx) - scene(p - e.yyx),
+    ));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let aspect_ratio = infos.dimensions.y / infos.dimensions.x;
+    let uv = vec2f(in.pos.x * aspect_ratio, in.pos.y);
+    let t = infos.time;
+
+    let origin = vec3f(0.5 * sin(t), 0.5 * cos(t), t * 2.0);
+    let direction = normalize(vec3f(uv, 1.5));
+
+    var travelled = 0.0;
+    var hit = false;
+    for (var i = 0; i < 96; i++) {
+        let d = scene(origin + direction * travelled);
+        if d < 0.001 {
+            hit = true;
+            break;
+        }
+        travelled += d;
+        if travelled > 40.0 {
+            break;
+        }
+    }
+
+    let background = vec3f(0.02, 0.02, 0.05);
+    if !hit {
+        return vec4f(background, 1.0);
+    }
+
+    let p = origin + direction * travelled;
+    let n = normal(p);
+    let light = normalize(vec3f(0.6, 0.8, -0.4));
+    let diffuse = max(dot(n, light), 0.0);
+    let specular = pow(max(dot(reflect(-light, n), -direction), 0.0), 32.0);
+    let albedo = 0.5 + 0.5 * cos(6.28318 * (0.1 * p.z + vec3f(0.0, 0.33, 0.67)));
+
+    let col = albedo * (0.1 + diffuse) + specular;
+    let fog = exp(-0.08 * travelled);
+
+    return vec4f(mix(background, col, fog), 1.0);
+}
@@ -0,0 +1,41 @@
+fn hash2(p: vec2f) -> vec2f {
+    let q = vec2f(dot(p, vec2f(127.1, 311.7)), dot(p, vec2f(269.5, 183.3)));
+    return fract(sin(q) * 43758.5453);
+}
+
+fn palette(t: f32) -> vec3f {
+    return 0.5 + 0.5 * cos(6.28318 * (t + vec3f(0.0, 0.33, 0.67)));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let aspect_ratio = infos.dimensions.y / infos.dimensions.x;
+    let p = vec2f(in.pos.x * aspect_ratio, in.pos.y) * param(PARAM_CELLS);
+    let cell = floor(p);
+    let f = fract(p);
+
+    // Distances to the nearest and the second nearest points
+    var d1 = 8.0;
+    var d2 = 8.0;
+    var nearest = vec2f(0.0);
+
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let b = vec2f(f32(x), f32(y));
+            let o = 0.5 + 0.5 * sin(infos.time + 6.28318 * hash2(cell + b));
+            let d = length(b + o - f);
+            if d < d1 {
+                d2 = d1;
+                d1 = d;
+                nearest = cell + b;
+            } else if d < d2 {
+                d2 = d;
+            }
+        }
+    }
+
+    let edge = smoothstep(0.0, param(PARAM_EDGE), d2 - d1);
+    let col = palette(hash2(nearest).x) * (1.0 - 0.5 * d1) * edge;
+
+    return vec4f(col, 1.0);
+}
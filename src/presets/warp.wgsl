@@ -0,0 +1,47 @@
+fn hash(p: vec2f) -> f32 {
+    return fract(sin(dot(p, vec2f(127.1, 311.7))) * 43758.5453);
+}
+
+fn noise(p: vec2f) -> f32 {
+    let i = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+    return mix(
+        mix(hash(i), hash(i + vec2f(1.0, 0.0)), u.x),
+        mix(hash(i + vec2f(0.0, 1.0)), hash(i + vec2f(1.0, 1.0)), u.x),
+        u.y,
+    );
+}
+
+fn fbm(p: vec2f) -> f32 {
+    var value = 0.0;
+    var amplitude = 0.5;
+    var q = p;
+    for (var i = 0; i < 5; i++) {
+        value += amplitude * noise(q);
+        q = mat2x2f(1.6, 1.2, -1.2, 1.6) * q;
+        amplitude *= 0.5;
+    }
+    return value;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let aspect_ratio = infos.dimensions.y / infos.dimensions.x;
+    let p = vec2f(in.pos.x * aspect_ratio, in.pos.y) * param(PARAM_SCALE);
+    let t = infos.time;
+    let warp = param(PARAM_WARP);
+
+    let q = vec2f(fbm(p), fbm(p + vec2f(5.2, 1.3)));
+    let r = vec2f(
+        fbm(p + warp * q + vec2f(1.7, 9.2) + 0.15 * t),
+        fbm(p + warp * q + vec2f(8.3, 2.8) + 0.126 * t),
+    );
+    let f = fbm(p + warp * r);
+
+    var col = mix(vec3f(0.1, 0.3, 0.4), vec3f(0.7, 0.6, 0.5), clamp(f * f * 4.0, 0.0, 1.0));
+    col = mix(col, vec3f(0.0, 0.1, 0.35), clamp(length(q), 0.0, 1.0));
+    col = mix(col, vec3f(0.9, 0.8, 0.6), clamp(r.x, 0.0, 1.0));
+
+    return vec4f(col * (f * f * f + 0.6 * f * f + 0.5 * f), 1.0);
+}
@@ -0,0 +1,592 @@
+use crate::app::RenderOptions;
+use crate::audio::Track;
+use crate::effects::Effect;
+use crate::gpu::GpuOptions;
+use crate::grade::{Grading, Lut, ToneMapping};
+use crate::pipeline::{
+    self, Compute, Scene, MAX_INPUTS, MAX_PARAMS, MAX_STORAGE_BUFFERS, MAX_STORAGE_BUFFER_SIZE,
+    MAX_WORKGROUP_INVOCATIONS,
+};
+use crate::post::DOWNSAMPLE_RADIUS;
+use crate::preprocess::{self, SourceMap};
+use crate::presets;
+use crate::resolution::parse_resolution;
+use crate::sound::{Sound, SAMPLE_RATE};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Everything needed to reproduce a render, usually stored as `shader-art.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Project {
+    /// Shader passes, rendered in order. The bundled shader is used when empty.
+    pub shaders: Vec<PathBuf>,
+    /// Bundled art rendered instead of the default one, see the `list` subcommand.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    /// Images bound as `channel0` to `channel3`.
+    pub inputs: Vec<PathBuf>,
+    /// WAV track analyzed at each frame for the `audio` texture and uniforms.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio: Option<PathBuf>,
+    /// Sound shader rendered to the WAV outputs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sound: Option<PathBuf>,
+    /// Sample rate of the WAV outputs in Hz.
+    pub sample_rate: u32,
+    /// Compute shader rendering before the passes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compute: Option<PathBuf>,
+    pub workgroup_size: [u32; 2],
+    /// Sizes in bytes of the storage buffers of the compute shader.
+    pub storage_buffers: Vec<u64>,
+    /// `WxH`, a preset like `1080p`, or `Wx?` completed with the aspect ratio.
+    pub resolution: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aspect: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f32>,
+    /// Largest side of the tiles used to render stills.
+    pub tile_size: u32,
+    /// Shader time of the first frame, also the time of stills.
+    pub time: f32,
+    /// Length of the exported animation in seconds.
+    pub duration: f64,
+    pub fps: u16,
+    /// Number of exported frames, takes precedence over the duration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frames: Option<usize>,
+    /// Supersampling factor of the exports, 1 disables it.
+    pub ssaa: u32,
+    /// Number of sub-frames averaged into each exported frame, 1 disables it.
+    pub motion_blur: u32,
+    /// Part of the frame interval the shutter is open, in degrees.
+    pub shutter_angle: f32,
+    /// Precision of the EXR outputs.
+    pub precision: Precision,
+    /// Encoding quality from 1 to 30.
+    pub quality: u8,
+    /// Forces the format of the outputs instead of deducing it from their extension.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<OutputFormat>,
+    /// Files to export.
+    pub outputs: Vec<PathBuf>,
+    pub preview: Preview,
+    pub serve: Serve,
+    /// Post effects, applied in order before the grading.
+    pub effects: Vec<Effect>,
+    pub grade: Grade,
+    /// Adapter selection, for the preview and the exports.
+    pub gpu: GpuOptions,
+    /// Renders the exports on the CPU, only for the bundled shader.
+    pub cpu: bool,
+    pub params: BTreeMap<String, f32>,
+    /// Names defined before the first line of the shaders, for `#ifdef`.
+    pub defines: BTreeMap<String, String>,
+}
+
+/// Color grading of the preview and the exports.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Grade {
+    pub tone_mapping: ToneMapping,
+    /// Exposure adjustment in stops.
+    pub exposure: f64,
+    pub gamma: f64,
+    pub saturation: f64,
+    /// `.cube` 3D LUT applied after the tone mapping.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lut: Option<PathBuf>,
+}
+
+/// Settings of the preview window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Preview {
+    /// Initial window size, chosen by the platform when missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<String>,
+    pub vsync: bool,
+    pub fullscreen: bool,
+    /// Presets or shader files shown in turn, switched with N and P.
+    pub playlist: Vec<String>,
+    /// Seconds each playlist entry is shown, only the keys switch them when missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<f64>,
+    /// Length of the crossfade between two playlist entries in seconds.
+    pub crossfade: f64,
+    /// UDP address listening to OSC messages, like `0.0.0.0:9000`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub osc: Option<String>,
+}
+
+/// Settings of the HTTP server of the `serve` subcommand.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Serve {
+    /// Address of the server, only local clients reach the default one.
+    pub listen: String,
+    /// Directory of the files written by the jobs, their outputs are relative
+    /// paths inside it. `renders` in the working directory when missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Gif,
+    /// OpenEXR image sequence, keeps the values above 1.
+    Exr,
+    /// Stereo audio rendered by the sound shader.
+    Wav,
+}
+
+/// Precision of the float outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    Half,
+    Full,
+}
+
+impl Default for Project {
+    fn default() -> Self {
+        Self {
+            shaders: Vec::new(),
+            preset: None,
+            compute: None,
+            workgroup_size: [8, 8],
+            storage_buffers: Vec::new(),
+            inputs: Vec::new(),
+            audio: None,
+            sound: None,
+            sample_rate: SAMPLE_RATE,
+            resolution: String::from("512x512"),
+            aspect: None,
+            tile_size: 2048,
+            speed: None,
+            time: 1.0,
+            duration: 3.0,
+            fps: 20,
+            frames: None,
+            ssaa: 1,
+            motion_blur: 1,
+            shutter_angle: 180.0,
+            precision: Precision::Half,
+            quality: 16,
+            format: None,
+            outputs: Vec::new(),
+            preview: Preview::default(),
+            serve: Serve::default(),
+            effects: Vec::new(),
+            grade: Grade::default(),
+            gpu: GpuOptions::default(),
+            cpu: false,
+            params: BTreeMap::new(),
+            defines: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for Preview {
+    fn default() -> Self {
+        Self {
+            size: None,
+            vsync: true,
+            fullscreen: false,
+            playlist: Vec::new(),
+            interval: None,
+            crossfade: 1.0,
+            osc: None,
+        }
+    }
+}
+
+impl Default for Serve {
+    fn default() -> Self {
+        Self {
+            listen: String::from("127.0.0.1:8080"),
+            output_dir: None,
+        }
+    }
+}
+
+impl Default for Grade {
+    fn default() -> Self {
+        Self {
+            tone_mapping: ToneMapping::None,
+            exposure: 0.0,
+            gamma: 1.0,
+            saturation: 1.0,
+            lut: None,
+        }
+    }
+}
+
+impl OutputFormat {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase());
+
+        match extension.as_deref() {
+            Some("gif") => Ok(Self::Gif),
+            Some("exr") => Ok(Self::Exr),
+            Some("wav") => Ok(Self::Wav),
+            _ => bail!("Unsupported output format for `{}`", path.display()),
+        }
+    }
+}
+
+impl Project {
+    /// Reads a project file, relative paths are resolved against its directory.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Can not read the project file `{}`", path.display()))?;
+        let mut project: Self = toml::from_str(&content)
+            .with_context(|| format!("Invalid project file `{}`", path.display()))?;
+
+        if let Some(root) = path.parent() {
+            for path in project
+                .shaders
+                .iter_mut()
+                .chain(project.inputs.iter_mut())
+                .chain(project.outputs.iter_mut())
+                .chain(project.audio.iter_mut())
+                .chain(project.sound.iter_mut())
+                .chain(project.compute.iter_mut())
+                .chain(project.grade.lut.iter_mut())
+                .chain(project.serve.output_dir.iter_mut())
+            {
+                *path = root.join(&*path);
+            }
+
+            for entry in &mut project.preview.playlist {
+                if presets::find(entry).is_none() {
+                    *entry = root.join(&*entry).to_string_lossy().into_owned();
+                }
+            }
+        }
+
+        Ok(project)
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(self).with_context(|| "Can not serialize the project")
+    }
+
+    pub fn is_export(&self) -> bool {
+        !self.outputs.is_empty()
+    }
+
+    /// Defaults to a faster animation for exports, since they only last a few seconds.
+    pub fn speed(&self, export: bool) -> f32 {
+        self.speed.unwrap_or(if export { 10.0 } else { 1.0 })
+    }
+
+    /// Shader time between two exported frames.
+    pub fn frame_interval(&self) -> f32 {
+        pipeline::frame_interval(self.speed(true), self.fps.max(1).into())
+    }
+
+    /// Shader time of the exported frame `frame`, the first one is a frame
+    /// after `time`.
+    pub fn frame_time(&self, frame: usize) -> f32 {
+        self.time + (frame + 1) as f32 * self.frame_interval()
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+            .unwrap_or((self.duration * self.fps as f64).round() as usize)
+            .max(1)
+    }
+
+    pub fn resolution(&self) -> Result<[u32; 2]> {
+        parse_resolution(&self.resolution, self.aspect.as_deref())
+    }
+
+    /// Rendering settings, `float` selects a format that keeps the values above 1.
+    pub fn render_options(&self, float: bool) -> RenderOptions {
+        RenderOptions {
+            ssaa: self.ssaa,
+            motion_blur: self.motion_blur,
+            shutter_angle: self.shutter_angle,
+            fps: self.fps.max(1).into(),
+            format: match (float, self.precision) {
+                (false, _) => wgpu::TextureFormat::Rgba8UnormSrgb,
+                (true, Precision::Half) => wgpu::TextureFormat::Rgba16Float,
+                (true, Precision::Full) => wgpu::TextureFormat::Rgba32Float,
+            },
+            gpu: self.gpu.clone(),
+        }
+    }
+
+    /// Size of the tiles rendering stills of `resolution`, a single one with
+    /// a compute shader. The supersampled tiles fit in textures of
+    /// `max_dimension` pixels.
+    pub fn tile(&self, resolution: [u32; 2], max_dimension: u32) -> Result<[u32; 2]> {
+        if self.compute.is_some() {
+            return Ok(resolution);
+        }
+
+        let largest = self.tile_size.min(max_dimension / self.ssaa.max(1)).max(1);
+        let tile = resolution.map(|side| side.min(largest));
+
+        let apron = self.apron(resolution);
+        // The sides split in several tiles keep a part of each after the apron
+        let too_small = tile
+            .iter()
+            .zip(resolution)
+            .any(|(tile, side)| *tile < side && apron as u64 * 2 >= *tile as u64);
+        if too_small {
+            bail!(
+                "The effects read {} pixels around each pixel, more than tiles of {} pixels allow, raise the tile size",
+                apron,
+                largest
+            );
+        }
+
+        Ok(tile)
+    }
+
+    /// Pixels rendered around the tiles for the downsampling and the effects
+    /// reading their neighbors.
+    pub fn apron(&self, resolution: [u32; 2]) -> u32 {
+        let downsample = if self.ssaa > 1 { DOWNSAMPLE_RADIUS } else { 0 };
+        self.effects
+            .iter()
+            .map(|effect| effect.apron(resolution))
+            .fold(downsample, u32::saturating_add)
+    }
+
+    pub fn output_format(&self, path: &Path) -> Result<OutputFormat> {
+        match self.format {
+            Some(format) => Ok(format),
+            None => OutputFormat::from_path(path),
+        }
+    }
+
+    /// Scenes of the playlist, each entry replaces the preset or the shaders.
+    pub fn playlist(&self) -> Result<Vec<Scene>> {
+        self.preview
+            .playlist
+            .iter()
+            .map(|entry| {
+                let mut project = self.clone();
+                if presets::find(entry).is_some() {
+                    project.preset = Some(entry.clone());
+                    project.shaders.clear();
+                } else {
+                    project.preset = None;
+                    project.shaders = vec![PathBuf::from(entry)];
+                }
+                project
+                    .scene()
+                    .with_context(|| format!("Invalid playlist entry `{}`", entry))
+            })
+            .collect()
+    }
+
+    /// Decodes the audio track if any.
+    pub fn track(&self) -> Result<Option<Track>> {
+        self.audio.as_deref().map(Track::load).transpose()
+    }
+
+    /// Loads the sound shader if any.
+    pub fn sound(&self) -> Result<Option<Sound>> {
+        let Some(path) = &self.sound else {
+            return Ok(None);
+        };
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Can not read the sound shader `{}`", path.display()))?;
+        let (source, source_map) = self.preprocess(&source, path)?;
+
+        Ok(Some(Sound { source, source_map }))
+    }
+
+    /// Number of samples of the WAV outputs, as long as the animation.
+    pub fn samples(&self) -> usize {
+        (self.frames() as u64 * self.sample_rate as u64 / self.fps.max(1) as u64) as usize
+    }
+
+    // Resolves the directives of a shader file
+    fn preprocess(&self, source: &str, path: &Path) -> Result<(String, SourceMap)> {
+        let name = path.display().to_string();
+        Ok(preprocess::preprocess(
+            source,
+            &name,
+            Some(path),
+            &self.defines,
+        )?)
+    }
+
+    /// Loads the shaders and the inputs from disk.
+    pub fn scene(&self) -> Result<Scene> {
+        if self.inputs.len() > MAX_INPUTS {
+            bail!("At most {} inputs are supported", MAX_INPUTS);
+        }
+
+        for name in self.params.keys() {
+            let valid = name.starts_with(|c: char| c.is_ascii_alphabetic())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                bail!("Invalid parameter name `{}`", name);
+            }
+        }
+
+        for name in self.defines.keys() {
+            let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                bail!("Invalid define `{}`", name);
+            }
+        }
+
+        let mut scene = Scene::default();
+
+        if let Some(path) = &self.compute {
+            let [x, y] = self.workgroup_size;
+            if x == 0 || y == 0 || x.saturating_mul(y) > MAX_WORKGROUP_INVOCATIONS {
+                bail!(
+                    "The workgroup size {}x{} must be positive with at most {} invocations",
+                    x,
+                    y,
+                    MAX_WORKGROUP_INVOCATIONS
+                );
+            }
+
+            if self.storage_buffers.len() > MAX_STORAGE_BUFFERS {
+                bail!(
+                    "At most {} storage buffers are supported",
+                    MAX_STORAGE_BUFFERS
+                );
+            }
+
+            for size in &self.storage_buffers {
+                if *size == 0 || size % 4 != 0 || *size > MAX_STORAGE_BUFFER_SIZE {
+                    bail!(
+                        "Invalid storage buffer size {}, expected a multiple of 4 up to {}",
+                        size,
+                        MAX_STORAGE_BUFFER_SIZE
+                    );
+                }
+            }
+
+            let source = std::fs::read_to_string(path)
+                .with_context(|| format!("Can not read the compute shader `{}`", path.display()))?;
+            let (source, source_map) = self.preprocess(&source, path)?;
+            scene.compute = Some(Compute {
+                source,
+                workgroup_size: self.workgroup_size,
+                buffers: self.storage_buffers.clone(),
+                source_map,
+            });
+
+            // Shows the output of the compute shader unless passes process it
+            scene.passes = vec![include_str!("display.wgsl").to_string()];
+        }
+
+        let preset = match &self.preset {
+            Some(_) if !self.shaders.is_empty() => bail!("Use either a preset or shaders"),
+            Some(name) => Some(
+                presets::find(name)
+                    .with_context(|| format!("Unknown preset `{}`, `list` shows them", name))?,
+            ),
+            None => None,
+        };
+
+        scene.bundled = self.compute.is_none()
+            && self.shaders.is_empty()
+            && preset.is_none_or(|preset| preset.name == presets::DEFAULT_PRESET);
+
+        if let Some(preset) = preset {
+            let (source, source_map) =
+                preprocess::preprocess(preset.source, preset.name, None, &self.defines)?;
+            scene.passes = vec![source];
+            scene.source_maps = vec![source_map];
+        }
+
+        if !self.shaders.is_empty() {
+            (scene.passes, scene.source_maps) = self
+                .shaders
+                .iter()
+                .map(|path| {
+                    let source = std::fs::read_to_string(path)
+                        .with_context(|| format!("Can not read the shader `{}`", path.display()))?;
+                    self.preprocess(&source, path)
+                })
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .unzip();
+        }
+
+        scene.inputs = self
+            .inputs
+            .iter()
+            .map(|path| {
+                Ok(image::open(path)
+                    .with_context(|| format!("Can not read the input `{}`", path.display()))?
+                    .to_rgba8())
+            })
+            .collect::<Result<_>>()?;
+
+        // Defaults of the preset, overridden by the project
+        scene.params = preset
+            .map(|preset| preset.params)
+            .unwrap_or_default()
+            .iter()
+            .map(|param| (param.name.to_string(), param.default))
+            .collect();
+        for (name, value) in &self.params {
+            match scene.params.iter_mut().find(|(n, _)| n == name) {
+                Some((_, default)) => *default = *value,
+                None => scene.params.push((name.clone(), *value)),
+            }
+        }
+        // Each parameter is a `PARAM_<NAME>` constant of the shaders
+        for (i, (name, _)) in scene.params.iter().enumerate() {
+            if let Some((other, _)) = scene.params[..i]
+                .iter()
+                .find(|(other, _)| other.to_uppercase() == name.to_uppercase())
+            {
+                bail!(
+                    "The parameters `{}` and `{}` would both be `PARAM_{}`, rename one",
+                    other,
+                    name,
+                    name.to_uppercase()
+                );
+            }
+        }
+
+        // The preset defaults count too
+        if scene.params.len() > MAX_PARAMS {
+            bail!(
+                "At most {} parameters are supported, with the ones of the preset",
+                MAX_PARAMS
+            );
+        }
+
+        for effect in &self.effects {
+            effect.validate()?;
+        }
+        scene.effects = self.effects.clone();
+
+        if self.grade.gamma <= 0.0 {
+            bail!("The gamma must be positive");
+        }
+
+        scene.grading = Grading {
+            tone_mapping: self.grade.tone_mapping,
+            exposure: self.grade.exposure as f32,
+            gamma: self.grade.gamma as f32,
+            saturation: self.grade.saturation as f32,
+            lut: self.grade.lut.as_deref().map(Lut::load).transpose()?,
+        };
+
+        Ok(scene)
+    }
+}
@@ -0,0 +1,202 @@
+use crate::error::ShaderArtError;
+use anyhow::{bail, Context, Result};
+
+/// Named resolutions as `(name, width, height)`.
+pub const PRESETS: &[(&str, u32, u32)] = &[
+    ("480p", 854, 480),
+    ("720p", 1280, 720),
+    ("1080p", 1920, 1080),
+    ("1440p", 2560, 1440),
+    ("4k", 3840, 2160),
+    ("8k", 7680, 4320),
+    ("square-512", 512, 512),
+    ("square-1080", 1080, 1080),
+    ("square-2048", 2048, 2048),
+    ("instagram-post", 1080, 1350),
+    ("instagram-story", 1080, 1920),
+];
+
+/// Parses `WxH`, a preset name, or `Wx?` and `?xH` completed with the aspect ratio.
+pub fn parse_resolution(resolution: &str, aspect: Option<&str>) -> Result<[u32; 2]> {
+    let resolution = resolution.trim().to_lowercase();
+
+    let [width, height] =
+        if let Some((_, width, height)) = PRESETS.iter().find(|(name, _, _)| *name == resolution) {
+            if aspect.is_some() {
+                bail!(
+                    "The aspect ratio can not be combined with the preset `{}`",
+                    resolution
+                );
+            }
+            [*width, *height]
+        } else {
+            let (width, height) = resolution.split_once('x').with_context(|| {
+                format!(
+                    "Invalid resolution `{}`, expected `widthxheight` or one of: {}",
+                    resolution,
+                    PRESETS
+                        .iter()
+                        .map(|(name, _, _)| *name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })?;
+
+            match (width, height) {
+                ("?", "?") => bail!("Only one side of the resolution can be deduced"),
+                ("?", height) => {
+                    let height = parse_side(height, "height")?;
+                    let [w, h] = parse_aspect(aspect)?;
+                    [deduce(height, w / h)?, height]
+                }
+                (width, "?") => {
+                    let width = parse_side(width, "width")?;
+                    let [w, h] = parse_aspect(aspect)?;
+                    [width, deduce(width, h / w)?]
+                }
+                (width, height) => {
+                    if aspect.is_some() {
+                        bail!("The aspect ratio requires `?` for the width or the height");
+                    }
+                    [parse_side(width, "width")?, parse_side(height, "height")?]
+                }
+            }
+        };
+
+    if width == 0 || height == 0 {
+        bail!("The resolution {}x{} is empty", width, height);
+    }
+
+    Ok([width, height])
+}
+
+/// Fails when the GPU textures can not hold `size`.
+pub fn check_limit(size: [u32; 2], max_dimension: u32) -> crate::error::Result<()> {
+    if size[0] > max_dimension || size[1] > max_dimension {
+        return Err(ShaderArtError::Unsupported(format!(
+            "The resolution {}x{} exceeds the maximum texture size {}x{} of the GPU",
+            size[0], size[1], max_dimension, max_dimension
+        )));
+    }
+    Ok(())
+}
+
+// The side missing from `?` for the known one
+fn deduce(side: u32, ratio: f64) -> Result<u32> {
+    let deduced = (side as f64 * ratio).round();
+    if deduced > u32::MAX as f64 {
+        bail!("The deduced side of the resolution is too large");
+    }
+    Ok(deduced as u32)
+}
+
+fn parse_side(value: &str, side: &str) -> Result<u32> {
+    let value: u32 = value
+        .parse()
+        .with_context(|| format!("Invalid {} value `{}`", side, value))?;
+    if value == 0 {
+        bail!("The {} must be greater than 0", side);
+    }
+    Ok(value)
+}
+
+// `16:9` or `1.777` as `[width, height]`
+fn parse_aspect(aspect: Option<&str>) -> Result<[f64; 2]> {
+    let aspect = aspect.context("The aspect ratio is required to deduce `?`")?;
+
+    let ratio = match aspect.split_once(':') {
+        Some((w, h)) => [w.trim().parse::<f64>(), h.trim().parse::<f64>()],
+        None => [aspect.trim().parse::<f64>(), Ok(1.0)],
+    };
+
+    match ratio {
+        [Ok(w), Ok(h)] if w > 0.0 && h > 0.0 && w.is_finite() && h.is_finite() => Ok([w, h]),
+        _ => bail!("Invalid aspect ratio `{}`, expected `width:height`", aspect),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets() {
+        assert_eq!(parse_resolution("1080p", None).unwrap(), [1920, 1080]);
+        assert_eq!(parse_resolution(" 4K ", None).unwrap(), [3840, 2160]);
+        assert_eq!(
+            parse_resolution("instagram-story", None).unwrap(),
+            [1080, 1920]
+        );
+        assert!(parse_resolution("1080p", Some("16:9")).is_err());
+    }
+
+    #[test]
+    fn width_and_height() {
+        assert_eq!(parse_resolution("640x480", None).unwrap(), [640, 480]);
+        assert_eq!(parse_resolution("640X480", None).unwrap(), [640, 480]);
+        assert!(parse_resolution("640x480", Some("16:9")).is_err());
+    }
+
+    #[test]
+    fn deduced_sides() {
+        assert_eq!(
+            parse_resolution("1920x?", Some("16:9")).unwrap(),
+            [1920, 1080]
+        );
+        assert_eq!(
+            parse_resolution("?x1080", Some("16:9")).unwrap(),
+            [1920, 1080]
+        );
+        assert_eq!(parse_resolution("100x?", Some("2")).unwrap(), [100, 50]);
+        assert!(parse_resolution("1920x?", None).is_err());
+        assert!(parse_resolution("?x?", Some("1:1")).is_err());
+        for aspect in ["0:1", "16:", "wide", "inf:1", "-1"] {
+            assert!(
+                parse_resolution("1920x?", Some(aspect)).is_err(),
+                "{}",
+                aspect
+            );
+        }
+    }
+
+    #[test]
+    fn empty_and_overflowing_sides() {
+        assert!(parse_resolution("0x480", None).is_err());
+        assert!(parse_resolution("640x0", None).is_err());
+        assert!(parse_resolution("0x?", Some("1:1")).is_err());
+        // Rounded down to an empty height
+        assert!(parse_resolution("1x?", Some("1000:1")).is_err());
+        assert!(parse_resolution("4294967296x480", None).is_err());
+        assert!(parse_resolution("4000000000x?", Some("1:2")).is_err());
+    }
+
+    #[test]
+    fn garbage() {
+        for resolution in [
+            "",
+            "x",
+            "640",
+            "640x",
+            "x480",
+            "640x480x2",
+            "axb",
+            "-640x480",
+        ] {
+            assert!(
+                parse_resolution(resolution, None).is_err(),
+                "{}",
+                resolution
+            );
+        }
+    }
+
+    #[test]
+    fn texture_limit() {
+        assert!(check_limit([8192, 8192], 8192).is_ok());
+        assert!(matches!(
+            check_limit([8193, 100], 8192),
+            Err(ShaderArtError::Unsupported(_))
+        ));
+        assert!(check_limit([100, 16384], 8192).is_err());
+    }
+}
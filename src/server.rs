@@ -0,0 +1,565 @@
+use crate::error::{Result, ShaderArtError};
+use crate::offscreen::OffscreenRenderer;
+use crate::pixels;
+use crate::project::Project;
+use crate::ui;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+// Quality of the frames of the stream
+const JPEG_QUALITY: u8 = 80;
+
+// Longest wait of a stream for a frame before checking that the server runs
+const FRAME_TIMEOUT: Duration = Duration::from_secs(1);
+
+const BOUNDARY: &str = "frame";
+
+// Threads answering the requests, the streams have their own
+const WORKERS: usize = 4;
+
+// Streams watched at once, more are refused
+const MAX_STREAMS: usize = 8;
+
+// Jobs waiting while one renders, more are refused
+const MAX_QUEUED_JOBS: usize = 4;
+
+// How often the idle threads check that the server still runs
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What the render node shows, read and written by the JSON API.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Control {
+    pub time: f32,
+    pub speed: f32,
+    pub params: BTreeMap<String, f32>,
+}
+
+// Body of `POST /state`, the missing fields are kept
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Update {
+    time: Option<f32>,
+    speed: Option<f32>,
+    params: BTreeMap<String, f32>,
+}
+
+// Body of `POST /still` and `POST /export`, the project is used for the rest
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JobRequest {
+    outputs: Vec<PathBuf>,
+    time: Option<f32>,
+    resolution: Option<String>,
+    duration: Option<f64>,
+    frames: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobKind {
+    Still,
+    Export,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+/// A still or an export rendered in the background with the state at the
+/// time of the request. The jobs render one at a time, in order.
+#[derive(Clone, Debug, Serialize)]
+pub struct Job {
+    pub id: usize,
+    pub kind: JobKind,
+    pub outputs: Vec<PathBuf>,
+    pub status: JobStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// The last frame of the stream, `index` counts the frames
+#[derive(Default)]
+struct Latest {
+    index: u64,
+    jpeg: Arc<Vec<u8>>,
+}
+
+// A job waiting for the previous ones, with the project it renders
+struct Queued {
+    id: usize,
+    kind: JobKind,
+    project: Project,
+}
+
+struct Shared {
+    project: Project,
+    control: Mutex<Control>,
+    latest: Mutex<Latest>,
+    frame_ready: Condvar,
+    // Frames are only rendered while someone watches the stream
+    viewers: AtomicUsize,
+    jobs: Mutex<Vec<Job>>,
+    queue: mpsc::SyncSender<Queued>,
+    stopped: AtomicBool,
+}
+
+// The locks are only poisoned by a panic, which already stops the server
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// An HTTP server controlling a headless renderer, with JSON endpoints and
+/// an MJPEG stream of the frames:
+///
+/// - `GET /state` and `POST /state` read and change the time, the speed and
+///   the parameters.
+/// - `POST /still` and `POST /export` start a job writing `outputs` in the
+///   output directory of the project, `GET /jobs` and `GET /jobs/<id>`
+///   follow them.
+/// - `GET /stream.mjpeg` streams the frames at the frame rate of the project.
+///
+/// The bodies of the `POST` requests must be `application/json`.
+pub struct Server {
+    http: Arc<tiny_http::Server>,
+    shared: Arc<Shared>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl Server {
+    /// Listens on `address` like `127.0.0.1:8080`, port 0 picks a free one.
+    /// The renderer is created before, so the errors of the scene are
+    /// returned here.
+    pub fn start(project: &Project, address: &str) -> Result<Self> {
+        let scene = project.scene()?;
+        let (queue, queued) = mpsc::sync_channel(MAX_QUEUED_JOBS);
+        let shared = Arc::new(Shared {
+            project: project.clone(),
+            control: Mutex::new(Control {
+                time: project.time,
+                speed: project.speed(false),
+                params: scene.params.iter().cloned().collect(),
+            }),
+            latest: Mutex::default(),
+            frame_ready: Condvar::new(),
+            viewers: AtomicUsize::new(0),
+            jobs: Mutex::default(),
+            queue,
+            stopped: AtomicBool::new(false),
+        });
+
+        let (created, creation) = mpsc::channel();
+        let render = {
+            let shared = shared.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = render(&shared, &created) {
+                    // After the creation, the streams end with the renderer
+                    if let Err(mpsc::SendError(Err(e))) = created.send(Err(e)) {
+                        eprintln!("The renderer stopped: {}", e);
+                        shared.stopped.store(true, Ordering::Relaxed);
+                        shared.frame_ready.notify_all();
+                    }
+                }
+            })
+        };
+        creation
+            .recv()
+            .unwrap_or_else(|_| Err(ShaderArtError::Device("The renderer stopped".into())))?;
+
+        let http = tiny_http::Server::http(address).map_err(|e| {
+            std::io::Error::other(format!(
+                "Can not listen to HTTP requests on `{}`: {}",
+                address, e
+            ))
+        })?;
+        let http = Arc::new(http);
+
+        let mut threads = vec![render];
+        threads.extend((0..WORKERS).map(|_| {
+            let http = http.clone();
+            let shared = shared.clone();
+            std::thread::spawn(move || {
+                while !shared.stopped.load(Ordering::Relaxed) {
+                    match http.recv_timeout(POLL_INTERVAL) {
+                        Ok(Some(request)) => handle(&shared, request),
+                        Ok(None) => {}
+                        Err(e) => eprintln!("Can not receive an HTTP request: {}", e),
+                    }
+                }
+            })
+        }));
+        threads.push({
+            let shared = shared.clone();
+            std::thread::spawn(move || run_jobs(&shared, &queued))
+        });
+
+        Ok(Self {
+            http,
+            shared,
+            threads,
+        })
+    }
+
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.http.server_addr().to_ip()
+    }
+
+    /// Serves until the process ends.
+    pub fn wait(mut self) {
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::Relaxed);
+        self.shared.frame_ready.notify_all();
+    }
+}
+
+// Renders the frames of the stream at the frame rate of the project, blocking
+// its thread between the frames
+fn render(shared: &Shared, created: &mpsc::Sender<Result<()>>) -> Result<()> {
+    let project = &shared.project;
+    let resolution = project.resolution()?;
+    let options = project.render_options(false);
+    let mut renderer = pollster::block_on(OffscreenRenderer::new(
+        project.speed(false),
+        resolution,
+        &project.scene()?,
+        &options,
+    ))?;
+    let _ = created.send(Ok(()));
+
+    let interval = Duration::from_secs_f64(1.0 / project.fps.max(1) as f64);
+    let mut next = Instant::now();
+    while !shared.stopped.load(Ordering::Relaxed) {
+        let time = {
+            let mut control = lock(&shared.control);
+            renderer.set_speed(control.speed);
+            for (name, value) in &control.params {
+                renderer.set_param(name, *value);
+            }
+            let time = control.time;
+            control.time += renderer.frame_interval();
+            time
+        };
+
+        if shared.viewers.load(Ordering::Relaxed) > 0 {
+            let frame = renderer.render_frame_at_blocking(time)?;
+            let rgb = pixels::to_rgba8(&frame.data, frame.format)?
+                .chunks_exact(4)
+                .flat_map(|pixel| &pixel[..3])
+                .copied()
+                .collect::<Vec<_>>();
+            let mut jpeg = Vec::new();
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY).encode(
+                &rgb,
+                frame.width,
+                frame.height,
+                image::ExtendedColorType::Rgb8,
+            )?;
+
+            let mut latest = lock(&shared.latest);
+            latest.index += 1;
+            latest.jpeg = Arc::new(jpeg);
+            shared.frame_ready.notify_all();
+        }
+
+        next += interval;
+        match next.checked_duration_since(Instant::now()) {
+            Some(delay) => std::thread::sleep(delay),
+            // Too slow for the frame rate, the time keeps flowing per frame
+            None => next = Instant::now(),
+        }
+    }
+
+    Ok(())
+}
+
+type Response = tiny_http::Response<std::io::Cursor<Vec<u8>>>;
+
+fn json(status: u16, value: &impl Serialize) -> Response {
+    let body = serde_json::to_vec(value).unwrap_or_default();
+    tiny_http::Response::from_data(body)
+        .with_status_code(status)
+        .with_header(
+            tiny_http::Header::from_bytes("Content-Type", "application/json")
+                .expect("valid header"),
+        )
+}
+
+fn error(status: u16, message: impl std::fmt::Display) -> Response {
+    json(status, &serde_json::json!({ "error": message.to_string() }))
+}
+
+fn handle(shared: &Arc<Shared>, mut request: tiny_http::Request) {
+    use tiny_http::Method::{Get, Post};
+
+    let path = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let mut body = Vec::new();
+    if let Err(e) = request.as_reader().read_to_end(&mut body) {
+        let _ = request.respond(error(400, e));
+        return;
+    }
+
+    // Browsers only send JSON cross origin after a preflight, which the
+    // server does not answer
+    let json_body = request.headers().iter().any(|header| {
+        header.field.equiv("Content-Type")
+            && header
+                .value
+                .as_str()
+                .split(';')
+                .next()
+                .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
+    });
+    if *request.method() == Post && !json_body {
+        let _ = request.respond(error(415, "The body must be `application/json`"));
+        return;
+    }
+
+    let response = match (request.method(), path.as_str()) {
+        (Get, "/stream.mjpeg") => {
+            let watched =
+                shared
+                    .viewers
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |viewers| {
+                        (viewers < MAX_STREAMS).then_some(viewers + 1)
+                    });
+            if watched.is_err() {
+                let _ = request.respond(error(503, "Too many streams are watched"));
+                return;
+            }
+            // Streams last as long as the client watches
+            let shared = shared.clone();
+            std::thread::spawn(move || stream(&shared, request));
+            return;
+        }
+        (Get, "/state") => json(200, &*lock(&shared.control)),
+        (Post, "/state") => update(shared, &body),
+        (Post, "/still") => start_job(shared, JobKind::Still, &body),
+        (Post, "/export") => start_job(shared, JobKind::Export, &body),
+        (Get, "/jobs") => json(200, &*lock(&shared.jobs)),
+        (Get, path) if path.starts_with("/jobs/") => {
+            let job = path["/jobs/".len()..]
+                .parse::<usize>()
+                .ok()
+                .and_then(|id| lock(&shared.jobs).get(id).cloned());
+            match job {
+                Some(job) => json(200, &job),
+                None => error(404, format!("No job at `{}`", path)),
+            }
+        }
+        (_, "/stream.mjpeg" | "/state" | "/still" | "/export" | "/jobs") => error(
+            405,
+            format!("`{}` does not accept {}", path, request.method()),
+        ),
+        _ => error(404, format!("Nothing at `{}`", path)),
+    };
+
+    let _ = request.respond(response);
+}
+
+fn update(shared: &Shared, body: &[u8]) -> Response {
+    let update: Update = match serde_json::from_slice(body) {
+        Ok(update) => update,
+        Err(e) => return error(400, e),
+    };
+
+    let mut control = lock(&shared.control);
+    if let Some(name) = update
+        .params
+        .keys()
+        .find(|name| !control.params.contains_key(*name))
+    {
+        return error(400, format!("Unknown parameter `{}`", name));
+    }
+
+    control.params.extend(update.params);
+    if let Some(time) = update.time {
+        control.time = time;
+    }
+    if let Some(speed) = update.speed {
+        control.speed = speed;
+    }
+
+    json(200, &*control)
+}
+
+fn start_job(shared: &Arc<Shared>, kind: JobKind, body: &[u8]) -> Response {
+    let request: JobRequest = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(e) => return error(400, e),
+    };
+    if request.outputs.is_empty() {
+        return error(400, "A job needs `outputs`");
+    }
+    let dir = shared
+        .project
+        .serve
+        .output_dir
+        .as_deref()
+        .unwrap_or(Path::new("renders"));
+    let Some(outputs) = request
+        .outputs
+        .iter()
+        .map(|output| output_path(dir, output))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return error(
+            400,
+            "The outputs must be relative paths without `..` in the output directory",
+        );
+    };
+
+    // The job renders what the stream shows now
+    let mut project = shared.project.clone();
+    {
+        let control = lock(&shared.control);
+        project.time = request.time.unwrap_or(control.time);
+        project.speed = Some(control.speed);
+        project.params = control.params.clone();
+    }
+    project.outputs = outputs.clone();
+    if let Some(resolution) = request.resolution {
+        project.resolution = resolution;
+    }
+    if let Some(duration) = request.duration {
+        project.duration = duration;
+    }
+    if request.frames.is_some() {
+        project.frames = request.frames;
+    }
+
+    let mut jobs = lock(&shared.jobs);
+    let job = Job {
+        id: jobs.len(),
+        kind,
+        outputs,
+        status: JobStatus::Queued,
+        error: None,
+    };
+    let queued = Queued {
+        id: job.id,
+        kind,
+        project,
+    };
+    if shared.queue.try_send(queued).is_err() {
+        return error(503, "Too many jobs are queued, retry later");
+    }
+    jobs.push(job.clone());
+
+    json(202, &job)
+}
+
+// Renders the queued jobs in order until the server stops
+fn run_jobs(shared: &Shared, queued: &mpsc::Receiver<Queued>) {
+    while !shared.stopped.load(Ordering::Relaxed) {
+        let job = match queued.recv_timeout(POLL_INTERVAL) {
+            Ok(job) => job,
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        };
+        lock(&shared.jobs)[job.id].status = JobStatus::Running;
+
+        let project = &job.project;
+        let result = pollster::block_on(async {
+            for output in &project.outputs {
+                if let Some(dir) = output.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+            }
+            match job.kind {
+                JobKind::Still => ui::still(project).await,
+                JobKind::Export => ui::run(project).await,
+            }
+        });
+
+        let mut jobs = lock(&shared.jobs);
+        match result {
+            Ok(()) => jobs[job.id].status = JobStatus::Done,
+            Err(e) => {
+                jobs[job.id].status = JobStatus::Failed;
+                jobs[job.id].error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+// `output` inside `dir`, `None` when it could be written elsewhere
+fn output_path(dir: &Path, output: &Path) -> Option<PathBuf> {
+    let inside = output.components().next().is_some()
+        && output
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    inside.then(|| dir.join(output))
+}
+
+// Writes the frames as a multipart response until the client goes away, the
+// viewer was counted by the caller
+fn stream(shared: &Shared, request: tiny_http::Request) {
+    let mut writer = request.into_writer();
+    let _ = write_stream(shared, &mut writer);
+    shared.viewers.fetch_sub(1, Ordering::Relaxed);
+}
+
+fn write_stream(shared: &Shared, writer: &mut impl Write) -> std::io::Result<()> {
+    write!(
+        writer,
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: multipart/x-mixed-replace; boundary={}\r\n\
+         Cache-Control: no-cache\r\n\
+         Connection: close\r\n\r\n",
+        BOUNDARY
+    )?;
+    writer.flush()?;
+
+    // Only the frames rendered from now on
+    let mut seen = lock(&shared.latest).index;
+    loop {
+        let jpeg = {
+            let mut latest = lock(&shared.latest);
+            while latest.index == seen {
+                if shared.stopped.load(Ordering::Relaxed) {
+                    return Ok(());
+                }
+                latest = shared
+                    .frame_ready
+                    .wait_timeout(latest, FRAME_TIMEOUT)
+                    .unwrap_or_else(|e| e.into_inner())
+                    .0;
+            }
+            seen = latest.index;
+            latest.jpeg.clone()
+        };
+
+        write!(
+            writer,
+            "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+            BOUNDARY,
+            jpeg.len()
+        )?;
+        writer.write_all(&jpeg)?;
+        writer.write_all(b"\r\n")?;
+        writer.flush()?;
+    }
+}
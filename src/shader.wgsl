@@ -1,23 +1,3 @@
-struct Infos {
-    dimensions: vec2<f32>,
-    time: f32,
-}
-
-@group(0) @binding(0) var<uniform> infos: Infos;
-
-struct VertexOutput {
-    @builtin(position) foo: vec4f,
-    @location(0) pos: vec2f,
-};
-
-@vertex
-fn vs_main(@location(0) position: vec2<f32>) -> VertexOutput {
-    var out: VertexOutput;
-    out.foo = vec4f(position, 0.0, 1.0);
-    out.pos = position;
-    return out;
-}
-
 fn color(t: f32) -> vec3f {
     let a = vec3f(0.5, 0.5, 0.5);
     let b = vec3f(0.5, 0.5, 0.5);
@@ -0,0 +1,207 @@
+use crate::app::creation_result;
+use crate::error::{DeviceErrors, Result, ShaderArtError};
+use crate::gpu::GpuOptions;
+use crate::pipeline::{check, lines, Lines};
+use crate::preprocess::SourceMap;
+use std::path::Path;
+
+/// Default sample rate of the rendered audio, in Hz.
+pub const SAMPLE_RATE: u32 = 44100;
+
+// Samples rendered by each dispatch, the workgroups hold 64 of them
+const CHUNK: u32 = 1 << 16;
+const WORKGROUP_SIZE: u32 = 64;
+
+/// A sound shader, it defines `fn mainSound(time: f32) -> vec2f` returning
+/// the left and right samples at `time` seconds, in [-1, 1].
+#[derive(Clone, Debug, Default)]
+pub struct Sound {
+    pub source: String,
+    pub source_map: SourceMap,
+}
+
+impl Sound {
+    fn header() -> &'static str {
+        include_str!("sound.wgsl")
+    }
+
+    /// Complete WGSL source of the compute shader.
+    pub fn full_source(&self) -> String {
+        format!("{}\n{}", Self::header(), self.source)
+    }
+
+    /// Checks that the shader compiles and defines `mainSound`, without any GPU.
+    pub fn validate(&self) -> Result<()> {
+        let has_entry = wgpu::naga::front::wgsl::parse_str(&self.source).map_or(true, |module| {
+            module
+                .functions
+                .iter()
+                .any(|(_, function)| function.name.as_deref() == Some("mainSound"))
+        });
+        if !has_entry {
+            return Err(ShaderArtError::Shader(
+                "Sound shader: missing the `mainSound` function".into(),
+            ));
+        }
+
+        check(
+            "Sound shader",
+            &self.full_source(),
+            Lines::new(lines(Self::header()) + 1, &self.source_map),
+            "sound_main",
+            wgpu::naga::ShaderStage::Compute,
+        )
+    }
+}
+
+/// Renders `count` stereo samples of `sound` at `sample_rate` on the GPU.
+pub async fn render(
+    sound: &Sound,
+    sample_rate: u32,
+    count: usize,
+    gpu: &GpuOptions,
+) -> Result<Vec<[f32; 2]>> {
+    sound.validate()?;
+    if sample_rate == 0 {
+        return Err(ShaderArtError::Project(
+            "The sample rate must be positive".into(),
+        ));
+    }
+
+    let instance = gpu.instance();
+    let adapter = gpu.adapter(&instance, None).await?;
+    if !adapter
+        .get_downlevel_capabilities()
+        .flags
+        .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+    {
+        return Err(ShaderArtError::Unsupported(
+            "The GPU does not support compute shaders".into(),
+        ));
+    }
+
+    let (device, queue) = adapter.request_device(&Default::default(), None).await?;
+    let errors = DeviceErrors::watch(&device);
+
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Sound Shader"),
+        source: wgpu::ShaderSource::Wgsl(sound.full_source().into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Sound Pipeline"),
+        layout: None,
+        module: &shader,
+        entry_point: "sound_main",
+        compilation_options: Default::default(),
+        cache: None,
+    });
+    let pipeline = creation_result(&device, Ok(pipeline)).await?;
+
+    let chunk_size = CHUNK as u64 * std::mem::size_of::<[f32; 2]>() as u64;
+    let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Sound Uniform Buffer"),
+        size: std::mem::size_of::<[u32; 4]>() as u64,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let samples_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Sound Samples Buffer"),
+        size: chunk_size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let output_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Sound Output Buffer"),
+        size: chunk_size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Sound Bind Group"),
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: samples_buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut samples = Vec::with_capacity(count);
+    while samples.len() < count {
+        let offset = u32::try_from(samples.len()).map_err(|_| {
+            ShaderArtError::Project(format!("At most {} samples are supported", u32::MAX))
+        })?;
+        let size = (count - samples.len()).min(CHUNK as usize) as u32;
+        queue.write_buffer(
+            &uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[offset, size, sample_rate, 0]),
+        );
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Sound Encoder"),
+        });
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Sound Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(size.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+        let bytes = size as u64 * std::mem::size_of::<[f32; 2]>() as u64;
+        encoder.copy_buffer_to_buffer(&samples_buffer, 0, &output_buffer, 0, bytes);
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let buffer_slice = output_buffer.slice(..bytes);
+        let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
+        buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+
+        match rx.receive().await {
+            Some(Ok(())) => {
+                let data = buffer_slice.get_mapped_range();
+                samples.extend_from_slice(bytemuck::cast_slice::<_, [f32; 2]>(&data));
+                drop(data);
+                output_buffer.unmap();
+            }
+            Some(Err(e)) => return Err(e.into()),
+            None => return Err(wgpu::BufferAsyncError.into()),
+        }
+        errors.check()?;
+    }
+
+    Ok(samples)
+}
+
+/// Writes stereo `samples` as a 16 bits WAV file, clipping them to [-1, 1].
+pub fn save_wav(path: &Path, samples: &[[f32; 2]], sample_rate: u32) -> Result<()> {
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for sample in samples.iter().flatten() {
+        let value = if sample.is_nan() {
+            0.0
+        } else {
+            sample.clamp(-1.0, 1.0)
+        };
+        writer.write_sample((value * i16::MAX as f32).round() as i16)?;
+    }
+    writer.finalize()?;
+
+    Ok(())
+}
@@ -0,0 +1,22 @@
+struct SoundInfos {
+    // Index of the first sample of the dispatch
+    offset: u32,
+    count: u32,
+    sample_rate: u32,
+}
+
+@group(0) @binding(0) var<uniform> sound: SoundInfos;
+// Left and right samples of the dispatch
+@group(0) @binding(1) var<storage, read_write> samples: array<vec2f>;
+
+@compute @workgroup_size(64)
+fn sound_main(@builtin(global_invocation_id) id: vec3u) {
+    if id.x >= sound.count {
+        return;
+    }
+    let index = sound.offset + id.x;
+    // The whole seconds apart, so the time keeps its precision in long tracks
+    let rate = sound.sample_rate;
+    let time = f32(index / rate) + f32(index % rate) / f32(rate);
+    samples[id.x] = mainSound(time);
+}
@@ -0,0 +1,61 @@
+// Color space conversions, RGB is linear unless stated otherwise.
+
+// Hue, saturation and value, all in [0, 1]
+fn hsv_to_rgb(c: vec3f) -> vec3f {
+    let p = abs(fract(c.x + vec3f(1.0, 2.0 / 3.0, 1.0 / 3.0)) * 6.0 - 3.0);
+    return c.z * mix(vec3f(1.0), clamp(p - 1.0, vec3f(0.0), vec3f(1.0)), c.y);
+}
+
+fn rgb_to_hsv(c: vec3f) -> vec3f {
+    let high = max(c.r, max(c.g, c.b));
+    let d = high - min(c.r, min(c.g, c.b));
+    var h = 0.0;
+    if d > 0.0 {
+        if high == c.r {
+            h = (c.g - c.b) / d;
+        } else if high == c.g {
+            h = (c.b - c.r) / d + 2.0;
+        } else {
+            h = (c.r - c.g) / d + 4.0;
+        }
+        h = fract(h / 6.0);
+    }
+    return vec3f(h, select(0.0, d / high, high > 0.0), high);
+}
+
+// https://bottosson.github.io/posts/oklab
+fn linear_to_oklab(c: vec3f) -> vec3f {
+    let lms = vec3f(
+        0.4122214708 * c.r + 0.5363325363 * c.g + 0.0514459929 * c.b,
+        0.2119034982 * c.r + 0.6806995451 * c.g + 0.1073969566 * c.b,
+        0.0883024619 * c.r + 0.2817188376 * c.g + 0.6299787005 * c.b,
+    );
+    let l = sign(lms) * pow(abs(lms), vec3f(1.0 / 3.0));
+    return vec3f(
+        0.2104542553 * l.x + 0.7936177850 * l.y - 0.0040720468 * l.z,
+        1.9779984951 * l.x - 2.4285922050 * l.y + 0.4505937099 * l.z,
+        0.0259040861 * l.x + 0.7827717670 * l.y - 0.8086757571 * l.z,
+    );
+}
+
+fn oklab_to_linear(c: vec3f) -> vec3f {
+    let l = vec3f(
+        c.x + 0.3963377774 * c.y + 0.2158037573 * c.z,
+        c.x - 0.1055613458 * c.y - 0.0638541728 * c.z,
+        c.x - 0.0894841775 * c.y - 1.2914855480 * c.z,
+    );
+    let lms = l * l * l;
+    return vec3f(
+        4.0767416621 * lms.x - 3.3077115913 * lms.y + 0.2309699292 * lms.z,
+        -1.2684380046 * lms.x + 2.6097574011 * lms.y - 0.3413193965 * lms.z,
+        -0.0041960863 * lms.x - 0.7034186147 * lms.y + 1.7076147010 * lms.z,
+    );
+}
+
+fn srgb_to_linear(c: vec3f) -> vec3f {
+    return select(pow((c + 0.055) / 1.055, vec3f(2.4)), c / 12.92, c <= vec3f(0.04045));
+}
+
+fn linear_to_srgb(c: vec3f) -> vec3f {
+    return select(1.055 * pow(c, vec3f(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3f(0.0031308));
+}
@@ -0,0 +1,37 @@
+// Hashes of integer coordinates, in [0, 1). Integer arithmetic gives the
+// same values on every GPU, unlike the usual `fract(sin(...))`.
+
+// PCG hash of https://jcgt.org/published/0009/03/02
+fn pcg(v: u32) -> u32 {
+    let state = v * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// The 24 high bits of `h`, exactly representable as a float
+fn hash_unit(h: u32) -> f32 {
+    return f32(h >> 8u) / 16777216.0;
+}
+
+fn hash1(v: i32) -> f32 {
+    return hash_unit(pcg(bitcast<u32>(v)));
+}
+
+fn hash2(c: vec2i) -> f32 {
+    return hash_unit(pcg(bitcast<u32>(c.x) + pcg(bitcast<u32>(c.y))));
+}
+
+fn hash3(c: vec3i) -> f32 {
+    return hash_unit(pcg(bitcast<u32>(c.x) + pcg(bitcast<u32>(c.y) + pcg(bitcast<u32>(c.z)))));
+}
+
+fn hash22(c: vec2i) -> vec2f {
+    let h = pcg(bitcast<u32>(c.x) + pcg(bitcast<u32>(c.y)));
+    return vec2f(hash_unit(h), hash_unit(pcg(h)));
+}
+
+fn hash33(c: vec3i) -> vec3f {
+    let h = pcg(bitcast<u32>(c.x) + pcg(bitcast<u32>(c.y) + pcg(bitcast<u32>(c.z))));
+    let h2 = pcg(h);
+    return vec3f(hash_unit(h), hash_unit(h2), hash_unit(pcg(h2)));
+}
@@ -0,0 +1,78 @@
+#include "std/hash.wgsl"
+
+// Interpolation weights with zero first and second derivatives at 0 and 1
+fn noise_fade(t: vec2f) -> vec2f {
+    return t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+}
+
+// Random unit vector of a lattice point
+fn noise_gradient(c: vec2i) -> vec2f {
+    let angle = hash2(c) * 6.2831853;
+    return vec2f(cos(angle), sin(angle));
+}
+
+// Random values of the lattice points interpolated, in [0, 1].
+fn value_noise(p: vec2f) -> f32 {
+    let c = vec2i(floor(p));
+    let u = noise_fade(fract(p));
+    let a = hash2(c);
+    let b = hash2(c + vec2i(1, 0));
+    let d = hash2(c + vec2i(0, 1));
+    let e = hash2(c + vec2i(1, 1));
+    return mix(mix(a, b, u.x), mix(d, e, u.x), u.y);
+}
+
+// Gradient noise in [-1, 1].
+fn perlin_noise(p: vec2f) -> f32 {
+    let c = vec2i(floor(p));
+    let f = fract(p);
+    let u = noise_fade(f);
+    let a = dot(noise_gradient(c), f);
+    let b = dot(noise_gradient(c + vec2i(1, 0)), f - vec2f(1.0, 0.0));
+    let d = dot(noise_gradient(c + vec2i(0, 1)), f - vec2f(0.0, 1.0));
+    let e = dot(noise_gradient(c + vec2i(1, 1)), f - vec2f(1.0, 1.0));
+    return mix(mix(a, b, u.x), mix(d, e, u.x), u.y) * 1.4142135;
+}
+
+// Gradient noise on a triangular lattice, roughly in [-1, 1]. It has fewer
+// axis aligned artifacts than `perlin_noise`.
+fn simplex_noise(p: vec2f) -> f32 {
+    // Skews the square lattice into triangles and back
+    let skew = 0.36602540;
+    let unskew = 0.21132487;
+
+    let s = floor(p + (p.x + p.y) * skew);
+    let c = vec2i(s);
+    let x0 = p - s + (s.x + s.y) * unskew;
+    let o = select(vec2f(0.0, 1.0), vec2f(1.0, 0.0), x0.x > x0.y);
+    let x1 = x0 - o + unskew;
+    let x2 = x0 - 1.0 + 2.0 * unskew;
+
+    var corners = array(x0, x1, x2);
+    var cells = array(c, c + vec2i(o), c + vec2i(1, 1));
+    var n = 0.0;
+    for (var i = 0; i < 3; i++) {
+        let t = 0.5 - dot(corners[i], corners[i]);
+        if t > 0.0 {
+            n += t * t * t * t * dot(noise_gradient(cells[i]), corners[i]);
+        }
+    }
+    return 70.0 * n;
+}
+
+// `octaves` layers of value noise of doubling frequency and halving
+// amplitude, in [0, 1]. `octaves` must be positive.
+fn fbm(p: vec2f, octaves: i32) -> f32 {
+    var sum = 0.0;
+    var total = 0.0;
+    var amplitude = 0.5;
+    var q = p;
+    for (var i = 0; i < octaves; i++) {
+        sum += amplitude * value_noise(q);
+        total += amplitude;
+        amplitude *= 0.5;
+        // Shifted so that the lattices of the octaves do not line up
+        q = q * 2.0 + vec2f(17.0, 31.0);
+    }
+    return sum / total;
+}
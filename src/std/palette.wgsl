@@ -0,0 +1,26 @@
+// Cosine palettes of https://iquilezles.org/articles/palettes, `t` usually in [0, 1].
+
+fn palette(t: f32, a: vec3f, b: vec3f, c: vec3f, d: vec3f) -> vec3f {
+    return a + b * cos(6.2831853 * (c * t + d));
+}
+
+// Palette of the bundled shader
+fn palette_rings(t: f32) -> vec3f {
+    return palette(t, vec3f(0.5), vec3f(0.5), vec3f(1.0), vec3f(0.263, 0.416, 0.557));
+}
+
+fn palette_rainbow(t: f32) -> vec3f {
+    return palette(t, vec3f(0.5), vec3f(0.5), vec3f(1.0), vec3f(0.0, 0.33, 0.67));
+}
+
+fn palette_sunset(t: f32) -> vec3f {
+    return palette(t, vec3f(0.5), vec3f(0.5), vec3f(1.0, 1.0, 0.5), vec3f(0.8, 0.9, 0.3));
+}
+
+fn palette_ice(t: f32) -> vec3f {
+    return palette(t, vec3f(0.5), vec3f(0.5), vec3f(1.0, 0.7, 0.4), vec3f(0.0, 0.15, 0.2));
+}
+
+fn palette_fire(t: f32) -> vec3f {
+    return palette(t, vec3f(0.5), vec3f(0.5), vec3f(2.0, 1.0, 0.0), vec3f(0.5, 0.2, 0.25));
+}
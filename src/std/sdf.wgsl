@@ -0,0 +1,74 @@
+// Signed distances, negative inside, of https://iquilezles.org/articles/distfunctions
+
+fn sd_circle(p: vec2f, radius: f32) -> f32 {
+    return length(p) - radius;
+}
+
+// `size` is half the size of the box
+fn sd_box2(p: vec2f, size: vec2f) -> f32 {
+    let d = abs(p) - size;
+    return length(max(d, vec2f(0.0))) + min(max(d.x, d.y), 0.0);
+}
+
+fn sd_segment(p: vec2f, a: vec2f, b: vec2f) -> f32 {
+    let pa = p - a;
+    let ba = b - a;
+    let h = clamp(dot(pa, ba) / dot(ba, ba), 0.0, 1.0);
+    return length(pa - ba * h);
+}
+
+fn sd_sphere(p: vec3f, radius: f32) -> f32 {
+    return length(p) - radius;
+}
+
+// `size` is half the size of the box
+fn sd_box(p: vec3f, size: vec3f) -> f32 {
+    let d = abs(p) - size;
+    return length(max(d, vec3f(0.0))) + min(max(d.x, max(d.y, d.z)), 0.0);
+}
+
+// Torus around the y axis, `radii` are the major then the minor radius
+fn sd_torus(p: vec3f, radii: vec2f) -> f32 {
+    let q = vec2f(length(p.xz) - radii.x, p.y);
+    return length(q) - radii.y;
+}
+
+fn sd_capsule(p: vec3f, a: vec3f, b: vec3f, radius: f32) -> f32 {
+    let pa = p - a;
+    let ba = b - a;
+    let h = clamp(dot(pa, ba) / dot(ba, ba), 0.0, 1.0);
+    return length(pa - ba * h) - radius;
+}
+
+// Plane of unit `normal`, at `offset` from the origin against the normal
+fn sd_plane(p: vec3f, normal: vec3f, offset: f32) -> f32 {
+    return dot(p, normal) + offset;
+}
+
+fn op_union(a: f32, b: f32) -> f32 {
+    return min(a, b);
+}
+
+fn op_intersection(a: f32, b: f32) -> f32 {
+    return max(a, b);
+}
+
+// `a` without `b`
+fn op_subtraction(a: f32, b: f32) -> f32 {
+    return max(a, -b);
+}
+
+// Union blending the shapes where they are closer than `k`
+fn op_smooth_union(a: f32, b: f32, k: f32) -> f32 {
+    let h = clamp(0.5 + 0.5 * (b - a) / k, 0.0, 1.0);
+    return mix(b, a, h) - k * h * (1.0 - h);
+}
+
+// Repeats the space every `period`, a copy is centered on the origin
+fn op_repeat2(p: vec2f, period: vec2f) -> vec2f {
+    return p - period * floor(p / period + 0.5);
+}
+
+fn op_repeat(p: vec3f, period: vec3f) -> vec3f {
+    return p - period * floor(p / period + 0.5);
+}
@@ -0,0 +1,25 @@
+// Rotations by `angle` radians, counterclockwise when the axis points to the viewer.
+
+fn rotate2(angle: f32) -> mat2x2f {
+    let c = cos(angle);
+    let s = sin(angle);
+    return mat2x2f(c, s, -s, c);
+}
+
+fn rotate_x(angle: f32) -> mat3x3f {
+    let c = cos(angle);
+    let s = sin(angle);
+    return mat3x3f(1.0, 0.0, 0.0, 0.0, c, s, 0.0, -s, c);
+}
+
+fn rotate_y(angle: f32) -> mat3x3f {
+    let c = cos(angle);
+    let s = sin(angle);
+    return mat3x3f(c, 0.0, -s, 0.0, 1.0, 0.0, s, 0.0, c);
+}
+
+fn rotate_z(angle: f32) -> mat3x3f {
+    let c = cos(angle);
+    let s = sin(angle);
+    return mat3x3f(c, s, 0.0, -s, c, 0.0, 0.0, 0.0, 1.0);
+}
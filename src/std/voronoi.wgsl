@@ -0,0 +1,25 @@
+#include "std/hash.wgsl"
+
+// Cellular noise with a random point in each unit cell. Returns the distance
+// to the nearest point, the distance to the second nearest one, and a random
+// value in [0, 1) identifying the cell of the nearest point.
+fn voronoi(p: vec2f) -> vec3f {
+    let c = vec2i(floor(p));
+    var f1 = 8.0;
+    var f2 = 8.0;
+    var id = 0.0;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let cell = c + vec2i(x, y);
+            let d = length(vec2f(cell) + hash22(cell) - p);
+            if d < f1 {
+                f2 = f1;
+                f1 = d;
+                id = hash3(vec3i(cell, 1));
+            } else if d < f2 {
+                f2 = d;
+            }
+        }
+    }
+    return vec3f(f1, f2, id);
+}
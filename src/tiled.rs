@@ -0,0 +1,134 @@
+use crate::app::{headless, App};
+use crate::error::{Result, ShaderArtError};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Renders an image of `resolution` tile by tile with an app created for the
+/// tile size, and hands `write_rows` bands of complete rows from top to bottom.
+///
+/// The effects reading the pixels around each pixel see `apron` more pixels
+/// on each side of the tiles, which are dropped.
+pub async fn render(
+    app: &mut App<'_>,
+    resolution: [u32; 2],
+    apron: u32,
+    mut write_rows: impl FnMut(&[u8]) -> Result<()>,
+) -> Result<()> {
+    let [width, height] = resolution;
+    let texture_size = app.texture_size.clone().ok_or_else(headless)?;
+    let (tile_width, tile_height) = (texture_size.width, texture_size.height);
+    let (core_width, core_height) = (
+        core(tile_width, width, apron)?,
+        core(tile_height, height, apron)?,
+    );
+
+    let row_bytes = width as usize * 4;
+    let tile_row_bytes = tile_width as usize * 4;
+    let mut band = vec![0; row_bytes * core_height as usize];
+
+    for y in (0..height).step_by(core_height as usize) {
+        let rows = core_height.min(height - y) as usize;
+        let origin_y = origin(y, tile_height, height, apron);
+
+        for x in (0..width).step_by(core_width as usize) {
+            let columns = core_width.min(width - x) as usize * 4;
+            let origin_x = origin(x, tile_width, width, apron);
+
+            app.set_tile(resolution, [origin_x, origin_y])?;
+
+            let mut frames = Vec::new();
+            app.run(&mut frames).await?;
+            let tile = frames
+                .pop()
+                .ok_or(ShaderArtError::Map(wgpu::BufferAsyncError))?;
+
+            let offset = (y - origin_y) as usize * tile_row_bytes + (x - origin_x) as usize * 4;
+            for row in 0..rows {
+                let start = row * row_bytes + x as usize * 4;
+                band[start..start + columns]
+                    .copy_from_slice(&tile[offset + row * tile_row_bytes..][..columns]);
+            }
+        }
+
+        write_rows(&band[..rows * row_bytes])?;
+    }
+
+    Ok(())
+}
+
+// Side of the part of the tiles kept in the image
+fn core(tile: u32, side: u32, apron: u32) -> Result<u32> {
+    if tile >= side {
+        return Ok(side);
+    }
+    match tile.checked_sub(apron.saturating_mul(2)) {
+        Some(core) if core > 0 => Ok(core),
+        _ => Err(ShaderArtError::Project(format!(
+            "Tiles of {} pixels are too small for the {} pixels read around each pixel",
+            tile, apron
+        ))),
+    }
+}
+
+// Position of the tile keeping `start`, the tiles stay in the image so that
+// their edges on the sides of the image are the ones of a single texture.
+fn origin(start: u32, tile: u32, side: u32, apron: u32) -> u32 {
+    start.saturating_sub(apron).min(side.saturating_sub(tile))
+}
+
+pub async fn save_png(
+    app: &mut App<'_>,
+    path: &Path,
+    resolution: [u32; 2],
+    apron: u32,
+) -> Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+
+    let mut encoder = png::Encoder::new(file, resolution[0], resolution[1]);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?.into_stream_writer()?;
+
+    render(app, resolution, apron, |rows| Ok(writer.write_all(rows)?)).await?;
+
+    writer.finish()?;
+
+    Ok(())
+}
+
+pub async fn save_tiff(
+    app: &mut App<'_>,
+    path: &Path,
+    resolution: [u32; 2],
+    apron: u32,
+) -> Result<()> {
+    use tiff::encoder::{colortype::RGBA8, TiffEncoder, TiffKind, TiffKindBig, TiffKindStandard};
+
+    async fn encode<K: TiffKind>(
+        app: &mut App<'_>,
+        mut encoder: TiffEncoder<BufWriter<File>, K>,
+        resolution: [u32; 2],
+        apron: u32,
+    ) -> Result<()> {
+        let tile_height = app.texture_size.as_ref().ok_or_else(headless)?.height;
+        let mut image = encoder.new_image::<RGBA8>(resolution[0], resolution[1])?;
+        // A strip for each band of tiles
+        image.rows_per_strip(core(tile_height, resolution[1], apron)?)?;
+
+        render(app, resolution, apron, |rows| Ok(image.write_strip(rows)?)).await?;
+
+        image.finish()?;
+
+        Ok(())
+    }
+
+    let file = BufWriter::new(File::create(path)?);
+
+    // Classic TIFF offsets are 32 bits
+    if resolution[0] as u64 * resolution[1] as u64 * 4 > u32::MAX as u64 / 2 {
+        encode::<TiffKindBig>(app, TiffEncoder::new_big(file)?, resolution, apron).await
+    } else {
+        encode::<TiffKindStandard>(app, TiffEncoder::new(file)?, resolution, apron).await
+    }
+}
@@ -1,86 +1,563 @@
-use crate::app::App;
+use crate::app::{App, State};
+use crate::cpu;
+use crate::error::{Result, ShaderArtError};
+use crate::offscreen::OffscreenRenderer;
+use crate::osc;
+use crate::pipeline::Scene;
+use crate::pixels;
+use crate::playlist::Transition;
+use crate::project::{OutputFormat, Project};
+use crate::resolution::parse_resolution;
+use crate::sound;
+use crate::tiled;
+use crate::watch::Watcher;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use winit::keyboard::PhysicalKey::Code;
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
     keyboard::KeyCode,
-    window::WindowBuilder,
+    window::{Fullscreen, WindowBuilder},
 };
 
-pub async fn render(speed: u8) {
+// Delay between two attempts to recreate a lost device
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+// How often the shader files are checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_millis(250);
+
+pub async fn render(project: &Project) -> Result<()> {
     env_logger::init();
-    let event_loop = EventLoop::new().unwrap();
+    let playlist = project.playlist()?;
+    let transition = Transition {
+        interval: project
+            .preview
+            .interval
+            .map(|seconds| duration(seconds, "interval"))
+            .transpose()?,
+        crossfade: duration(project.preview.crossfade, "crossfade")?,
+    };
+
+    // The first entry keeps the time of the playlist
+    let single;
+    let scene = match playlist.first() {
+        Some(first) => first,
+        None => {
+            single = project.scene()?;
+            &single
+        }
+    };
+    let event_loop = EventLoop::new().map_err(|e| ShaderArtError::Window(e.to_string()))?;
+
+    let mut builder = WindowBuilder::new().with_title("Shader Art");
+
+    if let Some(size) = &project.preview.size {
+        let [width, height] = parse_resolution(size, None)?;
+        builder = builder.with_inner_size(winit::dpi::PhysicalSize::new(width, height));
+    }
+
+    if project.preview.fullscreen {
+        builder = builder.with_fullscreen(Some(Fullscreen::Borderless(None)));
+    }
+
+    let window = builder
+        .build(&event_loop)
+        .map_err(|e| ShaderArtError::Window(e.to_string()))?;
+
+    let create = || async {
+        let mut app = App::new_with_window(
+            &window,
+            project.speed(false),
+            scene,
+            project.preview.vsync,
+            &project.gpu,
+        )
+        .await?;
+        if !playlist.is_empty() {
+            app.set_playlist(&playlist, transition).await?;
+        }
+        Ok::<_, ShaderArtError>(app)
+    };
+
+    let track = project.track()?;
+    let osc = project
+        .preview
+        .osc
+        .as_deref()
+        .map(osc::Server::bind)
+        .transpose()?;
+
+    let mut app = create().await?;
+    app.set_time(project.time);
 
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let watched = if playlist.is_empty() {
+        vec![scene]
+    } else {
+        playlist.iter().collect()
+    };
+    let mut watcher = Watcher::new(&watched, RELOAD_INTERVAL);
+    // A recreated app starts from the scenes read at launch
+    let mut reloaded = false;
+    let started = Instant::now();
 
-    let mut app = App::new_with_window(&window, speed).await;
+    // `None` after a device loss, until a new device is created with `state`
+    let mut app = Some(app);
+    let mut state: Option<State> = None;
+    let mut retry_at = Instant::now();
 
     event_loop.set_control_flow(ControlFlow::Wait);
 
+    // Error stopping the preview, returned once the loop exits
+    let mut failure = None;
+
     event_loop
         .run(|event, elwt| match event {
             Event::WindowEvent { window_id, event } if window_id == window.id() => match event {
                 WindowEvent::RedrawRequested => {
-                    app.update();
-                    match app.render() {
+                    let Some(current) = &mut app else {
+                        // The old device must be dropped before, its surface
+                        // holds the window.
+                        match pollster::block_on(create()) {
+                            Ok(mut recreated) => {
+                                if let Some(state) = state.take() {
+                                    recreated.restore(state);
+                                }
+                                if reloaded {
+                                    if let Err(e) = pollster::block_on(reload(
+                                        &mut recreated,
+                                        project,
+                                        transition,
+                                    )) {
+                                        eprintln!("{}", e);
+                                    }
+                                }
+                                app = Some(recreated);
+                                elwt.set_control_flow(ControlFlow::Wait);
+                            }
+                            Err(e) => {
+                                eprintln!("Can not recreate the device: {}", e);
+                                retry_at = Instant::now() + RETRY_DELAY;
+                                elwt.set_control_flow(ControlFlow::WaitUntil(retry_at));
+                            }
+                        }
+                        return;
+                    };
+
+                    if let Some(osc) = &osc {
+                        for command in osc.commands() {
+                            if let Err(e) = command.and_then(|command| {
+                                command.apply(current, &project.preview.playlist)
+                            }) {
+                                eprintln!("{}", e);
+                            }
+                        }
+                    }
+
+                    if watcher.changed() {
+                        match pollster::block_on(reload(current, project, transition)) {
+                            Ok(scenes) => {
+                                watcher.watch(&scenes.iter().collect::<Vec<_>>());
+                                reloaded = true;
+                            }
+                            // The previous passes keep running until the files are fixed
+                            Err(e) => eprintln!("{}", e),
+                        }
+                    }
+
+                    // The track plays along the wall clock
+                    if let Some(track) = &track {
+                        let sample = track.sample_at(started.elapsed().as_secs_f64());
+                        current.set_audio(&track.analyze(sample));
+                    }
+                    current.update();
+                    match current.render() {
                         Ok(_) => {}
-                        Err(wgpu::SurfaceError::Lost) => {
-                            let size = app.surface.as_ref().unwrap().surface_size;
-                            app.resize(size);
+                        Err(ShaderArtError::Surface(
+                            wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated,
+                        )) => current.resize(window.inner_size()),
+                        // The frame is skipped, the next one usually succeeds
+                        Err(ShaderArtError::Surface(wgpu::SurfaceError::Timeout)) => {}
+                        Err(e @ ShaderArtError::DeviceLost(_)) => {
+                            eprintln!("{}, recreating it", e);
+                            state = Some(current.state());
+                            app = None;
+                        }
+                        Err(e) => {
+                            failure = Some(e);
+                            elwt.exit();
                         }
-                        Err(wgpu::SurfaceError::OutOfMemory) => elwt.exit(),
-                        Err(e) => eprintln!("{:?}", e),
                     }
                 }
                 WindowEvent::CloseRequested => elwt.exit(),
-                WindowEvent::KeyboardInput { event, .. } => {
-                    if event.state.is_pressed() && event.physical_key == Code(KeyCode::Escape) {
-                        elwt.exit()
+                WindowEvent::KeyboardInput { event, .. }
+                    if event.state.is_pressed() && event.physical_key == Code(KeyCode::Escape) =>
+                {
+                    elwt.exit()
+                }
+                WindowEvent::KeyboardInput { event, .. } if event.state.is_pressed() => {
+                    let playlist = app.as_mut().and_then(|app| app.playlist.as_mut());
+                    match (playlist, event.physical_key) {
+                        (Some(playlist), Code(KeyCode::KeyN)) => playlist.next(),
+                        (Some(playlist), Code(KeyCode::KeyP)) => playlist.previous(),
+                        _ => {}
                     }
                 }
 
                 WindowEvent::Resized(physical_size) => {
-                    app.resize(physical_size);
+                    if let Some(app) = &mut app {
+                        app.resize(physical_size);
+                    }
                 }
                 WindowEvent::ScaleFactorChanged { .. } => {
-                    app.resize(window.inner_size());
+                    if let Some(app) = &mut app {
+                        app.resize(window.inner_size());
+                    }
                 }
                 _ => {}
             },
-            Event::AboutToWait => {
-                app.window().request_redraw();
+            Event::AboutToWait if app.is_some() || Instant::now() >= retry_at => {
+                window.request_redraw();
             }
             _ => {}
         })
-        .unwrap();
+        .map_err(|e| ShaderArtError::Window(e.to_string()))?;
+
+    failure.map_or(Ok(()), Err)
+}
+
+// Builds the scenes from their files again, an error leaves the app as it was
+async fn reload(
+    app: &mut App<'_>,
+    project: &Project,
+    transition: Transition,
+) -> Result<Vec<Scene>> {
+    let playlist = project.playlist()?;
+    if playlist.is_empty() {
+        let scene = project.scene()?;
+        app.set_scene(&scene).await?;
+        return Ok(vec![scene]);
+    }
+
+    let state = app.state();
+    app.set_playlist(&playlist, transition).await?;
+    app.restore(state);
+    Ok(playlist)
+}
+
+fn duration(seconds: f64, name: &str) -> Result<Duration> {
+    Duration::try_from_secs_f64(seconds)
+        .map_err(|_| ShaderArtError::Project(format!("Invalid {} of {} seconds", name, seconds)))
+}
+
+// `--cpu` renders on the CPU, which also takes over when no adapter is found
+// for a scene it can render. An adapter asked by name is never replaced.
+async fn on_cpu(project: &Project, scene: &Scene) -> Result<bool> {
+    if project.cpu {
+        cpu::check(scene)?;
+        return Ok(true);
+    }
+    if project.gpu.adapter.is_some() || cpu::check(scene).is_err() {
+        return Ok(false);
+    }
+
+    let instance = project.gpu.instance();
+    match project.gpu.adapter(&instance, None).await {
+        Err(ShaderArtError::NoAdapter(_)) => {
+            eprintln!("No GPU adapter found, rendering on the CPU");
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
 }
 
-pub async fn run(speed: u8, filename: &str, resolution: [u16; 2]) {
-    let mut app = App::new_without_window(speed, resolution).await;
+pub async fn run(project: &Project) -> Result<()> {
+    let resolution = project.resolution()?;
+    let scene = project.scene()?;
+    let formats = project
+        .outputs
+        .iter()
+        .map(|path| project.output_format(path))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let options = project.render_options(formats.contains(&OutputFormat::Exr));
+    let track = project.track()?;
+
+    let sound = if formats.contains(&OutputFormat::Wav) {
+        let sound = project.sound()?.ok_or_else(|| {
+            ShaderArtError::Project("WAV outputs need a sound shader, set it with `--sound`".into())
+        })?;
+        sound::render(&sound, project.sample_rate, project.samples(), &project.gpu).await?
+    } else {
+        Vec::new()
+    };
+
+    // WAV outputs alone do not need any frame
+    let video = formats.iter().any(|format| *format != OutputFormat::Wav);
+
     let mut frames: Vec<Vec<u8>> = Vec::new();
-    for _i in 1..60 {
-        app.update();
-        app.run(&mut frames).await;
+    if video && on_cpu(project, &scene).await? {
+        let mut common = cpu::common(project.speed(true), resolution, &options);
+        for i in 0..project.frames() {
+            common.time = project.frame_time(i);
+            frames.push(cpu::render_frame(&common, resolution, &options)?);
+        }
+    } else if video {
+        let mut renderer =
+            OffscreenRenderer::new(project.speed(true), resolution, &scene, &options).await?;
+        for i in 0..project.frames() {
+            // The frame `i` is shown at `i / fps` seconds of the video
+            if let Some(track) = &track {
+                renderer.set_audio(&track.analyze(track.sample_at_frame(i, project.fps)));
+            }
+            frames.push(renderer.render_frame_at(project.frame_time(i)).await?.data);
+        }
     }
-    save_gif(filename, &mut frames, 15, resolution[0], resolution[1]).unwrap();
+
+    for (path, format) in project.outputs.iter().zip(formats) {
+        match format {
+            OutputFormat::Gif => save_gif(
+                path,
+                &mut frames
+                    .iter()
+                    .map(|frame| pixels::to_rgba8(frame, options.format))
+                    .collect::<Result<_, _>>()?,
+                31 - project.quality as i32,
+                project.fps,
+                resolution[0],
+                resolution[1],
+            )?,
+            OutputFormat::Wav => sound::save_wav(path, &sound, project.sample_rate)?,
+            OutputFormat::Exr => {
+                for (i, frame) in frames.iter().enumerate() {
+                    pixels::save_exr(
+                        &sequence_path(path, i),
+                        frame,
+                        options.format,
+                        resolution[0],
+                        resolution[1],
+                    )?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `path` numbered with `index`, e.g. `art.exr` becomes `art_0042.exr`.
+fn sequence_path(path: &Path, index: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}_{:04}.{}", stem, index, extension))
+}
+
+pub async fn still(project: &Project) -> Result<()> {
+    let resolution = project.resolution()?;
+    let scene = project.scene()?;
+    // Stills show the start of the track, like the first exported frame
+    let audio = project
+        .track()?
+        .map(|track| track.analyze(0))
+        .unwrap_or_default();
+
+    let on_cpu = on_cpu(project, &scene).await?;
+    for path in &project.outputs {
+        let extension = match project.format {
+            Some(OutputFormat::Exr) => Some(String::from("exr")),
+            Some(format) => {
+                return Err(ShaderArtError::Project(format!(
+                    "Stills can not be saved as {:?}",
+                    format
+                )))
+            }
+            None => path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext.to_lowercase()),
+        };
+        let options = project.render_options(extension.as_deref() == Some("exr"));
+
+        // PNG and TIFF are streamed tile by tile, so they are not limited by
+        // the GPU texture size nor by the memory.
+        if on_cpu {
+            let mut common = cpu::common(project.speed(true), resolution, &options);
+            common.time = project.time;
+            let data = cpu::render_frame(&common, resolution, &options)?;
+
+            if extension.as_deref() == Some("exr") {
+                pixels::save_exr(path, &data, options.format, resolution[0], resolution[1])?;
+            } else {
+                image::save_buffer(
+                    path,
+                    &data,
+                    resolution[0],
+                    resolution[1],
+                    image::ExtendedColorType::Rgba8,
+                )?;
+            }
+        } else if let Some("png" | "tif" | "tiff") = extension.as_deref() {
+            let adapter = options.gpu.adapter(&options.gpu.instance(), None).await?;
+            let tile = project.tile(resolution, adapter.limits().max_texture_dimension_2d)?;
+            let apron = project.apron(resolution);
+            let mut app =
+                App::new_without_window(project.speed(true), tile, &scene, &options).await?;
+            app.set_audio(&audio);
+            app.set_time(project.time);
+
+            if extension.as_deref() == Some("png") {
+                tiled::save_png(&mut app, path, resolution, apron).await?;
+            } else {
+                tiled::save_tiff(&mut app, path, resolution, apron).await?;
+            }
+        } else {
+            let mut renderer =
+                OffscreenRenderer::new(project.speed(true), resolution, &scene, &options).await?;
+            renderer.set_audio(&audio);
+            let frame = renderer.render_frame_at(project.time).await?;
+
+            if extension.as_deref() == Some("exr") {
+                pixels::save_exr(
+                    path,
+                    &frame.data,
+                    options.format,
+                    resolution[0],
+                    resolution[1],
+                )?;
+            } else {
+                image::save_buffer(
+                    path,
+                    &frame.data,
+                    resolution[0],
+                    resolution[1],
+                    image::ExtendedColorType::Rgba8,
+                )?;
+            }
+        }
+    }
+
+    Ok(())
 }
 
 fn save_gif(
-    path: &str,
+    path: &Path,
     frames: &mut Vec<Vec<u8>>,
     speed: i32,
-    width: u16,
-    height: u16,
-) -> anyhow::Result<()> {
+    fps: u16,
+    width: u32,
+    height: u32,
+) -> Result<()> {
     use gif::{Encoder, Frame, Repeat};
 
+    let (Ok(width), Ok(height)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(ShaderArtError::Encoding(format!(
+            "GIF images are limited to {}x{}",
+            u16::MAX,
+            u16::MAX
+        )));
+    };
+
     let mut image = std::fs::File::create(path)?;
     let mut encoder = Encoder::new(&mut image, width, height, &[])?;
     encoder.set_repeat(Repeat::Infinite)?;
 
+    // GIF delays are expressed in hundredths of a second
+    let delay = (100 / fps).max(1);
+
     for frame in frames {
-        encoder.write_frame(&Frame::from_rgba_speed(width, height, frame, speed))?;
+        let mut frame = Frame::from_rgba_speed(width, height, frame, speed);
+        frame.delay = delay;
+        encoder.write_frame(&frame)?;
     }
 
     Ok(())
 }
+
+/// Human readable summary of the effective settings and of the scene.
+pub fn info(project: &Project) -> Result<String> {
+    use std::fmt::Write;
+
+    let scene = project.scene()?;
+    let mut info = String::new();
+
+    if let Some(compute) = &project.compute {
+        let [x, y] = project.workgroup_size;
+        writeln!(
+            info,
+            "Compute: {} ({}x{} workgroups, {} storage buffers)",
+            compute.display(),
+            x,
+            y,
+            project.storage_buffers.len()
+        )?;
+    }
+
+    if !project.preview.playlist.is_empty() {
+        writeln!(info, "Playlist:")?;
+        for entry in &project.preview.playlist {
+            writeln!(info, "  {}", entry)?;
+        }
+    }
+
+    if let Some(osc) = &project.preview.osc {
+        writeln!(info, "OSC: {}", osc)?;
+    }
+
+    writeln!(info, "Passes:")?;
+    if let (true, Some(preset)) = (project.shaders.is_empty(), &project.preset) {
+        writeln!(info, "  preset {}", preset)?;
+    } else if project.shaders.is_empty() && project.compute.is_some() {
+        writeln!(info, "  compute output")?;
+    } else if project.shaders.is_empty() {
+        writeln!(info, "  bundled shader")?;
+    }
+    for shader in &project.shaders {
+        writeln!(info, "  {}", shader.display())?;
+    }
+
+    if !project.inputs.is_empty() {
+        writeln!(info, "Inputs:")?;
+        for (i, (path, image)) in project.inputs.iter().zip(&scene.inputs).enumerate() {
+            writeln!(
+                info,
+                "  channel{}: {} ({}x{})",
+                i,
+                path.display(),
+                image.width(),
+                image.height()
+            )?;
+        }
+    }
+
+    if let (Some(path), Some(track)) = (&project.audio, project.track()?) {
+        writeln!(
+            info,
+            "Audio: {} ({} Hz, {:.2} s)",
+            path.display(),
+            track.sample_rate,
+            track.samples.len() as f64 / track.sample_rate as f64
+        )?;
+    }
+
+    if let Some(sound) = &project.sound {
+        writeln!(
+            info,
+            "Sound: {} ({} Hz, {} samples)",
+            sound.display(),
+            project.sample_rate,
+            project.samples()
+        )?;
+    }
+
+    if !project.params.is_empty() {
+        writeln!(info, "Parameters:")?;
+        for (name, value) in &project.params {
+            writeln!(info, "  {} = {}", name, value)?;
+        }
+    }
+
+    let [width, height] = project.resolution()?;
+    writeln!(info, "Resolution: {}x{}", width, height)?;
+    writeln!(info, "Frames: {} at {} fps", project.frames(), project.fps)?;
+
+    Ok(info)
+}
@@ -0,0 +1,13 @@
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let strength = settings.values.x;
+    let radius = settings.values.y;
+    let softness = settings.values.z;
+
+    // 1 in the middle of the sides, about 1.41 in the corners
+    let distance = length(frame_uv(in.position) * 2.0 - 1.0);
+    let factor = 1.0 - strength * smoothstep(radius, radius + softness, distance);
+
+    let color = textureLoad(source, vec2u(in.position.xy), 0);
+    return vec4f(color.rgb * factor, color.a);
+}
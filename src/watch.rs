@@ -0,0 +1,73 @@
+use crate::pipeline::Scene;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Polls the modification times of the files the shaders were read from, so
+/// that the preview rebuilds its passes when one is saved.
+pub struct Watcher {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    interval: Duration,
+    polled_at: Instant,
+}
+
+impl Watcher {
+    /// Watches the shader files of `scenes` and their includes, at most every
+    /// `interval`.
+    pub fn new(scenes: &[&Scene], interval: Duration) -> Self {
+        let mut watcher = Self {
+            files: Vec::new(),
+            interval,
+            polled_at: Instant::now(),
+        };
+        watcher.watch(scenes);
+        watcher
+    }
+
+    /// Replaces the watched files, the includes may have changed.
+    pub fn watch(&mut self, scenes: &[&Scene]) {
+        let mut files = Vec::new();
+        for scene in scenes {
+            let maps = scene
+                .source_maps
+                .iter()
+                .chain(scene.compute.iter().map(|compute| &compute.source_map));
+            for path in maps.flat_map(|map| map.files()) {
+                if !files.contains(path) {
+                    files.push(path.clone());
+                }
+            }
+        }
+
+        self.files = files
+            .into_iter()
+            .map(|path| {
+                let modified = modified(&path);
+                (path, modified)
+            })
+            .collect();
+    }
+
+    /// Whether a file was modified, created or removed since the last poll.
+    pub fn changed(&mut self) -> bool {
+        if self.polled_at.elapsed() < self.interval {
+            return false;
+        }
+        self.polled_at = Instant::now();
+
+        let mut changed = false;
+        for (path, last) in &mut self.files {
+            let modified = modified(path);
+            if modified != *last {
+                *last = modified;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -0,0 +1,168 @@
+use shader_art_rs::app::RenderOptions;
+use shader_art_rs::audio::{Track, AUDIO_SIZE};
+use shader_art_rs::offscreen::OffscreenRenderer;
+use shader_art_rs::pipeline::Scene;
+use std::path::PathBuf;
+
+const RATE: u32 = 48000;
+
+// Writes 16 bits `channels` to a WAV file in the test directory
+fn write_wav(name: &str, channels: &[Vec<f32>]) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("audio");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+
+    let spec = hound::WavSpec {
+        channels: channels.len() as u16,
+        sample_rate: RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for i in 0..channels[0].len() {
+        for channel in channels {
+            writer
+                .write_sample((channel[i] * i16::MAX as f32) as i16)
+                .unwrap();
+        }
+    }
+    writer.finalize().unwrap();
+
+    path
+}
+
+fn sine(frequency: f32, amplitude: f32, seconds: f32) -> Vec<f32> {
+    (0..(seconds * RATE as f32) as usize)
+        .map(|i| amplitude * (std::f32::consts::TAU * frequency * i as f32 / RATE as f32).sin())
+        .collect()
+}
+
+#[test]
+fn channels_are_mixed_down() {
+    let path = write_wav("stereo.wav", &[vec![0.5; 100], vec![-0.25; 100]]);
+    let track = Track::load(&path).unwrap();
+
+    assert_eq!(track.sample_rate, RATE);
+    assert_eq!(track.samples.len(), 100);
+    assert!(track
+        .samples
+        .iter()
+        .all(|sample| (sample - 0.125).abs() < 1e-3));
+}
+
+#[test]
+fn sines_peak_in_their_bin_and_band() {
+    // Quiet enough for the peak to stay below the top of the range
+    let amplitude = 0.01;
+    for (frequency, band) in [(100.0, 0), (1000.0, 1), (8000.0, 2)] {
+        let path = write_wav(
+            &format!("sine_{}.wav", frequency),
+            &[sine(frequency, amplitude, 1.0)],
+        );
+        let track = Track::load(&path).unwrap();
+        let frame = track.analyze(track.sample_at(0.5));
+
+        let peak = (0..AUDIO_SIZE)
+            .max_by_key(|&bin| frame.texture[0][bin])
+            .unwrap();
+        let expected = frequency * 2.0 * AUDIO_SIZE as f32 / RATE as f32;
+        assert!(
+            (peak as f32 - expected).abs() <= 1.0,
+            "{} Hz peaks in bin {}, expected {}",
+            frequency,
+            peak,
+            expected
+        );
+
+        let bands = [frame.bands.bass, frame.bands.mid, frame.bands.treble];
+        let loudest = (0..3)
+            .max_by(|&a, &b| bands[a].total_cmp(&bands[b]))
+            .unwrap();
+        assert_eq!(loudest, band, "{} Hz in the bands {:?}", frequency, bands);
+        assert!((frame.bands.volume - amplitude / 2f32.sqrt()).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn frames_are_sample_accurate() {
+    // 44100 Hz is not a multiple of 30 fps, frames start between samples
+    let fps = 30;
+    let mut samples = vec![0.0; RATE as usize];
+    let track = Track {
+        sample_rate: 44100,
+        samples: Vec::new(),
+    };
+    let click = track.sample_at_frame(7, fps) as usize;
+    assert_eq!(click, 10290);
+    samples[click] = 1.0;
+    let track = Track { samples, ..track };
+
+    // The waveform is centered on the frame time
+    let waveform = track.analyze(track.sample_at_frame(7, fps)).texture[1];
+    assert_eq!(waveform[AUDIO_SIZE / 2], 255);
+    assert!(waveform
+        .iter()
+        .enumerate()
+        .all(|(i, &value)| i == AUDIO_SIZE / 2 || value == 128));
+
+    for frame in [6, 8] {
+        let waveform = track.analyze(track.sample_at_frame(frame, fps)).texture[1];
+        assert!(waveform.iter().all(|&value| value == 128));
+    }
+}
+
+#[test]
+fn silence_outside_of_the_track() {
+    let track = Track {
+        sample_rate: RATE,
+        samples: sine(440.0, 0.5, 0.1),
+    };
+    let frame = track.analyze(track.sample_at(10.0));
+
+    assert_eq!(frame, Default::default());
+}
+
+#[test]
+fn shaders_read_the_audio() {
+    let path = write_wav("bass.wav", &[sine(100.0, 0.5, 1.0)]);
+    let track = Track::load(&path).unwrap();
+    let audio = track.analyze(track.sample_at(0.5));
+
+    let scene = Scene {
+        passes: vec![String::from(
+            "@fragment
+            fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+                let spectrum = textureLoad(audio, vec2u(2u, 0u), 0).r;
+                let waveform = textureLoad(audio, vec2u(300u, 1u), 0).r;
+                return vec4f(infos.bass, infos.treble, spectrum, waveform);
+            }",
+        )],
+        ..Default::default()
+    };
+    let frame = pollster::block_on(async {
+        let options = RenderOptions {
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            ..Default::default()
+        };
+        let mut renderer = OffscreenRenderer::new(1.0, [64, 64], &scene, &options)
+            .await
+            .unwrap();
+        renderer.set_audio(&audio);
+        renderer.render_frame_at(1.0).await.unwrap()
+    });
+
+    let expected = [
+        audio.bands.bass * 255.0,
+        audio.bands.treble * 255.0,
+        audio.texture[0][2] as f32,
+        audio.texture[1][300] as f32,
+    ];
+    for (value, expected) in frame.data[..4].iter().zip(expected) {
+        assert!(
+            (*value as f32 - expected).abs() <= 1.0,
+            "{:?} instead of {:?}",
+            &frame.data[..4],
+            expected
+        );
+    }
+}
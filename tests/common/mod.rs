@@ -0,0 +1,154 @@
+// Shared by several test crates, each uses a part of it
+#![allow(dead_code)]
+
+use image::{Rgba, RgbaImage};
+use shader_art_rs::app::{App, RenderOptions};
+use shader_art_rs::gpu::GpuOptions;
+use shader_art_rs::pipeline::Scene;
+use shader_art_rs::pixels;
+use std::path::PathBuf;
+
+/// Largest OKLab distance between two pixels still considered the same.
+pub const PIXEL_TOLERANCE: f32 = 0.02;
+
+/// Fraction of the pixels allowed to exceed `PIXEL_TOLERANCE`.
+pub const DIFFERENT_PIXELS_TOLERANCE: f32 = 0.001;
+
+/// Options rendering on the software adapter, so the references do not
+/// depend on the GPU of the machine.
+pub fn software() -> RenderOptions {
+    RenderOptions {
+        gpu: GpuOptions {
+            software: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// Renders `scene` at `time` through the headless app.
+pub fn render(
+    scene: &Scene,
+    resolution: [u32; 2],
+    time: f32,
+    options: &RenderOptions,
+) -> RgbaImage {
+    pollster::block_on(async {
+        let mut app = App::new_without_window(1.0, resolution, scene, options)
+            .await
+            .unwrap();
+        app.set_time(time);
+        let mut frames = Vec::new();
+        app.run(&mut frames).await.unwrap();
+        let data = pixels::to_rgba8(&frames.pop().unwrap(), app.output_format()).unwrap();
+        RgbaImage::from_raw(resolution[0], resolution[1], data).unwrap()
+    })
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+/// Compares `image` with `tests/golden/<name>.png`. On a mismatch the image
+/// and a diff are written to the test temporary directory.
+///
+/// `UPDATE_GOLDEN=1` writes the references instead.
+pub fn assert_golden(name: &str, image: &RgbaImage) {
+    let path = golden_dir().join(format!("{}.png", name));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(golden_dir()).unwrap();
+        image.save(&path).unwrap();
+        return;
+    }
+
+    let reference = match image::open(&path) {
+        Ok(reference) => reference.to_rgba8(),
+        Err(e) => panic!(
+            "Can not read `{}`: {}, run with UPDATE_GOLDEN=1 to create it",
+            path.display(),
+            e
+        ),
+    };
+
+    if reference.dimensions() != image.dimensions() {
+        panic!(
+            "`{}` is {:?} but the render is {:?}",
+            name,
+            reference.dimensions(),
+            image.dimensions()
+        );
+    }
+
+    let distances = reference
+        .pixels()
+        .zip(image.pixels())
+        .map(|(a, b)| distance(a, b))
+        .collect::<Vec<_>>();
+    let different = distances.iter().filter(|&&d| d > PIXEL_TOLERANCE).count();
+
+    if different as f32 > DIFFERENT_PIXELS_TOLERANCE * distances.len() as f32 {
+        let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden");
+        std::fs::create_dir_all(&dir).unwrap();
+        let actual = dir.join(format!("{}.png", name));
+        let diff = dir.join(format!("{}.diff.png", name));
+        image.save(&actual).unwrap();
+        diff_image(&reference, &distances).save(&diff).unwrap();
+
+        panic!(
+            "`{}` differs from the reference in {} pixels out of {}, max distance {:.3}\n  render: {}\n  diff: {}",
+            name,
+            different,
+            distances.len(),
+            distances.iter().fold(0.0f32, |a, &b| a.max(b)),
+            actual.display(),
+            diff.display()
+        );
+    }
+}
+
+// Dimmed reference with the differences in red
+fn diff_image(reference: &RgbaImage, distances: &[f32]) -> RgbaImage {
+    RgbaImage::from_fn(reference.width(), reference.height(), |x, y| {
+        let i = (y * reference.width() + x) as usize;
+        let pixel = reference.get_pixel(x, y);
+        let gray = ((pixel[0] as u32 + pixel[1] as u32 + pixel[2] as u32) / 12) as u8;
+        if distances[i] > PIXEL_TOLERANCE {
+            let red = (128.0 + distances[i] * 1270.0).min(255.0) as u8;
+            Rgba([red, gray, gray, 255])
+        } else {
+            Rgba([gray, gray, gray, 255])
+        }
+    })
+}
+
+/// Perceptual distance between two sRGB pixels, in OKLab.
+pub fn distance(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+    let [l1, a1, b1] = oklab(a);
+    let [l2, a2, b2] = oklab(b);
+    let alpha = (a[3] as f32 - b[3] as f32).abs() / 255.0;
+    ((l1 - l2).powi(2) + (a1 - a2).powi(2) + (b1 - b2).powi(2)).sqrt() + alpha
+}
+
+// https://bottosson.github.io/posts/oklab/
+fn oklab(pixel: &Rgba<u8>) -> [f32; 3] {
+    let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(|c| {
+        let c = c as f64 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    });
+
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040861 * l + 0.7827717670 * m - 0.8086757571 * s,
+    ]
+    .map(|v| v as f32)
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use common::software;
+use shader_art_rs::app::{App, RenderOptions};
+use shader_art_rs::pipeline::{Compute, Scene};
+use shader_art_rs::pixels;
+use shader_art_rs::project::Project;
+
+// Counts the dispatches in the storage buffer, one counter per pixel
+const COUNTER: &str = "
+@group(1) @binding(1) var<storage, read_write> state: array<f32>;
+
+@compute @workgroup_size(WORKGROUP_X, WORKGROUP_Y)
+fn cs_main(@builtin(global_invocation_id) id: vec3u) {
+    let size = textureDimensions(output);
+    if id.x >= size.x || id.y >= size.y {
+        return;
+    }
+    let i = id.y * size.x + id.x;
+    state[i] += 1.0;
+    textureStore(output, id.xy, vec4f(state[i], 0.0, 0.0, 1.0));
+}
+";
+
+const SHOW_COMPUTE: &str = "
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return textureLoad(previous_pass, vec2u(in.foo.xy), 0);
+}
+";
+
+const SIZE: u32 = 8;
+
+fn scene() -> Scene {
+    Scene {
+        passes: vec![SHOW_COMPUTE.to_string()],
+        compute: Some(Compute {
+            source: COUNTER.to_string(),
+            workgroup_size: [8, 8],
+            buffers: vec![4096],
+            source_map: Default::default(),
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn storage_buffers_advance_once_per_frame() {
+    // Every supersample and sub-frame reads the output of the same dispatch
+    let options = RenderOptions {
+        ssaa: 2,
+        motion_blur: 3,
+        format: wgpu::TextureFormat::Rgba16Float,
+        ..software()
+    };
+
+    let counts = pollster::block_on(async {
+        let mut app = App::new_without_window(1.0, [SIZE, SIZE], &scene(), &options)
+            .await
+            .unwrap();
+        let mut counts = Vec::new();
+        for frame in 0..3 {
+            app.set_time(frame as f32);
+            let mut frames = Vec::new();
+            app.run(&mut frames).await.unwrap();
+            let values = pixels::to_rgba32f(&frames.pop().unwrap(), options.format).unwrap();
+            counts.push(
+                values
+                    .chunks_exact(4)
+                    .map(|pixel| pixel[0])
+                    .collect::<Vec<_>>(),
+            );
+        }
+        counts
+    });
+
+    // Up to the rounding of the half floats averaging the samples
+    for (frame, counts) in counts.iter().enumerate() {
+        let expected = (frame + 1) as f32;
+        assert!(
+            counts.iter().all(|count| (count - expected).abs() < 0.01),
+            "frame {}: {:?}",
+            frame,
+            counts
+        );
+    }
+}
+
+#[test]
+fn compute_shaders_are_not_tiled() {
+    pollster::block_on(async {
+        let mut app = App::new_without_window(1.0, [SIZE, SIZE], &scene(), &software())
+            .await
+            .unwrap();
+        assert!(app.set_tile([SIZE * 2, SIZE], [SIZE, 0]).is_err());
+        assert!(app.set_tile([SIZE, SIZE], [0, 0]).is_ok());
+    });
+}
+
+#[test]
+fn overflowing_workgroup_sizes_are_rejected() {
+    let project = Project {
+        compute: Some("compute.wgsl".into()),
+        workgroup_size: [65536, 65536],
+        ..Default::default()
+    };
+    let Err(error) = project.scene() else {
+        panic!("The workgroup size was accepted");
+    };
+    let error = error.to_string();
+    assert!(error.contains("65536x65536"), "{}", error);
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use common::{distance, software};
+use image::RgbaImage;
+use shader_art_rs::app::{App, RenderOptions};
+use shader_art_rs::cpu;
+use shader_art_rs::error::ShaderArtError;
+use shader_art_rs::gpu::{Backend, GpuOptions};
+use shader_art_rs::pipeline::Scene;
+use shader_art_rs::project::Project;
+use shader_art_rs::ui;
+use std::path::Path;
+
+// Pixels where the shader is near a singularity differ between implementations
+// of `pow` and `exp`.
+const DIFFERENT_PIXELS_TOLERANCE: f32 = 0.01;
+
+fn compare(resolution: [u32; 2], time: f32, options: &RenderOptions) {
+    let (gpu, cpu) = pollster::block_on(async {
+        let mut app = App::new_without_window(1.0, resolution, &Scene::default(), options)
+            .await
+            .unwrap();
+        app.set_time(time);
+        let mut frames = Vec::new();
+        app.run(&mut frames).await.unwrap();
+        let cpu = cpu::render_frame(&app.pipeline.common, resolution, options).unwrap();
+        (frames.pop().unwrap(), cpu)
+    });
+
+    let [width, height] = resolution;
+    let gpu = RgbaImage::from_raw(width, height, gpu).unwrap();
+    let cpu = RgbaImage::from_raw(width, height, cpu).unwrap();
+    let different = gpu
+        .pixels()
+        .zip(cpu.pixels())
+        .filter(|(a, b)| distance(a, b) > common::PIXEL_TOLERANCE)
+        .count();
+
+    assert!(
+        different as f32 <= DIFFERENT_PIXELS_TOLERANCE * (width * height) as f32,
+        "{} pixels out of {} differ",
+        different,
+        width * height
+    );
+}
+
+#[test]
+fn cpu_matches_the_gpu() {
+    compare([64, 48], 1.0, &software());
+    compare([37, 51], 7.25, &software());
+}
+
+#[test]
+fn cpu_matches_the_gpu_with_supersampling_and_motion_blur() {
+    let options = RenderOptions {
+        ssaa: 2,
+        motion_blur: 3,
+        ..software()
+    };
+    compare([40, 40], 2.0, &options);
+}
+
+fn scene(preset: Option<&str>) -> Scene {
+    Project {
+        preset: preset.map(String::from),
+        ..Default::default()
+    }
+    .scene()
+    .unwrap()
+}
+
+#[test]
+fn only_the_bundled_shader_is_rendered() {
+    assert!(cpu::check(&scene(None)).is_ok());
+    assert!(cpu::check(&scene(Some("fractal-rings"))).is_ok());
+
+    let error = cpu::check(&scene(Some("plasma"))).unwrap_err();
+    assert!(
+        error.to_string().contains("need a GPU adapter"),
+        "{}",
+        error
+    );
+    // Equal passes are not enough
+    assert!(cpu::check(&Scene::default()).is_err());
+}
+
+#[test]
+fn stills_fall_back_to_the_cpu_without_adapter() {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("cpu_fallback.png");
+    let _ = std::fs::remove_file(&path);
+    let project = Project {
+        outputs: vec![path.clone()],
+        resolution: "24x16".into(),
+        // Not available on this platform
+        gpu: GpuOptions {
+            backend: Some(if cfg!(windows) {
+                Backend::Metal
+            } else {
+                Backend::Dx12
+            }),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    pollster::block_on(ui::still(&project)).unwrap();
+    assert_eq!(image::image_dimensions(&path).unwrap(), (24, 16));
+}
+
+#[test]
+fn oversized_frames_are_rejected() {
+    let options = RenderOptions {
+        ssaa: 4,
+        ..software()
+    };
+    let common = cpu::common(1.0, [u32::MAX, u32::MAX], &options);
+    let frame = cpu::render_frame(&common, [u32::MAX, u32::MAX], &options);
+    assert!(matches!(frame, Err(ShaderArtError::Project(_))));
+}
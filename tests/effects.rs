@@ -0,0 +1,155 @@
+use image::{Rgba, RgbaImage};
+use shader_art_rs::app::{App, RenderOptions};
+use shader_art_rs::effects::{Bloom, ChromaticAberration, Crt, Effect, Grain, Vignette};
+use shader_art_rs::pipeline::Scene;
+
+const SIZE: u32 = 64;
+
+// Shows `channel0` as is, so the effects are the only change.
+const SHOW_INPUT: &str = "
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return textureSampleLevel(channel0, channel_sampler, screen_uv(in.pos), 0.0);
+}
+";
+
+fn render(input: &RgbaImage, effects: Vec<Effect>) -> RgbaImage {
+    let scene = Scene {
+        passes: vec![SHOW_INPUT.to_string()],
+        inputs: vec![input.clone()],
+        effects,
+        ..Default::default()
+    };
+
+    pollster::block_on(async {
+        let mut app = App::new_without_window(1.0, [SIZE, SIZE], &scene, &RenderOptions::default())
+            .await
+            .unwrap();
+        app.set_time(1.0);
+        let mut frames = Vec::new();
+        app.run(&mut frames).await.unwrap();
+        RgbaImage::from_raw(SIZE, SIZE, frames.pop().unwrap()).unwrap()
+    })
+}
+
+fn uniform(value: u8) -> RgbaImage {
+    RgbaImage::from_pixel(SIZE, SIZE, Rgba([value, value, value, 255]))
+}
+
+fn max_difference(a: &RgbaImage, b: &RgbaImage) -> u8 {
+    a.as_raw()
+        .iter()
+        .zip(b.as_raw())
+        .map(|(x, y)| x.abs_diff(*y))
+        .max()
+        .unwrap()
+}
+
+#[test]
+fn without_effects_the_input_is_unchanged() {
+    let input = RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+        Rgba([(x * 4) as u8, (y * 4) as u8, 128, 255])
+    });
+    assert!(max_difference(&render(&input, vec![]), &input) <= 1);
+}
+
+#[test]
+fn bloom_spreads_the_bright_parts() {
+    let input = RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+        let inside = (28..36).contains(&x) && (28..36).contains(&y);
+        Rgba(if inside { [255; 4] } else { [0, 0, 0, 255] })
+    });
+    let output = render(
+        &input,
+        vec![Effect::Bloom(Bloom {
+            threshold: 0.5,
+            radius: 8.0,
+            intensity: 1.0,
+        })],
+    );
+
+    assert!(output.get_pixel(24, 32)[0] > 0);
+    assert_eq!(output.get_pixel(0, 0)[0], 0);
+    assert_eq!(output.get_pixel(32, 32)[0], 255);
+}
+
+#[test]
+fn vignette_darkens_the_corners_only() {
+    let input = uniform(200);
+    let output = render(
+        &input,
+        vec![Effect::Vignette(Vignette {
+            strength: 1.0,
+            radius: 0.5,
+            softness: 0.5,
+        })],
+    );
+
+    assert!(output.get_pixel(32, 32)[0].abs_diff(200) <= 1);
+    assert!(output.get_pixel(0, 0)[0] < 20);
+}
+
+#[test]
+fn grain_keeps_the_average() {
+    let input = uniform(128);
+    let output = render(&input, vec![Effect::Grain(Grain { amount: 0.2 })]);
+
+    let values = output.pixels().map(|p| p[0] as f64).collect::<Vec<_>>();
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    assert!((mean - 128.0).abs() < 4.0, "mean {}", mean);
+    assert!(max_difference(&output, &input) > 10);
+}
+
+#[test]
+fn chromatic_aberration_splits_red_and_blue() {
+    let line = 56;
+    let input = RgbaImage::from_fn(SIZE, SIZE, |x, _| {
+        Rgba(if x == line { [255; 4] } else { [0, 0, 0, 255] })
+    });
+    let output = render(
+        &input,
+        vec![Effect::ChromaticAberration(ChromaticAberration {
+            amount: 8.0,
+        })],
+    );
+
+    let peak = |channel: usize| {
+        (0..SIZE)
+            .max_by_key(|&x| output.get_pixel(x, SIZE / 2)[channel])
+            .unwrap()
+    };
+    assert!(peak(0) < line, "red at {}", peak(0));
+    assert_eq!(peak(1), line);
+    assert!(peak(2) > line, "blue at {}", peak(2));
+}
+
+#[test]
+fn crt_draws_scanlines_and_curves_the_screen() {
+    let input = uniform(200);
+
+    let flat = render(
+        &input,
+        vec![Effect::Crt(Crt {
+            curvature: 0.0,
+            scanlines: 0.8,
+            line_height: 4.0,
+        })],
+    );
+    let column = (0..SIZE)
+        .map(|y| flat.get_pixel(32, y)[0])
+        .collect::<Vec<_>>();
+    let (min, max) = (column.iter().min().unwrap(), column.iter().max().unwrap());
+    assert!(max - min > 50, "rows between {} and {}", min, max);
+    assert_eq!(column[0], column[4]);
+
+    let curved = render(
+        &input,
+        vec![Effect::Crt(Crt {
+            curvature: 0.3,
+            scanlines: 0.0,
+            line_height: 4.0,
+        })],
+    );
+    assert_eq!(curved.get_pixel(0, 0)[0], 0);
+    assert!(curved.get_pixel(32, 32)[0].abs_diff(200) <= 1);
+}
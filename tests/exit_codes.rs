@@ -0,0 +1,45 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn dir() -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("exit_codes");
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Exit code of a still rendered with `args`
+fn still(name: &str, args: &[&str]) -> i32 {
+    let output = dir().join(format!("{}.png", name));
+    let status = Command::new(env!("CARGO_BIN_EXE_shader-art-rs"))
+        .arg("still")
+        .arg(&output)
+        .args(["--software", "--resolution", "16x16"])
+        .args(args)
+        .output()
+        .unwrap()
+        .status;
+    status.code().unwrap()
+}
+
+#[test]
+fn invalid_projects_exit_with_2() {
+    let project = dir().join("invalid.toml");
+    std::fs::write(&project, "fps = \"many\"\n").unwrap();
+    assert_eq!(
+        still("invalid", &["--project", project.to_str().unwrap()]),
+        2
+    );
+}
+
+#[test]
+fn shaders_that_do_not_compile_exit_with_6() {
+    let shader = dir().join("broken.wgsl");
+    std::fs::write(&shader, "fn fs_main( {\n").unwrap();
+    assert_eq!(still("broken", &["--shader", shader.to_str().unwrap()]), 6);
+}
+
+#[test]
+fn missing_files_exit_with_9() {
+    let shader = dir().join("missing.wgsl");
+    assert_eq!(still("missing", &["--shader", shader.to_str().unwrap()]), 9);
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use common::software;
+use shader_art_rs::app::{App, RenderOptions};
+use shader_art_rs::error::ShaderArtError;
+use shader_art_rs::pipeline::Scene;
+
+#[test]
+fn padding_does_not_overflow() {
+    assert_eq!(App::calculate_padding(1, 4), 256);
+    assert_eq!(App::calculate_padding(16384, 16) * 16384, 1 << 32);
+}
+
+#[test]
+fn frames_larger_than_the_buffers_are_rejected() {
+    let options = RenderOptions {
+        format: wgpu::TextureFormat::Rgba16Float,
+        ..software()
+    };
+    let instance = options.gpu.instance();
+    let limits = pollster::block_on(options.gpu.adapter(&instance, None))
+        .unwrap()
+        .limits();
+
+    // Only adapters with small buffers can not read the largest textures back
+    let side = limits.max_texture_dimension_2d;
+    if App::calculate_padding(side, 8) * side as u64 <= limits.max_buffer_size {
+        return;
+    }
+
+    let app = pollster::block_on(App::new_without_window(
+        1.0,
+        [side, side],
+        &Scene::default(),
+        &options,
+    ));
+    assert!(matches!(app, Err(ShaderArtError::Unsupported(_))));
+}
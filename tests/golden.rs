@@ -0,0 +1,76 @@
+mod common;
+
+use common::{assert_golden, render, software};
+use shader_art_rs::app::RenderOptions;
+use shader_art_rs::pipeline::Scene;
+
+// Red grows to the right and green to the bottom, shows flips and row shifts.
+const GRADIENT: &str = "
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let uv = screen_uv(in.pos);
+    return vec4f(uv.x, uv.y, 0.5 + 0.5 * sin(infos.time), 1.0);
+}
+";
+
+// Inverts the previous pass, mirrored horizontally.
+const MIRROR: &str = "
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let uv = screen_uv(in.local) * vec2f(-1.0, 1.0) + vec2f(1.0, 0.0);
+    let color = textureSampleLevel(previous_pass, channel_sampler, uv, 0.0);
+    return vec4f(1.0 - color.rgb, 1.0);
+}
+";
+
+fn scene(passes: &[&str]) -> Scene {
+    Scene {
+        passes: passes.iter().map(|pass| pass.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn bundled_shader() {
+    let image = render(&Scene::default(), [64, 64], 1.0, &software());
+    assert_golden("bundled_64x64_t1", &image);
+}
+
+#[test]
+fn bundled_shader_at_an_odd_size() {
+    let image = render(&Scene::default(), [61, 37], 2.5, &software());
+    assert_golden("bundled_61x37_t2.5", &image);
+}
+
+// 97 pixels are 388 bytes, the rows read back are padded to 512
+#[test]
+fn gradient_rows_are_unpadded() {
+    let image = render(&scene(&[GRADIENT]), [97, 13], 0.0, &software());
+    assert_golden("gradient_97x13", &image);
+}
+
+#[test]
+fn passes_are_chained() {
+    let image = render(&scene(&[GRADIENT, MIRROR]), [40, 24], 1.0, &software());
+    assert_golden("mirror_40x24", &image);
+}
+
+#[test]
+fn supersampling() {
+    let options = RenderOptions {
+        ssaa: 2,
+        ..software()
+    };
+    let image = render(&Scene::default(), [48, 48], 1.0, &options);
+    assert_golden("bundled_48x48_ssaa2", &image);
+}
+
+#[test]
+fn half_float_output() {
+    let options = RenderOptions {
+        format: wgpu::TextureFormat::Rgba16Float,
+        ..software()
+    };
+    let image = render(&scene(&[GRADIENT]), [33, 21], 1.0, &options);
+    assert_golden("gradient_33x21_half", &image);
+}
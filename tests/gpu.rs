@@ -0,0 +1,111 @@
+use shader_art_rs::error::ShaderArtError;
+use shader_art_rs::gpu::{describe_adapters, GpuOptions};
+
+fn pick(options: &GpuOptions) -> Result<wgpu::AdapterInfo, ShaderArtError> {
+    let instance = options.instance();
+    pollster::block_on(options.adapter(&instance, None)).map(|adapter| adapter.get_info())
+}
+
+// Index in the `adapters` list of the software adapter
+fn software_index() -> usize {
+    let options = GpuOptions::default();
+    let name = pick(&GpuOptions {
+        software: true,
+        ..Default::default()
+    })
+    .unwrap()
+    .name;
+
+    options
+        .adapters(&options.instance())
+        .iter()
+        .position(|adapter| adapter.get_info().name == name)
+        .unwrap()
+}
+
+#[test]
+fn adapters_are_picked_by_index_or_name() {
+    let index = software_index();
+    let by_index = pick(&GpuOptions {
+        adapter: Some(index.to_string()),
+        software: true,
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(by_index.device_type, wgpu::DeviceType::Cpu);
+
+    // Any case-insensitive part of the name
+    let part = by_index
+        .name
+        .split_whitespace()
+        .next()
+        .unwrap()
+        .to_uppercase();
+    let by_name = pick(&GpuOptions {
+        adapter: Some(part),
+        software: true,
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(by_name.name, by_index.name);
+    assert_eq!(by_name.device_type, wgpu::DeviceType::Cpu);
+}
+
+#[test]
+fn named_adapters_must_be_software_ones_when_asked() {
+    let options = GpuOptions::default();
+    let hardware = options
+        .adapters(&options.instance())
+        .iter()
+        .position(|adapter| adapter.get_info().device_type != wgpu::DeviceType::Cpu);
+    // Machines without a GPU have nothing to refuse
+    let Some(hardware) = hardware else {
+        return;
+    };
+
+    let picked = pick(&GpuOptions {
+        adapter: Some(hardware.to_string()),
+        software: true,
+        ..Default::default()
+    });
+    assert!(matches!(picked, Err(ShaderArtError::NoAdapter(_))));
+}
+
+#[test]
+fn unmatched_adapters_are_errors() {
+    for query in ["no such adapter", "999"] {
+        let picked = pick(&GpuOptions {
+            adapter: Some(query.into()),
+            ..Default::default()
+        });
+        let Err(ShaderArtError::NoAdapter(error)) = picked else {
+            panic!("`{}` matched an adapter", query);
+        };
+        assert!(error.contains(query), "{}", error);
+    }
+}
+
+#[test]
+fn the_list_describes_each_adapter() {
+    let index = software_index();
+    let name = pick(&GpuOptions {
+        software: true,
+        ..Default::default()
+    })
+    .unwrap()
+    .name;
+
+    let description = describe_adapters(&GpuOptions::default()).unwrap();
+    assert!(
+        description.contains(&format!("{}: {}\n", index, name)),
+        "{}",
+        description
+    );
+    assert!(description.contains("  Type: Cpu\n"), "{}", description);
+    assert!(
+        description.contains("max_texture_dimension_2d"),
+        "{}",
+        description
+    );
+    assert!(description.contains("  Features:\n"), "{}", description);
+}
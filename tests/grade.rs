@@ -0,0 +1,189 @@
+mod common;
+
+use common::software;
+use image::{Rgba, RgbaImage};
+use shader_art_rs::app::{App, RenderOptions};
+use shader_art_rs::grade::{Grading, Lut, ToneMapping};
+use shader_art_rs::pipeline::Scene;
+use shader_art_rs::pixels;
+
+const SIZE: u32 = 16;
+
+const HDR: &str = "
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return vec4f(2.0, 0.5, 0.0, 1.0);
+}
+";
+
+const SHOW_INPUT: &str = "
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return textureSampleLevel(channel0, channel_sampler, screen_uv(in.pos), 0.0);
+}
+";
+
+fn render(scene: &Scene, format: wgpu::TextureFormat) -> Vec<u8> {
+    let options = RenderOptions {
+        format,
+        ..software()
+    };
+    pollster::block_on(async {
+        let mut app = App::new_without_window(1.0, [SIZE, SIZE], scene, &options)
+            .await
+            .unwrap();
+        let mut frames = Vec::new();
+        app.run(&mut frames).await.unwrap();
+        frames.pop().unwrap()
+    })
+}
+
+// The curves of `grade.wgsl`
+fn reinhard(x: f32) -> f32 {
+    x / (1.0 + x)
+}
+
+fn aces(x: f32) -> f32 {
+    ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0)
+}
+
+fn agx(color: [f32; 3]) -> [f32; 3] {
+    // Columns of the matrices
+    let inset: [[f64; 3]; 3] = [
+        [0.842479062253094, 0.0423282422610123, 0.0423756549057051],
+        [0.0784335999999992, 0.878468636469772, 0.0784336],
+        [0.0792237451477643, 0.0791661274605434, 0.879142973793104],
+    ];
+    let outset: [[f64; 3]; 3] = [
+        [1.19687900512017, -0.0528968517574562, -0.0529716355144438],
+        [-0.0980208811401368, 1.15190312990417, -0.0980434501171241],
+        [-0.0990297440797205, -0.0989611768448433, 1.15107367264116],
+    ];
+    let multiply = |m: [[f64; 3]; 3], [x, y, z]: [f64; 3]| {
+        [0, 1, 2].map(|i| m[0][i] * x + m[1][i] * y + m[2][i] * z)
+    };
+    let (min_ev, max_ev) = (-12.47393, 4.026069);
+
+    let contrast = |x: f64| {
+        let encoded = (x.max(1e-10).log2().clamp(min_ev, max_ev) - min_ev) / (max_ev - min_ev);
+        let (x2, x4) = (encoded * encoded, encoded.powi(4));
+        15.5 * x4 * x2 - 40.14 * x4 * encoded + 31.96 * x4 - 6.868 * x2 * encoded
+            + 0.4298 * x2
+            + 0.1191 * encoded
+            - 0.00232
+    };
+    multiply(outset, multiply(inset, color.map(f64::from)).map(contrast))
+        .map(|x| x.max(0.0).powf(2.2) as f32)
+}
+
+#[test]
+fn tone_mappings_follow_their_curves() {
+    let hdr = [2.0, 0.5, 0.0];
+    let cases = [
+        (ToneMapping::None, hdr),
+        (ToneMapping::Reinhard, hdr.map(reinhard)),
+        (ToneMapping::Aces, hdr.map(aces)),
+        (ToneMapping::Agx, agx(hdr)),
+    ];
+
+    for (tone_mapping, expected) in cases {
+        let scene = Scene {
+            passes: vec![HDR.to_string()],
+            grading: Grading {
+                tone_mapping,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let format = wgpu::TextureFormat::Rgba16Float;
+        let values = pixels::to_rgba32f(&render(&scene, format), format).unwrap();
+
+        for (value, channel) in values[..3].iter().zip(expected) {
+            assert!(
+                (value - channel).abs() <= 0.01 * channel.max(0.1),
+                "{:?}: {:?} instead of {:?}",
+                tone_mapping,
+                &values[..3],
+                expected
+            );
+        }
+    }
+}
+
+fn identity_cube(size: u32) -> String {
+    let mut cube = format!("TITLE \"Identity\"\nLUT_3D_SIZE {}\n", size);
+    let step = |i: u32| i as f32 / (size - 1) as f32;
+    for b in 0..size {
+        for g in 0..size {
+            for r in 0..size {
+                cube += &format!("{} {} {}\n", step(r), step(g), step(b));
+            }
+        }
+    }
+    cube
+}
+
+#[test]
+fn an_identity_lut_keeps_the_image() {
+    let input = RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+        Rgba([(x * 16) as u8, (y * 16) as u8, 200, 255])
+    });
+    let scene = Scene {
+        passes: vec![SHOW_INPUT.to_string()],
+        inputs: vec![input.clone()],
+        grading: Grading {
+            lut: Some(Lut::parse(&identity_cube(5)).unwrap()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let output = render(&scene, wgpu::TextureFormat::Rgba8UnormSrgb);
+    let difference = output
+        .iter()
+        .zip(input.as_raw())
+        .map(|(a, b)| a.abs_diff(*b))
+        .max()
+        .unwrap();
+    assert!(difference <= 1, "{}", difference);
+}
+
+#[test]
+fn cube_files_are_parsed() {
+    let lut = Lut::parse(
+        "# Comment
+TITLE \"Test\"
+DOMAIN_MIN 0 0 0
+DOMAIN_MAX 1 2 1
+LUT_3D_SIZE 2
+
+0 0 0
+1 0 0
+0 1 0
+1 1 0
+0 0 1
+1 0 1
+0 1 1
+1 1 1
+",
+    )
+    .unwrap();
+
+    assert_eq!(lut.size, 2);
+    assert_eq!(lut.domain_min, [0.0; 3]);
+    assert_eq!(lut.domain_max, [1.0, 2.0, 1.0]);
+    assert_eq!(lut.data.len(), 8);
+    assert_eq!(lut.data[1], [1.0, 0.0, 0.0]);
+    assert_eq!(lut.data[6], [0.0, 1.0, 1.0]);
+}
+
+#[test]
+fn invalid_cube_files_are_rejected() {
+    let error = |content: &str| format!("{:#}", Lut::parse(content).unwrap_err());
+
+    assert!(error("LUT_3D_SIZE 2\n0 0 0\n1 1 1\n").contains("Expected 8 colors"));
+    assert!(error("LUT_3D_SIZE 1\n0 0 0\n").contains("Invalid LUT_3D_SIZE"));
+    assert!(error(&identity_cube(2).replace("LUT_3D_SIZE 2\n", "")).contains("Missing LUT_3D_SIZE"));
+    assert!(error("LUT_3D_SIZE 2\n0 zero 0\n").contains("Invalid number on line 2"));
+    assert!(error("LUT_3D_SIZE 2\n0 0\n").contains("Expected 3 values on line 2"));
+}
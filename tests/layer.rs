@@ -0,0 +1,81 @@
+use shader_art_rs::app::RenderOptions;
+use shader_art_rs::layer::ArtLayer;
+use shader_art_rs::offscreen::OffscreenRenderer;
+use shader_art_rs::pipeline::Scene;
+
+const SIZE: u32 = 64;
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+// Renders with a device created here, like an application embedding the art.
+async fn render_with_own_device(scene: &Scene, time: f32) -> Vec<u8> {
+    let instance = wgpu::Instance::default();
+    let adapter = instance.request_adapter(&Default::default()).await.unwrap();
+    let (device, queue) = adapter
+        .request_device(&Default::default(), None)
+        .await
+        .unwrap();
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&Default::default());
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (SIZE * SIZE * 4) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut layer = ArtLayer::new(&device, &queue, FORMAT, [SIZE, SIZE], scene).unwrap();
+    layer.set_time(&queue, time);
+
+    let mut encoder = device.create_command_encoder(&Default::default());
+    layer.encode(&device, &mut encoder, &view);
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(SIZE * 4),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+    queue.submit([encoder.finish()]);
+
+    buffer.slice(..).map_async(wgpu::MapMode::Read, |_| {});
+    device.poll(wgpu::Maintain::Wait);
+    let data = buffer.slice(..).get_mapped_range().to_vec();
+    data
+}
+
+#[test]
+fn the_layer_renders_like_the_offscreen_renderer() {
+    let scene = Scene::default();
+    let time = 2.5;
+
+    let layer = pollster::block_on(render_with_own_device(&scene, time));
+    let offscreen = pollster::block_on(async {
+        OffscreenRenderer::new(1.0, [SIZE, SIZE], &scene, &RenderOptions::default())
+            .await
+            .unwrap()
+            .render_frame_at(time)
+            .await
+            .unwrap()
+    });
+
+    assert_eq!(layer, offscreen.data);
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use common::{render, software};
+use shader_art_rs::app::{App, RenderOptions};
+use shader_art_rs::pipeline::Scene;
+use shader_art_rs::pixels;
+
+// Overexposed after the time 1, so half of the sub-frames around it are lit
+const FLASH: &str = "
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return vec4f(vec3f(select(0.0, 8.0, infos.time > 1.0)), 1.0);
+}
+";
+
+fn options(format: wgpu::TextureFormat) -> RenderOptions {
+    RenderOptions {
+        motion_blur: 4,
+        format,
+        ..software()
+    }
+}
+
+fn scene() -> Scene {
+    Scene {
+        passes: vec![FLASH.to_string()],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn clipped_outputs_clamp_the_sub_frames() {
+    let options = options(wgpu::TextureFormat::Rgba8UnormSrgb);
+    let image = render(&scene(), [8, 8], 1.0, &options);
+
+    // Half of the exposure is white, 0.5 in linear light
+    let expected = pixels::to_rgba8(
+        bytemuck::cast_slice(&[0.5f32, 0.5, 0.5, 1.0]),
+        wgpu::TextureFormat::Rgba32Float,
+    )
+    .unwrap();
+    for pixel in image.pixels() {
+        assert!(pixel.0[0].abs_diff(expected[0]) <= 1, "{:?}", pixel);
+    }
+}
+
+#[test]
+fn float_outputs_keep_the_highlights() {
+    let options = options(wgpu::TextureFormat::Rgba16Float);
+    let frame = pollster::block_on(async {
+        let mut app = App::new_without_window(1.0, [8, 8], &scene(), &options)
+            .await
+            .unwrap();
+        app.set_time(1.0);
+        let mut frames = Vec::new();
+        app.run(&mut frames).await.unwrap();
+        frames.pop().unwrap()
+    });
+
+    let values = pixels::to_rgba32f(&frame, options.format).unwrap();
+    assert!((values[0] - 4.0).abs() < 0.01, "{}", values[0]);
+}
+
+#[test]
+fn the_shutter_covers_a_part_of_the_frame_interval() {
+    // The sub-frames of a 180° shutter span a quarter of the frame interval
+    // on each side, 0.0125 at 20 fps and 0.05 at 5 fps
+    let lit = |fps: f32| {
+        let options = RenderOptions {
+            fps,
+            ..options(wgpu::TextureFormat::Rgba16Float)
+        };
+        let frame = pollster::block_on(async {
+            let mut app = App::new_without_window(100.0, [8, 8], &scene(), &options)
+                .await
+                .unwrap();
+            app.set_time(0.99);
+            let mut frames = Vec::new();
+            app.run(&mut frames).await.unwrap();
+            frames.pop().unwrap()
+        });
+        pixels::to_rgba32f(&frame, options.format).unwrap()[0] / 8.0
+    };
+
+    assert_eq!(lit(20.0), 0.0);
+    assert!((lit(5.0) - 0.5).abs() < 0.01, "{}", lit(5.0));
+}
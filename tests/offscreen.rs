@@ -0,0 +1,87 @@
+use shader_art_rs::app::RenderOptions;
+use shader_art_rs::error::ShaderArtError;
+use shader_art_rs::offscreen::OffscreenRenderer;
+use shader_art_rs::pipeline::Scene;
+use shader_art_rs::pixels;
+use shader_art_rs::project::Project;
+
+#[test]
+fn frames_are_tightly_packed_and_follow_the_times() {
+    let scene = Scene::default();
+    let mut renderer = pollster::block_on(OffscreenRenderer::new(
+        100.0,
+        [33, 17],
+        &scene,
+        &RenderOptions::default(),
+    ))
+    .unwrap();
+    let frames = renderer
+        .frames(0.0..0.9, 4.0)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    // Spaced like the frames of an export at the same speed and frame rate
+    let project = Project {
+        speed: Some(100.0),
+        fps: 4,
+        ..Default::default()
+    };
+    assert_eq!(renderer.frame_interval(), project.frame_interval());
+    assert_eq!(frames.len(), 4);
+    for frame in &frames {
+        assert_eq!((frame.width, frame.height), (33, 17));
+        assert_eq!(frame.data.len(), 33 * 17 * 4);
+    }
+    assert_ne!(frames[0].data, frames[1].data);
+}
+
+#[test]
+fn invalid_frame_rates_and_speeds_yield_a_single_error() {
+    let mut renderer = pollster::block_on(OffscreenRenderer::new(
+        1.0,
+        [8, 8],
+        &Scene::default(),
+        &RenderOptions::default(),
+    ))
+    .unwrap();
+
+    for fps in [-4.0, 0.0, f32::NAN, f32::INFINITY] {
+        let frames = renderer.frames(0.0..1.0, fps).collect::<Vec<_>>();
+        assert_eq!(frames.len(), 1, "{}", fps);
+        assert!(
+            matches!(frames[0], Err(ShaderArtError::Project(_))),
+            "{}",
+            fps
+        );
+    }
+
+    // Frozen animations never reach the end
+    renderer.set_speed(0.0);
+    let frames = renderer.frames(0.0..1.0, 30.0).collect::<Vec<_>>();
+    assert_eq!(frames.len(), 1);
+    assert!(matches!(frames[0], Err(ShaderArtError::Project(_))));
+}
+
+#[test]
+fn formats_that_can_not_be_read_back_are_rejected() {
+    let format = wgpu::TextureFormat::Bgra8UnormSrgb;
+    let options = RenderOptions {
+        format,
+        ..Default::default()
+    };
+    let result = pollster::block_on(OffscreenRenderer::new(
+        1.0,
+        [8, 8],
+        &Scene::default(),
+        &options,
+    ));
+    assert!(matches!(
+        result,
+        Err(ShaderArtError::UnsupportedFormat(f)) if f == format
+    ));
+
+    assert!(matches!(
+        pixels::to_rgba8(&[0; 4], format),
+        Err(ShaderArtError::UnsupportedFormat(_))
+    ));
+}
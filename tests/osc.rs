@@ -0,0 +1,169 @@
+mod common;
+
+use shader_art_rs::app::App;
+use shader_art_rs::osc::{self, Argument, Command, Message, Server, Switch};
+use shader_art_rs::pipeline::Scene;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// Null terminated and padded to 4 bytes
+fn string(packet: &mut Vec<u8>, value: &str) {
+    packet.extend_from_slice(value.as_bytes());
+    packet.resize((packet.len() + 1).next_multiple_of(4), 0);
+}
+
+enum Value<'a> {
+    Int(i32),
+    Float(f32),
+    String(&'a str),
+}
+
+fn message(address: &str, values: &[Value]) -> Vec<u8> {
+    let mut packet = Vec::new();
+    string(&mut packet, address);
+    let tags = values
+        .iter()
+        .map(|value| match value {
+            Value::Int(_) => 'i',
+            Value::Float(_) => 'f',
+            Value::String(_) => 's',
+        })
+        .collect::<String>();
+    string(&mut packet, &format!(",{}", tags));
+    for value in values {
+        match value {
+            Value::Int(value) => packet.extend_from_slice(&value.to_be_bytes()),
+            Value::Float(value) => packet.extend_from_slice(&value.to_be_bytes()),
+            Value::String(value) => string(&mut packet, value),
+        }
+    }
+    packet
+}
+
+fn bundle(messages: &[Vec<u8>]) -> Vec<u8> {
+    let mut packet = Vec::new();
+    string(&mut packet, "#bundle");
+    // Immediately
+    packet.extend_from_slice(&1u64.to_be_bytes());
+    for message in messages {
+        packet.extend_from_slice(&(message.len() as i32).to_be_bytes());
+        packet.extend_from_slice(message);
+    }
+    packet
+}
+
+#[test]
+fn messages_and_bundles_are_decoded() {
+    let packet = bundle(&[
+        message("/param/zoom", &[Value::Float(1.5)]),
+        message("/preset", &[Value::String("next")]),
+    ]);
+
+    assert_eq!(
+        osc::decode(&packet).unwrap(),
+        [
+            Message {
+                address: "/param/zoom".into(),
+                arguments: vec![Argument::Float(1.5)],
+            },
+            Message {
+                address: "/preset".into(),
+                arguments: vec![Argument::String("next".into())],
+            },
+        ]
+    );
+
+    assert!(osc::decode(&packet[..packet.len() - 2]).is_err());
+    assert!(osc::decode(b"zoom\0\0\0\0").is_err());
+}
+
+#[test]
+fn deeply_nested_bundles_are_rejected() {
+    let nested = |depth: usize| {
+        (0..depth).fold(message("/time", &[Value::Float(1.0)]), |packet, _| {
+            bundle(&[packet])
+        })
+    };
+
+    assert_eq!(osc::decode(&nested(8)).unwrap().len(), 1);
+    assert!(osc::decode(&nested(9)).is_err());
+    // As many levels as a UDP packet holds
+    assert!(osc::decode(&nested(4000)).is_err());
+}
+
+#[test]
+fn the_server_receives_commands() {
+    let server = Server::bind("127.0.0.1:0").unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    for packet in [
+        message("/param/zoom", &[Value::Float(1.5)]),
+        message("/speed", &[Value::Int(2)]),
+        bundle(&[
+            message("/time", &[Value::Float(10.0)]),
+            message("/preset", &[Value::String("previous")]),
+        ]),
+        message("/preset", &[Value::Int(2)]),
+        message("/preset", &[Value::String("plasma")]),
+        message("/unknown", &[]),
+    ] {
+        sender.send_to(&packet, server.local_addr()).unwrap();
+    }
+
+    let mut commands = Vec::new();
+    while let Some(command) = server.next_command(TIMEOUT) {
+        commands.push(command);
+        if commands.len() == 7 {
+            break;
+        }
+    }
+
+    let (invalid, commands): (Vec<_>, Vec<_>) = commands.into_iter().partition(Result::is_err);
+    assert_eq!(invalid.len(), 1);
+    assert_eq!(
+        commands.into_iter().map(Result::unwrap).collect::<Vec<_>>(),
+        [
+            Command::Param("zoom".into(), 1.5),
+            Command::Speed(2.0),
+            Command::Time(10.0),
+            Command::Preset(Switch::Previous),
+            Command::Preset(Switch::Index(2)),
+            Command::Preset(Switch::Entry("plasma".into())),
+        ]
+    );
+}
+
+#[test]
+fn commands_change_the_app() {
+    let scene = Scene {
+        params: vec![("zoom".into(), 1.0)],
+        ..Default::default()
+    };
+    let mut app = pollster::block_on(App::new_without_window(
+        1.0,
+        [64, 64],
+        &scene,
+        &common::software(),
+    ))
+    .unwrap();
+
+    for command in [
+        Command::Param("zoom".into(), 1.5),
+        Command::Speed(2.0),
+        Command::Time(10.0),
+    ] {
+        command.apply(&mut app, &[]).unwrap();
+    }
+
+    assert_eq!(app.pipeline.param("zoom"), Some(1.5));
+    assert_eq!(app.pipeline.common.speed(), 2.0);
+    assert_eq!(app.pipeline.common.time, 10.0);
+
+    assert!(Command::Param("missing".into(), 1.0)
+        .apply(&mut app, &[])
+        .is_err());
+    // Without a playlist there is nothing to switch
+    assert!(Command::Preset(Switch::Next).apply(&mut app, &[]).is_err());
+}
@@ -0,0 +1,184 @@
+use shader_art_rs::layer::ArtLayer;
+use shader_art_rs::pipeline::Scene;
+use shader_art_rs::playlist::{Playlist, Transition};
+use shader_art_rs::presets;
+use std::time::Duration;
+
+const SIZE: u32 = 64;
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+const TIME: f32 = 1.5;
+
+fn scenes() -> Vec<Scene> {
+    ["plasma", "voronoi", "warp"]
+        .iter()
+        .map(|name| {
+            let preset = presets::find(name).unwrap();
+            Scene {
+                passes: vec![preset.source.to_string()],
+                params: preset
+                    .params
+                    .iter()
+                    .map(|param| (param.name.to_string(), param.default))
+                    .collect(),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+fn device() -> (wgpu::Device, wgpu::Queue) {
+    pollster::block_on(async {
+        let instance = wgpu::Instance::default();
+        let adapter = instance.request_adapter(&Default::default()).await.unwrap();
+        adapter
+            .request_device(&Default::default(), None)
+            .await
+            .unwrap()
+    })
+}
+
+// Pixels of what `encode` records into a texture of `SIZE` x `SIZE`
+fn read(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    encode: impl FnOnce(&mut wgpu::CommandEncoder, &wgpu::TextureView),
+) -> Vec<u8> {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (SIZE * SIZE * 4) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&Default::default());
+    encode(&mut encoder, &texture.create_view(&Default::default()));
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(SIZE * 4),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+    queue.submit([encoder.finish()]);
+
+    buffer.slice(..).map_async(wgpu::MapMode::Read, |_| {});
+    device.poll(wgpu::Maintain::Wait);
+    let data = buffer.slice(..).get_mapped_range().to_vec();
+    data
+}
+
+fn entry(device: &wgpu::Device, queue: &wgpu::Queue, scene: &Scene) -> Vec<u8> {
+    let mut layer = ArtLayer::new(device, queue, FORMAT, [SIZE, SIZE], scene).unwrap();
+    layer.set_time(queue, TIME);
+    read(device, queue, |encoder, view| {
+        layer.encode(device, encoder, view)
+    })
+}
+
+fn shown(device: &wgpu::Device, queue: &wgpu::Queue, playlist: &mut Playlist) -> Vec<u8> {
+    playlist.update(queue, TIME);
+    read(device, queue, |encoder, view| {
+        playlist.encode(device, encoder, view)
+    })
+}
+
+// The playlist renders through half floats, a rounding step apart at most
+fn assert_close(a: &[u8], b: &[u8]) {
+    let difference = a.iter().zip(b).map(|(a, b)| a.abs_diff(*b)).max();
+    assert!(
+        difference <= Some(1),
+        "the frames differ by {:?}",
+        difference
+    );
+}
+
+fn playlist(device: &wgpu::Device, queue: &wgpu::Queue, crossfade: Duration) -> Playlist {
+    let transition = Transition {
+        interval: None,
+        crossfade,
+    };
+    Playlist::new(device, queue, FORMAT, [SIZE, SIZE], &scenes(), transition).unwrap()
+}
+
+#[test]
+fn entries_are_switched_in_both_directions() {
+    let (device, queue) = device();
+    let scenes = scenes();
+    let mut playlist = playlist(&device, &queue, Duration::ZERO);
+
+    assert_close(
+        &shown(&device, &queue, &mut playlist),
+        &entry(&device, &queue, &scenes[0]),
+    );
+
+    playlist.next();
+    assert_eq!(playlist.current(), 1);
+    assert_close(
+        &shown(&device, &queue, &mut playlist),
+        &entry(&device, &queue, &scenes[1]),
+    );
+
+    playlist.previous();
+    playlist.previous();
+    assert_eq!(playlist.current(), 2);
+    assert_close(
+        &shown(&device, &queue, &mut playlist),
+        &entry(&device, &queue, &scenes[2]),
+    );
+}
+
+#[test]
+fn the_crossfade_starts_from_the_outgoing_entry() {
+    let (device, queue) = device();
+    let scenes = scenes();
+    let mut playlist = playlist(&device, &queue, Duration::from_secs(3600));
+
+    playlist.next();
+    assert_eq!(playlist.current(), 1);
+    assert_close(
+        &shown(&device, &queue, &mut playlist),
+        &entry(&device, &queue, &scenes[0]),
+    );
+
+    playlist.jump_to(2);
+    assert_close(
+        &shown(&device, &queue, &mut playlist),
+        &entry(&device, &queue, &scenes[2]),
+    );
+}
+
+#[test]
+fn the_state_restores_the_entry_the_crossfade_and_the_params() {
+    let (device, queue) = device();
+    let mut original = playlist(&device, &queue, Duration::from_secs(3600));
+    assert!(original.set_param(&queue, "contrast", 3.0));
+    original.next();
+    let before = shown(&device, &queue, &mut original);
+
+    // Rebuilt like after a device loss
+    let mut rebuilt = playlist(&device, &queue, Duration::from_secs(3600));
+    assert_ne!(shown(&device, &queue, &mut rebuilt), before);
+
+    rebuilt.restore(&queue, original.state());
+    assert_eq!(rebuilt.current(), 1);
+    assert_close(&shown(&device, &queue, &mut rebuilt), &before);
+}
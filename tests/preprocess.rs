@@ -0,0 +1,150 @@
+use shader_art_rs::pipeline::Scene;
+use shader_art_rs::preprocess::preprocess;
+use shader_art_rs::watch::Watcher;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// Writes `files` into a directory of its own under the test temporary directory
+fn files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("preprocess")
+        .join(test);
+    for (name, content) in files {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+fn run(path: &Path, defines: &[(&str, &str)]) -> String {
+    let defines = defines
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect::<BTreeMap<_, _>>();
+    let source = std::fs::read_to_string(path).unwrap();
+    preprocess(&source, "main.wgsl", Some(path), &defines)
+        .unwrap()
+        .0
+}
+
+#[test]
+fn includes_are_relative_to_the_including_file_and_included_once() {
+    let dir = files(
+        "includes",
+        &[
+            (
+                "main.wgsl",
+                "#include \"lib/a.wgsl\"\n#include \"lib/b.wgsl\"\nmain\n",
+            ),
+            ("lib/a.wgsl", "#include \"b.wgsl\"\na\n"),
+            ("lib/b.wgsl", "b\n"),
+        ],
+    );
+
+    assert_eq!(run(&dir.join("main.wgsl"), &[]), "b\na\nmain\n");
+}
+
+#[test]
+fn conditional_blocks_follow_the_defines() {
+    let dir = files(
+        "conditionals",
+        &[(
+            "main.wgsl",
+            "#ifdef A\na\n#ifndef B\nnot b\n#endif\n#else\nnot a\n#endif\n",
+        )],
+    );
+    let path = dir.join("main.wgsl");
+
+    assert_eq!(run(&path, &[]), "not a\n");
+    assert_eq!(run(&path, &[("A", "")]), "a\nnot b\n");
+    assert_eq!(run(&path, &[("A", ""), ("B", "")]), "a\n");
+}
+
+#[test]
+fn defines_replace_whole_words() {
+    let dir = files(
+        "defines",
+        &[(
+            "main.wgsl",
+            "#define STEPS 64\n#define MAX STEPS\nlet a = STEPS + MAX + STEPS_2 + 1e5;\n#undef STEPS\nSTEPS\n",
+        )],
+    );
+
+    assert_eq!(
+        run(&dir.join("main.wgsl"), &[]),
+        "let a = 64 + 64 + STEPS_2 + 1e5;\nSTEPS\n"
+    );
+}
+
+#[test]
+fn errors_point_to_the_included_file() {
+    let dir = files(
+        "errors",
+        &[
+            (
+                "main.wgsl",
+                "#include \"std/palette.wgsl\"\n#include \"helper.wgsl\"\n\n@fragment\nfn fs_main(in: VertexOutput) -> @location(0) vec4f {\n    return vec4f(helper(), 1.0);\n}\n",
+            ),
+            ("helper.wgsl", "// Comment\nfn helper() -> vec3f {\n    return undefined;\n}\n"),
+        ],
+    );
+    let path = dir.join("main.wgsl");
+    let source = std::fs::read_to_string(&path).unwrap();
+    let (pass, source_map) =
+        preprocess(&source, "main.wgsl", Some(&path), &BTreeMap::new()).unwrap();
+    assert_eq!(source_map.files(), [path, dir.join("helper.wgsl")]);
+
+    let scene = Scene {
+        passes: vec![pass],
+        source_maps: vec![source_map],
+        ..Default::default()
+    };
+    let error = scene.validate().unwrap_err().to_string();
+    assert!(error.contains("helper.wgsl:3:12"), "{}", error);
+}
+
+#[test]
+fn unbalanced_conditionals_are_errors() {
+    for source in ["#ifdef A\n", "#endif\n", "#ifdef A\n#else\n#else\n#endif\n"] {
+        assert!(preprocess(source, "main.wgsl", None, &BTreeMap::new()).is_err());
+    }
+}
+
+#[test]
+fn the_watcher_sees_changes_of_included_files() {
+    let dir = files(
+        "watch",
+        &[
+            ("main.wgsl", "#include \"helper.wgsl\"\nmain\n"),
+            ("helper.wgsl", "helper\n"),
+        ],
+    );
+    let path = dir.join("main.wgsl");
+    let source = std::fs::read_to_string(&path).unwrap();
+    let (pass, source_map) =
+        preprocess(&source, "main.wgsl", Some(&path), &BTreeMap::new()).unwrap();
+    let scene = Scene {
+        passes: vec![pass],
+        source_maps: vec![source_map],
+        ..Default::default()
+    };
+
+    let mut watcher = Watcher::new(&[&scene], Duration::ZERO);
+    assert!(!watcher.changed());
+
+    // The modification time is set, a write may fall in the same tick
+    let helper = std::fs::File::options()
+        .write(true)
+        .open(dir.join("helper.wgsl"))
+        .unwrap();
+    helper
+        .set_modified(SystemTime::now() + Duration::from_secs(60))
+        .unwrap();
+    assert!(watcher.changed());
+    assert!(!watcher.changed());
+
+    std::fs::remove_file(dir.join("helper.wgsl")).unwrap();
+    assert!(watcher.changed());
+}
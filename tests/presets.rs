@@ -0,0 +1,86 @@
+mod common;
+
+use common::{assert_golden, render, software};
+use shader_art_rs::pipeline::{Scene, MAX_PARAMS};
+use shader_art_rs::presets::{self, PRESETS};
+use shader_art_rs::project::Project;
+
+const TESTED: &[&str] = &[
+    "fractal-rings",
+    "plasma",
+    "voronoi",
+    "spheres",
+    "warp",
+    "kaleidoscope",
+];
+
+fn golden(name: &str) {
+    let preset = presets::find(name).unwrap();
+    let scene = Scene {
+        passes: vec![preset.source.to_string()],
+        params: preset
+            .params
+            .iter()
+            .map(|param| (param.name.to_string(), param.default))
+            .collect(),
+        ..Default::default()
+    };
+
+    let image = render(&scene, [64, 64], 1.0, &software());
+    assert_golden(&format!("preset_{}", name), &image);
+}
+
+#[test]
+fn every_preset_is_tested() {
+    for preset in PRESETS {
+        assert!(
+            TESTED.contains(&preset.name),
+            "`{}` has no test",
+            preset.name
+        );
+    }
+}
+
+#[test]
+fn fractal_rings() {
+    golden("fractal-rings");
+}
+
+#[test]
+fn plasma() {
+    golden("plasma");
+}
+
+#[test]
+fn voronoi() {
+    golden("voronoi");
+}
+
+#[test]
+fn spheres() {
+    golden("spheres");
+}
+
+#[test]
+fn warp() {
+    golden("warp");
+}
+
+#[test]
+fn kaleidoscope() {
+    golden("kaleidoscope");
+}
+
+#[test]
+fn the_preset_params_count_in_the_limit() {
+    let preset = presets::find("plasma").unwrap();
+    let project = |extra: usize| Project {
+        preset: Some(preset.name.into()),
+        params: (0..extra).map(|i| (format!("extra{}", i), 1.0)).collect(),
+        ..Default::default()
+    };
+
+    let fitting = MAX_PARAMS - preset.params.len();
+    assert_eq!(project(fitting).scene().unwrap().params.len(), MAX_PARAMS);
+    assert!(project(fitting + 1).scene().is_err());
+}
@@ -0,0 +1,186 @@
+use shader_art_rs::cli;
+use shader_art_rs::gpu::{Backend, Power};
+use shader_art_rs::grade::ToneMapping;
+use shader_art_rs::project::{OutputFormat, Precision, Project};
+use std::path::{Path, PathBuf};
+
+fn dir(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("project")
+        .join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn project(args: &[&str]) -> Project {
+    let matches = cli::cli()
+        .try_get_matches_from(["shader-art"].iter().chain(args))
+        .unwrap();
+    let (_, matches) = cli::mode(&matches).unwrap();
+    cli::project(matches).unwrap()
+}
+
+const PROJECT: &str = r#"
+shaders = ["art.wgsl"]
+inputs = ["images/input.png"]
+outputs = ["out/art.gif"]
+resolution = "320x240"
+fps = 30
+speed = 4
+
+[params]
+zoom = 2.0
+
+[grade]
+lut = "/absolute/look.cube"
+"#;
+
+#[test]
+fn relative_paths_are_resolved_against_the_file() {
+    let dir = dir("relative");
+    let path = dir.join("shader-art.toml");
+    std::fs::write(&path, PROJECT).unwrap();
+
+    let project = Project::load(&path).unwrap();
+    assert_eq!(project.shaders, [dir.join("art.wgsl")]);
+    assert_eq!(project.inputs, [dir.join("images/input.png")]);
+    assert_eq!(project.outputs, [dir.join("out/art.gif")]);
+    assert_eq!(
+        project.grade.lut,
+        Some(PathBuf::from("/absolute/look.cube"))
+    );
+    assert_eq!(project.resolution, "320x240");
+    assert_eq!(project.params["zoom"], 2.0);
+    // Missing values keep their defaults
+    assert_eq!(project.duration, Project::default().duration);
+
+    std::fs::write(&path, "unknown = 1").unwrap();
+    assert!(Project::load(&path).is_err());
+}
+
+#[test]
+fn flags_override_the_file() {
+    let dir = dir("overrides");
+    let path = dir.join("shader-art.toml");
+    std::fs::write(&path, PROJECT).unwrap();
+    let path = path.to_str().unwrap();
+
+    let project = project(&[
+        "--project",
+        path,
+        "--param",
+        "zoom=3",
+        "--speed",
+        "2",
+        "export",
+        "other.gif",
+        "--fps",
+        "60",
+    ]);
+    assert_eq!(project.params["zoom"], 3.0);
+    assert_eq!(project.speed, Some(2.0));
+    assert_eq!(project.fps, 60);
+    assert_eq!(project.outputs, [PathBuf::from("other.gif")]);
+    // Not overridden
+    assert_eq!(project.resolution, "320x240");
+    assert_eq!(project.shaders, [dir.join("art.wgsl")]);
+}
+
+#[test]
+fn the_dumped_config_loads_back() {
+    let dir = dir("dump");
+    let project = project(&[
+        "--preset",
+        "plasma",
+        "--param",
+        "scale=2.5",
+        "--define",
+        "FAST",
+        "--effect",
+        "vignette:strength=0.5",
+        "--tone-mapping",
+        "aces",
+        "export",
+        dir.join("art.exr").to_str().unwrap(),
+        "--resolution",
+        "1080p",
+        "--motion-blur",
+        "4",
+    ]);
+
+    let path = dir.join("shader-art.toml");
+    std::fs::write(&path, project.to_toml().unwrap()).unwrap();
+    assert_eq!(Project::load(&path).unwrap(), project);
+}
+
+#[test]
+fn flat_flags_before_a_subcommand_are_rejected() {
+    let matches = cli::cli()
+        .try_get_matches_from(["shader-art", "--fps", "60", "export", "art.gif"])
+        .unwrap();
+    let error = cli::mode(&matches).unwrap_err().to_string();
+    assert!(error.contains("--fps"), "{}", error);
+
+    assert_eq!(project(&["--fps", "60", "--save", "art.gif"]).fps, 60);
+    assert_eq!(
+        project(&["still", "art.png", "--format", "exr"]).format,
+        Some(OutputFormat::Exr)
+    );
+    assert!(cli::cli()
+        .try_get_matches_from(["shader-art", "export", "art.gif", "--format", "png"])
+        .is_err());
+}
+
+#[test]
+fn named_values_are_parsed_as_enums() {
+    assert_eq!(
+        project(&["export", "art.exr", "--precision", "half"]).precision,
+        Precision::Half
+    );
+    assert!(cli::cli()
+        .try_get_matches_from(["shader-art", "export", "art.exr", "--precision", "double"])
+        .is_err());
+
+    assert_eq!(
+        project(&["--tone-mapping", "agx"]).grade.tone_mapping,
+        ToneMapping::Agx
+    );
+    assert_eq!(
+        project(&["--tone-mapping", "none"]).grade.tone_mapping,
+        ToneMapping::None
+    );
+    assert!(cli::cli()
+        .try_get_matches_from(["shader-art", "--tone-mapping", "filmic"])
+        .is_err());
+
+    let gpu = project(&["--backend", "dx12", "--power", "low"]).gpu;
+    assert_eq!(gpu.backend, Some(Backend::Dx12));
+    assert_eq!(gpu.power, Some(Power::Low));
+    assert!(cli::cli()
+        .try_get_matches_from(["shader-art", "--backend", "webgpu"])
+        .is_err());
+    // A named adapter has no preference to apply
+    assert!(cli::cli()
+        .try_get_matches_from(["shader-art", "--adapter", "0", "--power", "high"])
+        .is_err());
+}
+
+#[test]
+fn the_duration_covers_the_same_time_at_any_frame_rate() {
+    let end = |fps: &str| {
+        let project = project(&["export", "art.gif", "--duration", "2", "--fps", fps]);
+        project.frame_time(project.frames() - 1)
+    };
+
+    assert!((end("10") - end("40")).abs() < 1e-5);
+    assert!(end("10") > Project::default().time);
+}
+
+#[test]
+fn parameter_names_differing_by_case_are_rejected() {
+    let project = project(&["--param", "zoom=1", "--param", "Zoom=2"]);
+    let Err(error) = project.scene() else {
+        panic!("The parameters were accepted");
+    };
+    assert!(error.to_string().contains("PARAM_ZOOM"), "{}", error);
+}
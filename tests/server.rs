@@ -0,0 +1,211 @@
+use shader_art_rs::project::Project;
+use shader_art_rs::server::Server;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+fn output_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("server")
+}
+
+fn start() -> Server {
+    let mut project = Project {
+        preset: Some("plasma".into()),
+        resolution: "64x64".into(),
+        fps: 30,
+        ..Default::default()
+    };
+    project.gpu.software = true;
+    project.serve.output_dir = Some(output_dir());
+    Server::start(&project, "127.0.0.1:0").unwrap()
+}
+
+// Sends a JSON request and returns the status code and the JSON body
+fn request(address: SocketAddr, method: &str, path: &str, body: &str) -> (u16, serde_json::Value) {
+    send(address, method, path, "application/json", body)
+}
+
+fn send(
+    address: SocketAddr,
+    method: &str,
+    path: &str,
+    content_type: &str,
+    body: &str,
+) -> (u16, serde_json::Value) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        content_type,
+        body.len(),
+        body
+    )
+    .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+    (status, serde_json::from_str(body).unwrap())
+}
+
+#[test]
+fn the_state_is_read_and_changed() {
+    let server = start();
+    let address = server.address().unwrap();
+
+    let (status, state) = request(address, "GET", "/state", "");
+    assert_eq!(status, 200);
+    assert_eq!(state["params"]["scale"], 3.0);
+
+    let (status, state) = request(
+        address,
+        "POST",
+        "/state",
+        r#"{"time": 10, "speed": 2.5, "params": {"scale": 5}}"#,
+    );
+    assert_eq!(status, 200);
+    assert_eq!(state["speed"], 2.5);
+    assert_eq!(state["params"]["scale"], 5.0);
+    assert_eq!(state["params"]["contrast"], 1.5);
+    assert!(state["time"].as_f64().unwrap() >= 10.0);
+
+    for body in [r#"{"params": {"missing": 1}}"#, r#"{"speed": "fast"}"#, "{"] {
+        let (status, error) = request(address, "POST", "/state", body);
+        assert_eq!(status, 400, "{}", body);
+        assert!(error["error"].is_string());
+    }
+
+    assert_eq!(request(address, "GET", "/missing", "").0, 404);
+    assert_eq!(request(address, "POST", "/jobs", "").0, 405);
+
+    // What a cross-origin form or `fetch` sends without a preflight
+    let (status, _) = send(address, "POST", "/state", "text/plain", r#"{"speed": 8}"#);
+    assert_eq!(status, 415);
+    assert_eq!(request(address, "GET", "/state", "").1["speed"], 2.5);
+}
+
+#[test]
+fn jobs_render_stills() {
+    let server = start();
+    let address = server.address().unwrap();
+    let path = output_dir().join("stills/still.png");
+    let _ = std::fs::remove_file(&path);
+
+    let body = r#"{"outputs": ["stills/still.png"], "resolution": "32x16"}"#;
+    let (status, job) = request(address, "POST", "/still", body);
+    assert_eq!(status, 202);
+    assert_eq!(job["kind"], "still");
+    assert_eq!(job["outputs"][0], path.to_str().unwrap());
+
+    let started = Instant::now();
+    let job = loop {
+        let (_, job) = request(address, "GET", &format!("/jobs/{}", job["id"]), "");
+        let pending = job["status"] == "queued" || job["status"] == "running";
+        if !pending || started.elapsed() > TIMEOUT {
+            break job;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    assert_eq!(job["status"], "done", "{}", job);
+    let image = image::open(&path).unwrap();
+    assert_eq!((image.width(), image.height()), (32, 16));
+
+    let (status, _) = request(address, "POST", "/export", r#"{"outputs": []}"#);
+    assert_eq!(status, 400);
+    // Nothing is written outside of the output directory
+    for output in [
+        "/tmp/still.png",
+        "../still.png",
+        "stills/../../still.png",
+        "./still.png",
+    ] {
+        let body = serde_json::json!({ "outputs": [output] }).to_string();
+        assert_eq!(
+            request(address, "POST", "/still", &body).0,
+            400,
+            "{}",
+            output
+        );
+    }
+    assert_eq!(request(address, "GET", "/jobs/7", "").0, 404);
+}
+
+#[test]
+fn jobs_beyond_the_queue_are_refused() {
+    let server = start();
+    let address = server.address().unwrap();
+
+    // Long enough for the requests to arrive while the first one renders
+    let body = r#"{"outputs": ["queue/long.gif"], "resolution": "256x256", "frames": 300}"#;
+    let statuses = (0..8)
+        .map(|_| request(address, "POST", "/export", body).0)
+        .collect::<Vec<_>>();
+
+    // Four wait at least, five when the first one already renders
+    assert!(
+        statuses[..4].iter().all(|status| *status == 202),
+        "{:?}",
+        statuses
+    );
+    assert!(statuses.contains(&503), "{:?}", statuses);
+}
+
+// Reads the header lines until the empty one after them
+fn headers(reader: &mut impl BufRead) -> Vec<String> {
+    let mut lines = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        match line.trim() {
+            "" if lines.is_empty() => {}
+            "" => return lines,
+            line => lines.push(line.to_string()),
+        }
+    }
+}
+
+#[test]
+fn the_stream_serves_jpeg_frames() {
+    let server = start();
+    let mut stream = TcpStream::connect(server.address().unwrap()).unwrap();
+    stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+    write!(
+        stream,
+        "GET /stream.mjpeg HTTP/1.1\r\nHost: localhost\r\n\r\n"
+    )
+    .unwrap();
+    let mut reader = BufReader::new(stream);
+
+    let head = headers(&mut reader);
+    assert!(head[0].contains("200"));
+    assert!(head
+        .iter()
+        .any(|line| line.contains("multipart/x-mixed-replace")));
+
+    let mut frames = Vec::new();
+    while frames.len() < 2 {
+        let part = headers(&mut reader);
+        assert_eq!(part[0], "--frame");
+        let length = part
+            .iter()
+            .find_map(|line| line.strip_prefix("Content-Length: "))
+            .unwrap()
+            .parse::<usize>()
+            .unwrap();
+        let mut jpeg = vec![0; length];
+        reader.read_exact(&mut jpeg).unwrap();
+        frames.push(image::load_from_memory_with_format(&jpeg, image::ImageFormat::Jpeg).unwrap());
+    }
+
+    for frame in &frames {
+        assert_eq!((frame.width(), frame.height()), (64, 64));
+    }
+    // The animation goes on between the frames
+    assert_ne!(frames[0].as_bytes(), frames[1].as_bytes());
+}
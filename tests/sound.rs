@@ -0,0 +1,76 @@
+mod common;
+
+use shader_art_rs::error::ShaderArtError;
+use shader_art_rs::sound::{self, Sound};
+use std::path::PathBuf;
+
+const RATE: u32 = 8000;
+
+fn sound(source: &str) -> Sound {
+    Sound {
+        source: source.to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn samples_follow_the_time() {
+    // More samples than a dispatch renders, in several seconds
+    let count = 70001;
+    let samples = pollster::block_on(sound::render(
+        &sound(
+            "fn mainSound(time: f32) -> vec2f {
+                return vec2f(sin(6.2831853 * 440.0 * time), fract(time));
+            }",
+        ),
+        RATE,
+        count,
+        &common::software().gpu,
+    ))
+    .unwrap();
+
+    assert_eq!(samples.len(), count);
+    for (i, [left, right]) in samples.into_iter().enumerate() {
+        let time = i as f64 / RATE as f64;
+        let sine = (std::f64::consts::TAU * 440.0 * time).sin() as f32;
+        assert!((left - sine).abs() < 1e-2, "{} at sample {}", left, i);
+        assert!(
+            (right - time.fract() as f32).abs() < 1e-4,
+            "{} at sample {}",
+            right,
+            i
+        );
+    }
+}
+
+#[test]
+fn wav_files_are_stereo_and_clipped() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("sound");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("clipped.wav");
+
+    sound::save_wav(&path, &[[0.5, -0.25], [2.0, -2.0], [f32::NAN, 0.0]], RATE).unwrap();
+
+    let mut reader = hound::WavReader::open(&path).unwrap();
+    let spec = reader.spec();
+    assert_eq!((spec.channels, spec.sample_rate), (2, RATE));
+    let samples = reader
+        .samples::<i16>()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(samples, [16384, -8192, i16::MAX, -i16::MAX, 0, 0]);
+}
+
+#[test]
+fn sound_shaders_need_main_sound() {
+    let error = sound("fn other(time: f32) -> vec2f { return vec2f(time); }")
+        .validate()
+        .unwrap_err();
+    assert!(matches!(error, ShaderArtError::Shader(_)));
+    assert!(error.to_string().contains("mainSound"));
+
+    let error = sound("fn mainSound(time: f32) -> vec2f {\n    return vec3f(time);\n}")
+        .validate()
+        .unwrap_err();
+    assert!(error.to_string().contains("line 2:"), "{}", error);
+}
@@ -0,0 +1,587 @@
+// The mirrors of the library keep the constants of its WGSL sources
+#![allow(clippy::excessive_precision, clippy::approx_constant)]
+
+mod common;
+
+use common::software;
+use shader_art_rs::app::RenderOptions;
+use shader_art_rs::offscreen::OffscreenRenderer;
+use shader_art_rs::pipeline::Scene;
+use shader_art_rs::preprocess::preprocess;
+use std::collections::BTreeMap;
+
+const SIZE: u32 = 32;
+
+/// Evaluates `expression`, a `vec4f` of `p`, on the GPU and compares it with
+/// `reference` for every pixel. `p` goes from -7.75 to 7.75 in steps of 0.5,
+/// away from the integers where the lattice functions jump.
+///
+/// Float targets are not renderable everywhere, so the bits of each component
+/// are written to a pixel of its own of an 8-bit target.
+fn compare(include: &str, expression: &str, tolerance: f32, reference: impl Fn(V2) -> [f32; 4]) {
+    let source = format!(
+        "#include \"std/{include}.wgsl\"\n\
+         @fragment\n\
+         fn fs_main(in: VertexOutput) -> @location(0) vec4f {{\n\
+             let p = (vec2f(floor(in.foo.x / 4.0), floor(in.foo.y)) + 0.5 - {half}.0) * 0.5;\n\
+             let bits = bitcast<u32>(({expression})[u32(in.foo.x) % 4u]);\n\
+             return vec4f((vec4u(bits) >> vec4u(0u, 8u, 16u, 24u)) & vec4u(255u)) / 255.0;\n\
+         }}\n",
+        half = SIZE / 2,
+    );
+    let (pass, source_map) = preprocess(&source, "test", None, &BTreeMap::new()).unwrap();
+    let scene = Scene {
+        passes: vec![pass],
+        source_maps: vec![source_map],
+        ..Default::default()
+    };
+    let options = RenderOptions {
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        ..software()
+    };
+
+    let frame = pollster::block_on(OffscreenRenderer::new(
+        1.0,
+        [SIZE * 4, SIZE],
+        &scene,
+        &options,
+    ))
+    .unwrap()
+    .render_frame_at_blocking(0.0)
+    .unwrap();
+    let values = frame
+        .data
+        .chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+        .collect::<Vec<_>>();
+
+    for (i, gpu) in values.chunks_exact(4).enumerate() {
+        let pixel = [(i as u32 % SIZE) as f32, (i as u32 / SIZE) as f32];
+        let p = pixel.map(|v| (v + 0.5 - (SIZE / 2) as f32) * 0.5);
+        let cpu = reference(p);
+        for (gpu, cpu) in gpu.iter().zip(cpu) {
+            assert!(
+                (gpu - cpu).abs() <= tolerance,
+                "`{}` at {:?}: {:?} on the GPU, {:?} on the CPU",
+                expression,
+                p,
+                gpu,
+                cpu
+            );
+        }
+    }
+}
+
+type V2 = [f32; 2];
+type V3 = [f32; 3];
+
+// Rotates a vector given the cosine and the sine of the angle
+type Rotation = fn(V3, f32, f32) -> V3;
+
+fn v4(v: V3, w: f32) -> [f32; 4] {
+    [v[0], v[1], v[2], w]
+}
+
+fn add<const N: usize>(a: [f32; N], b: [f32; N]) -> [f32; N] {
+    std::array::from_fn(|i| a[i] + b[i])
+}
+
+fn sub<const N: usize>(a: [f32; N], b: [f32; N]) -> [f32; N] {
+    std::array::from_fn(|i| a[i] - b[i])
+}
+
+fn scale<const N: usize>(a: [f32; N], s: f32) -> [f32; N] {
+    a.map(|v| v * s)
+}
+
+fn dot<const N: usize>(a: [f32; N], b: [f32; N]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+fn length<const N: usize>(a: [f32; N]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+fn mix(a: f32, b: f32, t: f32) -> f32 {
+    a * (1.0 - t) + b * t
+}
+
+fn fract(v: f32) -> f32 {
+    v - v.floor()
+}
+
+// Mirrors of the library
+
+fn pcg(v: u32) -> u32 {
+    let state = v.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+fn hash_unit(h: u32) -> f32 {
+    (h >> 8) as f32 / 16777216.0
+}
+
+fn hash2(c: [i32; 2]) -> f32 {
+    hash_unit(pcg((c[0] as u32).wrapping_add(pcg(c[1] as u32))))
+}
+
+fn hash3(c: [i32; 3]) -> f32 {
+    hash_unit(pcg(
+        (c[0] as u32).wrapping_add(pcg((c[1] as u32).wrapping_add(pcg(c[2] as u32))))
+    ))
+}
+
+fn hash22(c: [i32; 2]) -> V2 {
+    let h = pcg((c[0] as u32).wrapping_add(pcg(c[1] as u32)));
+    [hash_unit(h), hash_unit(pcg(h))]
+}
+
+fn hash33(c: [i32; 3]) -> V3 {
+    let h = pcg((c[0] as u32).wrapping_add(pcg((c[1] as u32).wrapping_add(pcg(c[2] as u32)))));
+    let h2 = pcg(h);
+    [hash_unit(h), hash_unit(h2), hash_unit(pcg(h2))]
+}
+
+fn cell(p: V2) -> [i32; 2] {
+    p.map(|v| v.floor() as i32)
+}
+
+fn noise_fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn noise_gradient(c: [i32; 2]) -> V2 {
+    let angle = hash2(c) * 6.2831853;
+    [angle.cos(), angle.sin()]
+}
+
+fn value_noise(p: V2) -> f32 {
+    let [x, y] = cell(p);
+    let u = p.map(|v| noise_fade(fract(v)));
+    let a = hash2([x, y]);
+    let b = hash2([x + 1, y]);
+    let d = hash2([x, y + 1]);
+    let e = hash2([x + 1, y + 1]);
+    mix(mix(a, b, u[0]), mix(d, e, u[0]), u[1])
+}
+
+fn perlin_noise(p: V2) -> f32 {
+    let [x, y] = cell(p);
+    let f = p.map(fract);
+    let u = f.map(noise_fade);
+    let a = dot(noise_gradient([x, y]), f);
+    let b = dot(noise_gradient([x + 1, y]), sub(f, [1.0, 0.0]));
+    let d = dot(noise_gradient([x, y + 1]), sub(f, [0.0, 1.0]));
+    let e = dot(noise_gradient([x + 1, y + 1]), sub(f, [1.0, 1.0]));
+    mix(mix(a, b, u[0]), mix(d, e, u[0]), u[1]) * 1.4142135
+}
+
+fn simplex_noise(p: V2) -> f32 {
+    let skew = 0.36602540;
+    let unskew = 0.21132487;
+
+    let s = p.map(|v| (v + (p[0] + p[1]) * skew).floor());
+    let c = s.map(|v| v as i32);
+    let x0 = add(sub(p, s), [(s[0] + s[1]) * unskew; 2]);
+    let o = if x0[0] > x0[1] { [1, 0] } else { [0, 1] };
+    let x1 = add(sub(x0, o.map(|v| v as f32)), [unskew; 2]);
+    let x2 = add(sub(x0, [1.0; 2]), [2.0 * unskew; 2]);
+
+    let corners = [x0, x1, x2];
+    let cells = [c, [c[0] + o[0], c[1] + o[1]], [c[0] + 1, c[1] + 1]];
+    let mut n = 0.0;
+    for (corner, cell) in corners.into_iter().zip(cells) {
+        let t = 0.5 - dot(corner, corner);
+        if t > 0.0 {
+            n += t * t * t * t * dot(noise_gradient(cell), corner);
+        }
+    }
+    70.0 * n
+}
+
+fn fbm(p: V2, octaves: i32) -> f32 {
+    let (mut sum, mut total, mut amplitude, mut q) = (0.0, 0.0, 0.5, p);
+    for _ in 0..octaves {
+        sum += amplitude * value_noise(q);
+        total += amplitude;
+        amplitude *= 0.5;
+        q = add(scale(q, 2.0), [17.0, 31.0]);
+    }
+    sum / total
+}
+
+fn voronoi(p: V2) -> V3 {
+    let c = cell(p);
+    let (mut f1, mut f2, mut id) = (8.0f32, 8.0f32, 0.0);
+    for y in -1..=1 {
+        for x in -1..=1 {
+            let cell = [c[0] + x, c[1] + y];
+            let point = add(cell.map(|v| v as f32), hash22(cell));
+            let d = length(sub(point, p));
+            if d < f1 {
+                f2 = f1;
+                f1 = d;
+                id = hash3([cell[0], cell[1], 1]);
+            } else if d < f2 {
+                f2 = d;
+            }
+        }
+    }
+    [f1, f2, id]
+}
+
+fn palette(t: f32, a: V3, b: V3, c: V3, d: V3) -> V3 {
+    std::array::from_fn(|i| a[i] + b[i] * (6.2831853 * (c[i] * t + d[i])).cos())
+}
+
+fn sd_box2(p: V2, size: V2) -> f32 {
+    let d = sub(p.map(f32::abs), size);
+    length(d.map(|v| v.max(0.0))) + d[0].max(d[1]).min(0.0)
+}
+
+fn sd_segment(p: V2, a: V2, b: V2) -> f32 {
+    let pa = sub(p, a);
+    let ba = sub(b, a);
+    let h = (dot(pa, ba) / dot(ba, ba)).clamp(0.0, 1.0);
+    length(sub(pa, scale(ba, h)))
+}
+
+fn sd_box(p: V3, size: V3) -> f32 {
+    let d = sub(p.map(f32::abs), size);
+    length(d.map(|v| v.max(0.0))) + d[0].max(d[1].max(d[2])).min(0.0)
+}
+
+fn sd_torus(p: V3, radii: V2) -> f32 {
+    let q = [length([p[0], p[2]]) - radii[0], p[1]];
+    length(q) - radii[1]
+}
+
+fn sd_capsule(p: V3, a: V3, b: V3, radius: f32) -> f32 {
+    let pa = sub(p, a);
+    let ba = sub(b, a);
+    let h = (dot(pa, ba) / dot(ba, ba)).clamp(0.0, 1.0);
+    length(sub(pa, scale(ba, h))) - radius
+}
+
+fn op_smooth_union(a: f32, b: f32, k: f32) -> f32 {
+    let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+    mix(b, a, h) - k * h * (1.0 - h)
+}
+
+fn op_repeat<const N: usize>(p: [f32; N], period: [f32; N]) -> [f32; N] {
+    std::array::from_fn(|i| p[i] - period[i] * (p[i] / period[i] + 0.5).floor())
+}
+
+fn hsv_to_rgb(c: V3) -> V3 {
+    [1.0, 2.0 / 3.0, 1.0 / 3.0].map(|k| {
+        let p = (fract(c[0] + k) * 6.0 - 3.0).abs();
+        c[2] * mix(1.0, (p - 1.0).clamp(0.0, 1.0), c[1])
+    })
+}
+
+fn rgb_to_hsv(c: V3) -> V3 {
+    let high = c[0].max(c[1].max(c[2]));
+    let d = high - c[0].min(c[1].min(c[2]));
+    let mut h = 0.0;
+    if d > 0.0 {
+        h = if high == c[0] {
+            (c[1] - c[2]) / d
+        } else if high == c[1] {
+            (c[2] - c[0]) / d + 2.0
+        } else {
+            (c[0] - c[1]) / d + 4.0
+        };
+        h = fract(h / 6.0);
+    }
+    [h, if high > 0.0 { d / high } else { 0.0 }, high]
+}
+
+fn linear_to_oklab(c: V3) -> V3 {
+    let l = [
+        [0.4122214708, 0.5363325363, 0.0514459929],
+        [0.2119034982, 0.6806995451, 0.1073969566],
+        [0.0883024619, 0.2817188376, 0.6299787005],
+    ]
+    .map(|row| dot(row, c).cbrt());
+    [
+        [0.2104542553, 0.7936177850, -0.0040720468],
+        [1.9779984951, -2.4285922050, 0.4505937099],
+        [0.0259040861, 0.7827717670, -0.8086757571],
+    ]
+    .map(|row| dot(row, l))
+}
+
+fn oklab_to_linear(c: V3) -> V3 {
+    let lms = [
+        [1.0, 0.3963377774, 0.2158037573],
+        [1.0, -0.1055613458, -0.0638541728],
+        [1.0, -0.0894841775, -1.2914855480],
+    ]
+    .map(|row| dot(row, c).powi(3));
+    [
+        [4.0767416621, -3.3077115913, 0.2309699292],
+        [-1.2684380046, 2.6097574011, -0.3413193965],
+        [-0.0041960863, -0.7034186147, 1.7076147010],
+    ]
+    .map(|row| dot(row, lms))
+}
+
+fn srgb_to_linear(c: V3) -> V3 {
+    c.map(|c| {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    })
+}
+
+fn linear_to_srgb(c: V3) -> V3 {
+    c.map(|c| {
+        if c <= 0.0031308 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        }
+    })
+}
+
+// A color in [0, 1] of the position
+fn color(p: V2) -> V3 {
+    [
+        fract(p[0] * 0.37),
+        fract(p[1] * 0.61),
+        fract((p[0] + p[1]) * 0.23),
+    ]
+}
+
+const COLOR: &str = "fract(vec3f(p.x * 0.37, p.y * 0.61, (p.x + p.y) * 0.23))";
+
+#[test]
+fn hashes() {
+    compare("hash", "vec4f(hash1(i32(p.x * 4.0)), hash2(vec2i(floor(p))), hash3(vec3i(vec2i(floor(p)), -3)), 0.0)", 0.0, |p| {
+        let c = cell(p);
+        let h1 = hash_unit(pcg((p[0] * 4.0) as i32 as u32));
+        [h1, hash2(c), hash3([c[0], c[1], -3]), 0.0]
+    });
+    compare(
+        "hash",
+        "vec4f(hash22(vec2i(floor(p))), hash33(vec3i(vec2i(floor(p)), 5)).yz)",
+        0.0,
+        |p| {
+            let c = cell(p);
+            let h = hash33([c[0], c[1], 5]);
+            let [x, y] = hash22(c);
+            [x, y, h[1], h[2]]
+        },
+    );
+}
+
+#[test]
+fn value_and_perlin_noise() {
+    compare(
+        "noise",
+        "vec4f(value_noise(p * 0.7), perlin_noise(p * 0.7), 0.0, 0.0)",
+        1e-4,
+        |p| {
+            let q = scale(p, 0.7);
+            [value_noise(q), perlin_noise(q), 0.0, 0.0]
+        },
+    );
+}
+
+#[test]
+fn simplex_noise_and_fbm() {
+    compare(
+        "noise",
+        "vec4f(simplex_noise(p * 0.7), fbm(p * 0.3, 5), 0.0, 0.0)",
+        1e-4,
+        |p| {
+            [
+                simplex_noise(scale(p, 0.7)),
+                fbm(scale(p, 0.3), 5),
+                0.0,
+                0.0,
+            ]
+        },
+    );
+}
+
+#[test]
+fn voronoi_distances_and_cells() {
+    compare("voronoi", "vec4f(voronoi(p * 0.6), 0.0)", 1e-4, |p| {
+        let q = scale(p, 0.6);
+        let [f1, f2, id] = voronoi(q);
+        [f1, f2, id, 0.0]
+    });
+}
+
+#[test]
+fn palettes() {
+    compare(
+        "palette",
+        "vec4f(palette(p.x * 0.1, vec3f(0.2), vec3f(0.7), vec3f(1.5), vec3f(p.y)), 0.0)",
+        1e-5,
+        |p| {
+            v4(
+                palette(p[0] * 0.1, [0.2; 3], [0.7; 3], [1.5; 3], [p[1]; 3]),
+                0.0,
+            )
+        },
+    );
+
+    let presets: [(&str, V3, V3); 5] = [
+        ("rings", [1.0; 3], [0.263, 0.416, 0.557]),
+        ("rainbow", [1.0; 3], [0.0, 0.33, 0.67]),
+        ("sunset", [1.0, 1.0, 0.5], [0.8, 0.9, 0.3]),
+        ("ice", [1.0, 0.7, 0.4], [0.0, 0.15, 0.2]),
+        ("fire", [2.0, 1.0, 0.0], [0.5, 0.2, 0.25]),
+    ];
+    for (name, c, d) in presets {
+        compare(
+            "palette",
+            &format!("vec4f(palette_{}(p.x * 0.1 + p.y), 0.0)", name),
+            1e-5,
+            |p| v4(palette(p[0] * 0.1 + p[1], [0.5; 3], [0.5; 3], c, d), 0.0),
+        );
+    }
+}
+
+#[test]
+fn sdf_2d() {
+    compare(
+        "sdf",
+        "vec4f(sd_circle(p, 3.0), sd_box2(p, vec2f(2.0, 3.0)), sd_segment(p, vec2f(-1.0, 2.0), vec2f(3.0, -2.0)), 0.0)",
+        1e-5,
+        |p| [length(p) - 3.0, sd_box2(p, [2.0, 3.0]), sd_segment(p, [-1.0, 2.0], [3.0, -2.0]), 0.0],
+    );
+}
+
+#[test]
+fn sdf_3d() {
+    compare(
+        "sdf",
+        "vec4f(sd_sphere(vec3f(p, 1.0), 3.0), sd_box(vec3f(p, 0.5), vec3f(2.0, 3.0, 1.0)), sd_torus(vec3f(p.x, 0.5, p.y), vec2f(4.0, 1.0)), sd_capsule(vec3f(p, 1.0), vec3f(-2.0), vec3f(2.0, 1.0, 0.0), 0.5))",
+        1e-5,
+        |p| {
+            [
+                length([p[0], p[1], 1.0]) - 3.0,
+                sd_box([p[0], p[1], 0.5], [2.0, 3.0, 1.0]),
+                sd_torus([p[0], 0.5, p[1]], [4.0, 1.0]),
+                sd_capsule([p[0], p[1], 1.0], [-2.0; 3], [2.0, 1.0, 0.0], 0.5),
+            ]
+        },
+    );
+    compare(
+        "sdf",
+        "vec4f(sd_plane(vec3f(p, 1.0), vec3f(0.6, 0.0, 0.8), 0.5), 0.0, 0.0, 0.0)",
+        1e-5,
+        |p| [dot([p[0], p[1], 1.0], [0.6, 0.0, 0.8]) + 0.5, 0.0, 0.0, 0.0],
+    );
+}
+
+#[test]
+fn sdf_operators() {
+    compare(
+        "sdf",
+        "vec4f(op_union(p.x, p.y), op_intersection(p.x, p.y), op_subtraction(p.x, p.y), op_smooth_union(p.x, p.y, 1.5))",
+        1e-5,
+        |[x, y]| [x.min(y), x.max(y), x.max(-y), op_smooth_union(x, y, 1.5)],
+    );
+    compare(
+        "sdf",
+        "vec4f(op_repeat2(p, vec2f(1.3, 2.9)), op_repeat(vec3f(p, 0.0), vec3f(2.3, 1.7, 1.0)).xy)",
+        1e-5,
+        |p| {
+            let [x, y] = op_repeat(p, [1.3, 2.9]);
+            let [z, w, _] = op_repeat([p[0], p[1], 0.0], [2.3, 1.7, 1.0]);
+            [x, y, z, w]
+        },
+    );
+}
+
+#[test]
+fn rotations() {
+    compare(
+        "transform",
+        "vec4f(rotate2(p.y) * vec2f(p.x, 1.0), 0.0, 0.0)",
+        1e-5,
+        |[x, y]| {
+            let (s, c) = y.sin_cos();
+            [c * x - s, s * x + c, 0.0, 0.0]
+        },
+    );
+
+    let v = [0.3, -1.2, 2.0];
+    let rotations: [(&str, Rotation); 3] = [
+        ("x", |v, c, s| {
+            [v[0], c * v[1] - s * v[2], s * v[1] + c * v[2]]
+        }),
+        ("y", |v, c, s| {
+            [c * v[0] + s * v[2], v[1], -s * v[0] + c * v[2]]
+        }),
+        ("z", |v, c, s| {
+            [c * v[0] - s * v[1], s * v[0] + c * v[1], v[2]]
+        }),
+    ];
+    for (axis, rotate) in rotations {
+        compare(
+            "transform",
+            &format!("vec4f(rotate_{}(p.x) * vec3f(0.3, -1.2, 2.0), 0.0)", axis),
+            1e-5,
+            |p| {
+                let (s, c) = p[0].sin_cos();
+                v4(rotate(v, c, s), 0.0)
+            },
+        );
+    }
+}
+
+#[test]
+fn hsv() {
+    compare(
+        "color",
+        &format!("vec4f(hsv_to_rgb({}), 0.0)", COLOR),
+        1e-5,
+        |p| v4(hsv_to_rgb(color(p)), 0.0),
+    );
+    compare(
+        "color",
+        &format!("vec4f(rgb_to_hsv({}), 0.0)", COLOR),
+        1e-5,
+        |p| v4(rgb_to_hsv(color(p)), 0.0),
+    );
+}
+
+#[test]
+fn oklab() {
+    compare(
+        "color",
+        &format!("vec4f(linear_to_oklab({}), 0.0)", COLOR),
+        1e-4,
+        |p| v4(linear_to_oklab(color(p)), 0.0),
+    );
+    compare(
+        "color",
+        "vec4f(oklab_to_linear(vec3f(0.7, p * 0.02)), 0.0)",
+        1e-4,
+        |p| v4(oklab_to_linear([0.7, p[0] * 0.02, p[1] * 0.02]), 0.0),
+    );
+}
+
+#[test]
+fn srgb() {
+    compare(
+        "color",
+        &format!("vec4f(srgb_to_linear({}), 0.0)", COLOR),
+        1e-4,
+        |p| v4(srgb_to_linear(color(p)), 0.0),
+    );
+    compare(
+        "color",
+        &format!("vec4f(linear_to_srgb({}), 0.0)", COLOR),
+        1e-4,
+        |p| v4(linear_to_srgb(color(p)), 0.0),
+    );
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use common::software;
+use shader_art_rs::app::App;
+use shader_art_rs::effects::Effect;
+use shader_art_rs::project::Project;
+use shader_art_rs::tiled;
+
+// Powers of 2, so the positions in the image are exact in every tile
+const RESOLUTION: [u32; 2] = [128, 64];
+
+fn tiled(project: &Project) -> Vec<u8> {
+    let scene = project.scene().unwrap();
+    let tile = project.tile(RESOLUTION, 8192).unwrap();
+    let apron = project.apron(RESOLUTION);
+
+    pollster::block_on(async {
+        let mut app = App::new_without_window(1.0, tile, &scene, &software())
+            .await
+            .unwrap();
+        app.set_time(project.time);
+        let mut image = Vec::new();
+        tiled::render(&mut app, RESOLUTION, apron, |rows| {
+            image.extend_from_slice(rows);
+            Ok(())
+        })
+        .await
+        .unwrap();
+        image
+    })
+}
+
+#[test]
+fn tiles_match_a_single_texture() {
+    let effects = [
+        "bloom:threshold=0.5,radius=4",
+        "chromatic-aberration:amount=2",
+        "crt:curvature=0.05",
+        "vignette",
+        "grain",
+    ];
+    let project = Project {
+        tile_size: 48,
+        effects: effects
+            .iter()
+            .map(|spec| Effect::parse(spec).unwrap())
+            .collect(),
+        ..Default::default()
+    };
+    assert!(project.apron(RESOLUTION) > 0);
+
+    let single = common::render(
+        &project.scene().unwrap(),
+        RESOLUTION,
+        project.time,
+        &software(),
+    );
+    assert_identical(&tiled(&project), &single.into_raw());
+}
+
+fn assert_identical(tiled: &[u8], single: &[u8]) {
+    let different = tiled
+        .chunks_exact(4)
+        .zip(single.chunks_exact(4))
+        .enumerate()
+        .filter(|(_, (a, b))| a != b)
+        .map(|(i, _)| [i as u32 % RESOLUTION[0], i as u32 / RESOLUTION[0]])
+        .collect::<Vec<_>>();
+    assert!(
+        different.is_empty(),
+        "{} pixels differ, like {:?}",
+        different.len(),
+        &different[..different.len().min(20)]
+    );
+}
+
+#[test]
+fn tiles_smaller_than_the_apron_are_rejected() {
+    let project = Project {
+        tile_size: 16,
+        effects: vec![Effect::parse("bloom:radius=8").unwrap()],
+        ..Default::default()
+    };
+    assert!(project.tile(RESOLUTION, 8192).is_err());
+
+    // A single tile has no apron
+    assert!(project.tile([16, 16], 8192).is_ok());
+}
+
+#[test]
+fn supersampled_tiles_fit_in_the_textures() {
+    let project = Project {
+        ssaa: 4,
+        ..Default::default()
+    };
+    assert_eq!(project.tile([16384, 16384], 8192).unwrap(), [2048, 2048]);
+    assert_eq!(project.tile([16384, 16384], 4096).unwrap(), [1024, 1024]);
+
+    // The apron is taken from the clamped tiles
+    let project = Project {
+        ssaa: 4,
+        tile_size: 4096,
+        effects: vec![Effect::parse("bloom:radius=100").unwrap()],
+        ..Default::default()
+    };
+    assert!(project.tile([16384, 16384], 8192).is_ok());
+    assert!(project.tile([16384, 16384], 512).is_err());
+}